@@ -0,0 +1,9 @@
+//! Sección transversal de la carretera: plantillas de carriles,
+//! arcenes y medianas, y su aplicación a lo largo del eje
+
+pub mod template;
+pub mod corridor;
+pub mod widening;
+pub mod lane;
+pub mod footprint;
+pub mod sideslope;
@@ -0,0 +1,383 @@
+//! Corredor: el eje en planta con su alzado, su peralte y las
+//! plantillas de sección transversal asignadas por tramos de PK.
+//!
+//! It gives the position of every break point of the cross section at
+//! any station. Offsets are positive to the right of the axis and
+//! heights are relative to the axis. With profile and terrain the side
+//! slopes of the templates are extended to their catch points
+use crate::geom::point::Point;
+use crate::geom::point3d::Point3D;
+use crate::road::roadaxis::RoadAxis;
+use crate::road::verticalalignment::VerticalAlignment;
+use crate::road::superelevation::Superelevation;
+use super::template::{Template, TemplateElement, ElementKind, SlopeRule};
+use super::widening::Widening;
+use super::sideslope::{daylight, SideSlope, SlopeKind, CatchFailure};
+use crate::terrain::TerrainModel;
+use crate::terrain::groundsection::ground_section;
+
+/// Sampling interval of the ground line for the side slopes on smooth
+/// terrain models
+const SLOPE_GROUND_INTERVAL: f64 = 0.5;
+
+/// Template applied between two stations
+#[derive(Debug, Clone)]
+pub struct TemplateRange {
+   pub start_station: f64,
+   pub end_station: f64,
+   pub template: Template
+}
+
+/// Break point of the cross section, at the outer edge of an element
+#[derive(Debug, Clone, Copy)]
+pub struct BreakPoint {
+   pub kind: ElementKind,
+   /// Offset from the axis, negative on the left side
+   pub offset: f64,
+   /// Height over the axis
+   pub height: f64,
+   pub point: Point,
+   /// Point with elevation, if the corridor has profile
+   pub point3d: Option<Point3D>
+}
+
+/// Break points of the cross section at a station, from the axis outward
+#[derive(Debug, Clone)]
+pub struct CrossSection {
+   pub station: f64,
+   /// Elevation of the axis, if the corridor has profile
+   pub axis_elevation: Option<f64>,
+   pub left: Vec<BreakPoint>,
+   pub right: Vec<BreakPoint>,
+   /// Result of the search of the catch point of the side slope of each
+   /// side. None without side slope, terrain or profile
+   pub left_catch: Option<Result<SlopeKind, CatchFailure>>,
+   pub right_catch: Option<Result<SlopeKind, CatchFailure>>
+}
+
+impl CrossSection {
+   /// Failure of the side slope of the left side, or else of the right
+   /// side. If there is one the section ends at the platform on that side
+   pub fn catch_failure(&self) -> Option<CatchFailure> {
+      [self.left_catch, self.right_catch].into_iter().flatten().find_map(|c| c.err())
+   }
+   /// Outer break point of each side (left, right)
+   pub fn edges(&self) -> (Option<&BreakPoint>, Option<&BreakPoint>) {
+      (self.left.last(), self.right.last())
+   }
+   /// Design line as (offset, elevation) pairs from left to right. None
+   /// if the corridor has no profile
+   pub fn design_line(&self) -> Option<Vec<(f64, f64)>> {
+      let z = self.axis_elevation?;
+      let mut line: Vec<(f64, f64)> = self.left.iter().rev().map(|b| (b.offset, z + b.height)).collect();
+      line.push((0.0, z));
+      line.extend(self.right.iter().map(|b| (b.offset, z + b.height)));
+      Some(line)
+   }
+}
+
+pub struct Corridor {
+   pub axis: RoadAxis,
+   pub profile: Option<VerticalAlignment>,
+   pub superelevation: Option<Superelevation>,
+   /// Widening of the outer lane of each side on small radius curves
+   pub widening: Option<Widening>,
+   /// Ground where the side slopes end
+   pub terrain: Option<Box<dyn TerrainModel>>,
+   pub templates: Vec<TemplateRange>
+}
+
+impl Corridor {
+   pub fn new(axis: RoadAxis) -> Self {
+      Corridor{axis, profile: None, superelevation: None, widening: None, terrain: None, 
+         templates: Vec::new()}
+   }
+   /// Sets the profile of the corridor. Returns Err if it doesn't cover
+   /// the stations of the axis, within the linear tolerance of the axis
+   pub fn set_profile(&mut self, profile: VerticalAlignment) -> Result<(), String> {
+      let tol = &self.axis.tolerances;
+      if profile.sections.is_empty() {
+         return Err(String::from("Vertical alignment without sections"));
+      }
+      if profile.start_station() > self.axis.start_station + tol.linear 
+         || profile.end_station() < self.axis.end_station() - tol.linear {
+         return Err(format!("Vertical alignment from {:.3} to {:.3} doesn't cover the axis from {:.3} to {:.3}",
+            profile.start_station(), profile.end_station(), self.axis.start_station, self.axis.end_station()));
+      }
+      self.profile = Some(profile);
+      Ok(())
+   }
+   /// Assigns the template between two stations of the axis. It panics
+   /// if the range is out of the axis or overlaps another range
+   pub fn assign_template(&mut self, start_station: f64, end_station: f64, template: Template) {
+      if end_station <= start_station || !self.axis.contains_station(start_station) 
+         || !self.axis.contains_station(end_station) {
+         panic!("Template range out of the axis");
+      }
+      let tol = self.axis.tolerances.linear;
+      if self.templates.iter().any(|r| start_station < r.end_station - tol && end_station > r.start_station + tol) {
+         panic!("Template range overlaps another range");
+      }
+      self.templates.push(TemplateRange{start_station, end_station, template});
+      self.templates.sort_by(|a, b| a.start_station.total_cmp(&b.start_station));
+   }
+   /// Template at the station. In the common station of two ranges
+   /// the second one is returned
+   pub fn template_at(&self, station: f64) -> Option<&Template> {
+      let tol = self.axis.tolerances.linear;
+      self.templates.iter().rev()
+         .find(|r| station > r.start_station - tol && station < r.end_station + tol)
+         .map(|r| &r.template)
+   }
+   /// Cross slopes of the pavement (left, right) at the station
+   pub fn pavement_slopes_at(&self, station: f64, template: &Template) -> (f64, f64) {
+      match &self.superelevation {
+         Some(se) => se.cross_slopes_at(station),
+         None => (-template.crown, -template.crown)
+      }
+   }
+   /// Elevation of the axis at the station. None without profile or out
+   /// of it
+   pub fn axis_elevation_at(&self, station: f64) -> Option<f64> {
+      let p = self.profile.as_ref()?;
+      if !p.contains_station(station) {
+         return None;
+      }
+      Some(p.elevation_at(station.clamp(p.start_station(), p.end_station())))
+   }
+   /// Cross section at the station. None if the station has no template
+   /// or the corridor has profile and the station is out of it
+   pub fn cross_section_at(&self, station: f64) -> Option<CrossSection> {
+      let template = self.template_at(station)?;
+      if self.profile.as_ref().is_some_and(|p| !p.contains_station(station)) {
+         return None;
+      }
+      Some(self.cross_section_with(station, template))
+   }
+   /// Cross section at the station with the template, that may be 
+   /// other than the one of the station, as at the ends of the ranges.
+   /// Out of the profile the section has no elevations
+   pub fn cross_section_with(&self, station: f64, template: &Template) -> CrossSection {
+      let (left_slope, right_slope) = self.pavement_slopes_at(station, template);
+      let axis_elevation = self.axis_elevation_at(station);
+      let (left_widening, right_widening) = match &self.widening {
+         Some(w) => w.widening_at(station),
+         None => (0.0, 0.0)
+      };
+      let side = |elements: &[TemplateElement], pavement: f64, widening: f64, sign: f64| {
+         // The widening is added to the outer lane
+         let outer_lane = elements.iter().rposition(|e| e.kind == ElementKind::Lane);
+         let mut offset = 0.0;
+         let mut height = 0.0;
+         let mut points = Vec::<BreakPoint>::new();
+         for (i, e) in elements.iter().enumerate() {
+            let slope = match e.slope {
+               SlopeRule::Pavement => pavement,
+               SlopeRule::Fixed(s) => s
+            };
+            let width = if Some(i) == outer_lane { e.width + widening } else { e.width };
+            offset += width;
+            height += width * slope;
+            let point = self.axis.offset_point_at_station(station, sign * offset);
+            let point3d = axis_elevation.map(|z| Point3D::from_point(point, z + height));
+            points.push(BreakPoint{kind: e.kind, offset: sign * offset, height, point, point3d});
+         }
+         points
+      };
+      let mut left = side(&template.left, left_slope, left_widening, -1.0);
+      let mut right = side(&template.right, right_slope, right_widening, 1.0);
+      let (mut left_catch, mut right_catch) = (None, None);
+      if let (Some(z), Some(terrain)) = (axis_elevation, &self.terrain) {
+         if let Some(rule) = &template.left_side_slope {
+            left_catch = Some(self.add_side_slope(station, z, terrain.as_ref(), rule, &mut left, -1.0));
+         }
+         if let Some(rule) = &template.right_side_slope {
+            right_catch = Some(self.add_side_slope(station, z, terrain.as_ref(), rule, &mut right, 1.0));
+         }
+      }
+      CrossSection{station, axis_elevation, left, right, left_catch, right_catch}
+   }
+   /// Adds to the break points of the side these of the side slope down
+   /// or up to the catch point. Nothing is added if there isn't ground
+   /// at the edge or the slope doesn't reach the ground within the 
+   /// maximum width, and the failure is returned
+   fn add_side_slope(&self, station: f64, axis_elevation: f64, terrain: &dyn TerrainModel, 
+      rule: &SideSlope, points: &mut Vec<BreakPoint>, sign: f64) -> Result<SlopeKind, CatchFailure> {
+      let (edge_offset, edge_height) = points.last().map(|b| (b.offset.abs(), b.height)).unwrap_or((0.0, 0.0));
+      let width = edge_offset + rule.max_width;
+      let (left_width, right_width) = if sign < 0.0 { (width, 0.0) } else { (0.0, width) };
+      let section = ground_section(&self.axis, terrain, station, left_width, right_width, 
+         SLOPE_GROUND_INTERVAL).map_err(|_| CatchFailure::NoGround)?;
+      // Ground as distances from the edge outward
+      let mut ground: Vec<(f64, f64)> = section.pairs().iter()
+         .map(|&(offset, z)| (sign * offset - edge_offset, z)).collect();
+      ground.sort_by(|a, b| a.0.total_cmp(&b.0));
+      let (kind, slope) = daylight(axis_elevation + edge_height, rule, &ground)?;
+      for p in slope {
+         let offset = sign * (edge_offset + p.distance);
+         let point = self.axis.offset_point_at_station(station, offset);
+         points.push(BreakPoint{kind: p.kind, offset, height: p.elevation - axis_elevation, point, 
+            point3d: Some(Point3D::from_point(point, p.elevation))});
+      }
+      Ok(kind)
+   }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+   use super::*;
+   use crate::eq001;
+   use crate::road::roadaxis::tests::sample_axis;
+   use crate::road::verticalalignment::{Pvi, PviCurve};
+   use crate::road::superelevation::SlopeTransition;
+   use crate::terrain::tin::Tin;
+
+   /// Sample axis with a two lanes road, 3.5 m lanes and 1.5 m 
+   /// shoulders at 4%, along the whole axis
+   pub(crate) fn sample_corridor() -> Corridor {
+      let mut corridor = Corridor::new(sample_axis());
+      let mut template = Template::new(0.02);
+      for side in [&mut template.left, &mut template.right] {
+         side.push(TemplateElement::new(ElementKind::Lane, 3.5, SlopeRule::Pavement));
+         side.push(TemplateElement::new(ElementKind::Shoulder, 1.5, SlopeRule::Fixed(-0.04)));
+      }
+      corridor.assign_template(100.0, 520.0, template);
+      corridor
+   }
+
+   #[test]
+   fn test_cross_section_at() {
+      let corridor = sample_corridor();
+      let cs = corridor.cross_section_at(150.0).unwrap();
+      assert!(cs.axis_elevation.is_none());
+      assert_eq!(cs.right.len(), 2);
+      let lane = cs.right[0];
+      assert!(eq001(lane.offset, 3.5));
+      assert!(eq001(lane.height, -0.07));
+      assert!(eq001(lane.point.x, 1003.5));
+      let (left, right) = cs.edges();
+      assert!(eq001(left.unwrap().offset, -5.0));
+      assert!(eq001(left.unwrap().point.x, 995.0));
+      assert!(eq001(right.unwrap().height, -0.07 - 0.06));
+      assert!(corridor.cross_section_at(600.0).is_none());
+      assert!(cs.design_line().is_none());
+   }
+   #[test]
+   fn test_cross_section_3d() {
+      let mut corridor = sample_corridor();
+      let pvis = [Pvi::new(100.0, 500.0, PviCurve::None), Pvi::new(520.0, 504.2, PviCurve::None)];
+      corridor.set_profile(VerticalAlignment::from_pvis(&pvis).unwrap()).unwrap();
+      let mut se = Superelevation::new();
+      se.add_key(100.0, 0.05, -0.05, SlopeTransition::Linear);
+      corridor.superelevation = Some(se);
+      let cs = corridor.cross_section_at(200.0).unwrap();
+      assert!(eq001(cs.axis_elevation.unwrap(), 501.0));
+      let p = cs.left[0].point3d.unwrap();
+      assert!(eq001(p.z, 501.0 + 3.5 * 0.05));
+      let p = cs.right[1].point3d.unwrap();
+      assert!(eq001(p.z, 501.0 - 3.5 * 0.05 - 1.5 * 0.04));
+      let line = cs.design_line().unwrap();
+      assert_eq!(line.len(), 5);
+      assert!(eq001(line[0].0, -5.0) && eq001(line[0].1, 501.0 + 3.5 * 0.05 - 1.5 * 0.04));
+      assert!(eq001(line[2].0, 0.0) && eq001(line[2].1, 501.0));
+   }
+   #[test]
+   fn test_short_profile() {
+      let mut corridor = sample_corridor();
+      let pvis = [Pvi::new(100.0, 500.0, PviCurve::None), Pvi::new(400.0, 503.0, PviCurve::None)];
+      let profile = VerticalAlignment::from_pvis(&pvis).unwrap();
+      assert!(corridor.set_profile(profile).is_err());
+      assert!(corridor.profile.is_none());
+      // Assigned directly, there aren't sections out of the profile
+      corridor.profile = Some(VerticalAlignment::from_pvis(&pvis).unwrap());
+      assert!(eq001(corridor.cross_section_at(400.0).unwrap().axis_elevation.unwrap(), 503.0));
+      assert!(corridor.cross_section_at(450.0).is_none());
+      assert!(corridor.axis_elevation_at(450.0).is_none());
+      let template = &corridor.templates[0].template;
+      assert!(corridor.cross_section_with(450.0, template).design_line().is_none());
+   }
+   #[test]
+   fn test_side_slopes() {
+      use crate::crosssection::sideslope::Ditch;
+      let mut corridor = sample_corridor();
+      let mut template = corridor.templates[0].template.clone();
+      let mut rule = SideSlope::new(1.0, 2.0);
+      rule.ditch = Some(Ditch{foreslope: 2.0, depth: 0.5, bottom_width: 1.0});
+      template.set_side_slopes(rule);
+      corridor.templates[0].template = template;
+      let pvis = [Pvi::new(100.0, 101.0, PviCurve::None), Pvi::new(520.0, 101.0, PviCurve::None)];
+      corridor.set_profile(VerticalAlignment::from_pvis(&pvis).unwrap()).unwrap();
+      // Without terrain the section ends at the platform
+      assert_eq!(corridor.cross_section_at(150.0).unwrap().right.len(), 2);
+      // Ground at 100 on the left of x = 1000 and at 102 on the right
+      let mut points = Vec::<Point3D>::new();
+      for i in 0..=20 {
+         for j in 0..=10 {
+            let x = 950.0 + 5.0 * i as f64;
+            let z = if x < 1000.0 { 100.0 } else if x > 1000.0 { 102.0 } else { 101.0 };
+            points.push(Point3D::new(x, 1000.0 + 20.0 * j as f64, z));
+         }
+      }
+      corridor.terrain = Some(Box::new(Tin::new(&points).unwrap()));
+      let cs = corridor.cross_section_at(150.0).unwrap();
+      // Fill on the left: edge at 101 - 0.13, slope 2H:1V down to 100
+      let catch = cs.left.last().unwrap();
+      assert_eq!(catch.kind, ElementKind::FillSlope);
+      assert!(eq001(catch.offset, -5.0 - 2.0 * 0.87));
+      assert!(eq001(catch.point3d.unwrap().z, 100.0));
+      // Cut on the right: ditch 0.5 m deep and 1H:1V up to 102
+      assert_eq!(cs.right.len(), 5);
+      assert_eq!(cs.right[2].kind, ElementKind::Ditch);
+      let catch = cs.right[4];
+      assert_eq!(catch.kind, ElementKind::CutSlope);
+      assert!(eq001(catch.offset, 5.0 + 1.0 + 1.0 + (102.0 - 100.37)));
+      assert!(eq001(catch.height, 1.0));
+      assert!(eq001(catch.point.x, 1000.0 + catch.offset));
+      assert_eq!(cs.left_catch, Some(Ok(SlopeKind::Fill)));
+      assert_eq!(cs.right_catch, Some(Ok(SlopeKind::Cut)));
+      assert!(cs.catch_failure().is_none());
+      // Fill slope wider than the maximum width: the left side ends at
+      // the platform and the failure is reported
+      let mut template = corridor.templates[0].template.clone();
+      template.left_side_slope.as_mut().unwrap().max_width = 1.0;
+      corridor.templates[0].template = template;
+      let cs = corridor.cross_section_at(150.0).unwrap();
+      assert_eq!(cs.left.len(), 2);
+      assert_eq!(cs.left_catch, Some(Err(CatchFailure::OutOfWidth)));
+      assert_eq!(cs.catch_failure(), Some(CatchFailure::OutOfWidth));
+      assert!(crate::crosssection::footprint::footprint(&corridor, 10.0).is_err());
+      let terrain = corridor.terrain.as_deref().unwrap();
+      assert!(crate::earthwork::volumes::corridor_section_areas(&corridor, terrain, 150.0).is_err());
+      // Out of the terrain
+      let cs = corridor.cross_section_at(500.0).unwrap();
+      assert_eq!(cs.right_catch, Some(Err(CatchFailure::NoGround)));
+   }
+   #[test]
+   fn test_widening() {
+      let mut corridor = sample_corridor();
+      let design = crate::crosssection::widening::design_widening(&corridor.axis, 2,
+         &crate::crosssection::widening::DesignVehicle::default(), 250.0, 30.0);
+      corridor.widening = Some(design.widening);
+      // Right curve: the right lane is widened, the left one isn't
+      let cs = corridor.cross_section_at(300.0).unwrap();
+      assert!(eq001(cs.right[0].offset, 3.5 + 0.405));
+      assert!(eq001(cs.right[1].offset, 5.0 + 0.405));
+      assert!(eq001(cs.right[0].height, -0.02 * 3.905));
+      assert!(eq001(cs.left[1].offset, -5.0));
+   }
+   #[test]
+   fn test_template_ranges() {
+      let mut corridor = Corridor::new(sample_axis());
+      corridor.assign_template(300.0, 520.0, Template::from_inventory(2, 7.0, 1.0, 1.0));
+      corridor.assign_template(100.0, 300.0, Template::from_inventory(2, 6.0, 0.5, 0.5));
+      assert!(eq001(corridor.template_at(200.0).unwrap().width(), 7.0));
+      assert!(eq001(corridor.template_at(300.0).unwrap().width(), 9.0));
+      assert!(eq001(corridor.template_at(400.0).unwrap().width(), 9.0));
+   }
+   #[test]
+   #[should_panic]
+   fn test_assign_template_panic() {
+      let mut corridor = sample_corridor();
+      corridor.assign_template(200.0, 300.0, Template::new(0.02));
+   }
+}
@@ -0,0 +1,204 @@
+//! Huella de la carretera: el contorno exterior de la ocupación.
+//!
+//! The outline joins the outer points of the cross sections of both
+//! sides along each stretch of the corridor with templates, and the
+//! footprint is the region where its winding number isn't zero. On the
+//! inner side of tight curves the offset line loops back on itself and
+//! these loops, covered twice, are removed, so the polygons are always
+//! simple. Where the axis crosses itself, as in a loop ramp, the area
+//! enclosed by the loop is a hole, and a closed axis (a roundabout)
+//! gives a ring: the outer edge with the inner edge as a hole
+use crate::geom::point::Point;
+use crate::geom::polygon::{Polygon, nonzero_regions};
+use super::corridor::{Corridor, TemplateRange};
+
+/// Connected part of the footprint
+#[derive(Debug, Clone)]
+pub struct FootprintPart {
+   pub outer: Polygon,
+   pub holes: Vec<Polygon>
+}
+
+impl FootprintPart {
+   pub fn area(&self) -> f64 {
+      self.outer.area() - self.holes.iter().map(|h| h.area()).sum::<f64>()
+   }
+}
+
+#[derive(Debug, Clone)]
+pub struct Footprint {
+   pub parts: Vec<FootprintPart>
+}
+
+impl Footprint {
+   pub fn area(&self) -> f64 {
+      self.parts.iter().map(|p| p.area()).sum()
+   }
+}
+
+/// Footprint of the corridor, with the cross sections at the stations
+/// multiple of interval and at the ends of the sections and templates.
+/// Err if a side slope doesn't reach the ground
+pub fn footprint(corridor: &Corridor, interval: f64) -> Result<Footprint, String> {
+   if corridor.templates.is_empty() {
+      return Err(String::from("Corridor without templates"));
+   }
+   let axis = &corridor.axis;
+   let tol = &axis.tolerances;
+   let all_stations = axis.stations_at_interval(interval)?;
+   // Stretches of consecutive template ranges
+   let mut ranges: Vec<&TemplateRange> = corridor.templates.iter().collect();
+   ranges.sort_by(|a, b| a.start_station.total_cmp(&b.start_station));
+   let mut stretches = Vec::<Vec<&TemplateRange>>::new();
+   for range in ranges {
+      match stretches.last_mut() {
+         Some(last) if tol.eq_linear(last[last.len() - 1].end_station, range.start_station) => last.push(range),
+         _ => stretches.push(vec![range])
+      }
+   }
+   let closed_axis = axis.is_closed();
+   let mut parts = Vec::<FootprintPart>::new();
+   for stretch in stretches {
+      let start = stretch[0].start_station;
+      let end = stretch[stretch.len() - 1].end_station;
+      // Both cross sections are taken at the common station of two
+      // ranges, with a step if the templates are different
+      let mut left = Vec::<Point>::new();
+      let mut right = Vec::<Point>::new();
+      for range in stretch {
+         let mut stations: Vec<f64> = all_stations.iter().cloned()
+            .filter(|s| *s > range.start_station && *s < range.end_station).collect();
+         stations.insert(0, range.start_station);
+         stations.push(range.end_station);
+         for station in stations {
+            let cs = corridor.cross_section_with(station, &range.template);
+            if let Some(failure) = cs.catch_failure() {
+               return Err(format!("Side slope without catch point at station {:.3}: {}", station, failure));
+            }
+            let (l, r) = cs.edges();
+            let axis_point = axis.point_at_station(station);
+            left.push(l.map(|b| b.point).unwrap_or(axis_point));
+            right.push(r.map(|b| b.point).unwrap_or(axis_point));
+         }
+      }
+      let whole_axis = tol.eq_linear(start, axis.start_station) && tol.eq_linear(end, axis.end_station());
+      right.reverse();
+      let polygons = if closed_axis && whole_axis {
+         left.pop();
+         right.remove(0);
+         vec![Polygon::new(left), Polygon::new(right)]
+      } else {
+         left.extend(right);
+         vec![Polygon::new(left)]
+      };
+      parts.extend(nonzero_regions(&polygons).into_iter()
+         .map(|r| FootprintPart{outer: r.outer, holes: r.holes}));
+   }
+   Ok(Footprint{parts})
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use crate::crosssection::corridor::tests::sample_corridor;
+   use crate::crosssection::template::{Template, TemplateElement, ElementKind, SlopeRule};
+   use crate::road::roadaxis::RoadAxis;
+   use crate::road::htangent::HTangent;
+   use crate::road::hcircle::HCircle;
+   use crate::road::hsection::HSection;
+
+   fn symmetric_template(width: f64) -> Template {
+      let mut template = Template::new(0.02);
+      template.add_left(TemplateElement::new(ElementKind::Lane, width, SlopeRule::Pavement));
+      template.add_right(TemplateElement::new(ElementKind::Lane, width, SlopeRule::Pavement));
+      template
+   }
+
+   #[test]
+   fn test_footprint() {
+      let corridor = sample_corridor();
+      let fp = footprint(&corridor, 10.0).unwrap();
+      assert_eq!(fp.parts.len(), 1);
+      assert!(fp.parts[0].holes.is_empty());
+      assert!(fp.parts[0].outer.is_simple());
+      // 10 m wide along 420 m: the outer side gains what the inner loses
+      assert!((fp.area() - 4200.0).abs() < 4.2);
+      assert!(footprint(&Corridor::new(corridor.axis), 10.0).is_err());
+   }
+   #[test]
+   fn test_footprint_stretches() {
+      let mut corridor = Corridor::new(sample_corridor().axis);
+      corridor.assign_template(100.0, 150.0, symmetric_template(3.0));
+      corridor.assign_template(150.0, 200.0, symmetric_template(4.0));
+      corridor.assign_template(300.0, 400.0, symmetric_template(3.0));
+      let fp = footprint(&corridor, 10.0).unwrap();
+      assert_eq!(fp.parts.len(), 2);
+      assert!((fp.parts[0].area() - (50.0 * 6.0 + 50.0 * 8.0)).abs() < 0.5);
+   }
+   #[test]
+   fn test_footprint_tight_curve() {
+      // Half turn of R = 10 with 15 m on the inner side
+      let mut axis = RoadAxis::new();
+      let t1 = HTangent::new(Point::new(0.0, 0.0), 0.0, 50.0);
+      let c = HCircle::new(t1.end_point(), 0.0, 10.0, std::f64::consts::PI * 10.0);
+      let t2 = HTangent::new(c.end_point(), c.end_azimuth(), 50.0);
+      axis.add_htangent(t1);
+      axis.add_hcircle(c);
+      axis.add_htangent(t2);
+      let end = axis.end_station();
+      let mut corridor = Corridor::new(axis);
+      let mut template = Template::new(0.02);
+      template.add_left(TemplateElement::new(ElementKind::Lane, 3.5, SlopeRule::Pavement));
+      template.add_right(TemplateElement::new(ElementKind::Lane, 15.0, SlopeRule::Pavement));
+      corridor.assign_template(0.0, end, template);
+      let fp = footprint(&corridor, 2.0).unwrap();
+      let outer = &fp.parts[0].outer;
+      assert!(outer.is_simple());
+      assert!(outer.contains(Point::new(0.0, 25.0)));
+      assert!(outer.contains(Point::new(10.0, 55.0)));
+   }
+   #[test]
+   fn test_footprint_crossing_axis() {
+      // Tangent toward the East, loop of R = 20 turning 300 degrees to
+      // the left and tangent that crosses the first one at 60 degrees
+      let mut axis = RoadAxis::new();
+      let t1 = HTangent::new(Point::new(0.0, 0.0), 90.0, 100.0);
+      let c = HCircle::new(t1.end_point(), 90.0, -20.0, 300.0_f64.to_radians() * 20.0);
+      let t2 = HTangent::new(c.end_point(), c.end_azimuth(), 100.0);
+      axis.add_htangent(t1);
+      axis.add_hcircle(c);
+      axis.add_htangent(t2);
+      let length = axis.length();
+      let end = axis.end_station();
+      let mut corridor = Corridor::new(axis);
+      corridor.assign_template(0.0, end, symmetric_template(3.0));
+      let fp = footprint(&corridor, 1.0).unwrap();
+      assert_eq!(fp.parts.len(), 1);
+      let part = &fp.parts[0];
+      assert!(part.outer.is_simple());
+      // The area enclosed by the loop is a hole
+      assert_eq!(part.holes.len(), 1);
+      assert!(part.holes[0].contains(Point::new(100.0, 20.0)));
+      let hole = part.holes[0].area();
+      assert!(hole > 0.5 * std::f64::consts::PI * 17.0 * 17.0 && hole < std::f64::consts::PI * 20.0 * 20.0);
+      // The strips overlap where the axis crosses itself
+      assert!(fp.area() < 6.0 * length - 36.0 / 60.0_f64.to_radians().sin() + 1.0);
+      assert!(fp.area() > 6.0 * length - 100.0);
+   }
+   #[test]
+   fn test_footprint_closed_axis() {
+      // Roundabout of R = 50 with 5 m on each side
+      let mut axis = RoadAxis::new();
+      axis.add_hcircle(HCircle::new(Point::new(0.0, 0.0), 0.0, 50.0, 2.0 * std::f64::consts::PI * 50.0));
+      let end = axis.end_station();
+      let mut corridor = Corridor::new(axis);
+      corridor.assign_template(0.0, end, symmetric_template(5.0));
+      let fp = footprint(&corridor, 5.0).unwrap();
+      assert_eq!(fp.parts.len(), 1);
+      assert_eq!(fp.parts[0].holes.len(), 1);
+      // Island centre at (50, 0) is out of the footprint
+      assert!(fp.parts[0].holes[0].contains(Point::new(50.0, 0.0)));
+      let area = std::f64::consts::PI * (55.0 * 55.0 - 45.0 * 45.0);
+      assert!((fp.area() - area).abs() < 0.01 * area);
+   }
+}
@@ -0,0 +1,293 @@
+//! Carriles de anchura variable.
+//!
+//! Each lane has a width given by key stations, as the superelevation
+//! does with the cross slopes. Between two keys the width changes with
+//! the shape of the taper of the first key, and before the first key
+//! and after the last one it is constant, so an added lane (climbing
+//! lane, bus bay) starts and ends with keys of width zero.
+//!
+//! The lanes of each side are placed from the axis outward, and their
+//! boundary lines are offset polylines of the `RoadAxis`
+use crate::geom::point::Point;
+use crate::geom::polygon::Polygon;
+use crate::road::roadaxis::RoadAxis;
+use super::template::ElementKind;
+
+/// Number of parts in which a curved taper is divided, at least, to 
+/// compute the boundary lines
+const TAPER_PARTS: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TaperShape {
+   Linear,
+   /// Two parabolic arcs of opposite curvature, tangent to both widths
+   ReverseCurve,
+   /// Cosine curve (1 - cos(pi t)) / 2
+   SShaped
+}
+
+impl TaperShape {
+   /// Fraction of the change of width at parameter t of the taper
+   pub fn shape(&self, t: f64) -> f64 {
+      match self {
+         TaperShape::Linear => t,
+         TaperShape::ReverseCurve => if t < 0.5 { 2.0 * t * t } else { 1.0 - 2.0 * (1.0 - t) * (1.0 - t) },
+         TaperShape::SShaped => (1.0 - (std::f64::consts::PI * t).cos()) / 2.0
+      }
+   }
+}
+
+/// Width of a lane at a station. The shape is used between this key
+/// and the next one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WidthKey {
+   pub station: f64,
+   pub width: f64,
+   pub shape: TaperShape
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lane {
+   pub kind: ElementKind,
+   pub keys: Vec<WidthKey>
+}
+
+impl Lane {
+   pub fn new(kind: ElementKind) -> Self {
+      Lane{kind, keys: Vec::new()}
+   }
+   /// Lane of constant width along the whole axis
+   pub fn constant(kind: ElementKind, width: f64) -> Self {
+      let mut lane = Lane::new(kind);
+      lane.add_key(0.0, width, TaperShape::Linear);
+      lane
+   }
+   /// Adds a key. Stations must be added in increasing order and widths
+   /// can't be negative
+   pub fn add_key(&mut self, station: f64, width: f64, shape: TaperShape) {
+      if width < 0.0 {
+         panic!("Width of lane can't be negative");
+      }
+      if let Some(last) = self.keys.last() {
+         if station <= last.station {
+            panic!("Width keys must be added in increasing station order");
+         }
+      }
+      self.keys.push(WidthKey{station, width, shape});
+   }
+   /// Width of the lane at the station
+   pub fn width_at(&self, station: f64) -> f64 {
+      if self.keys.is_empty() {
+         return 0.0;
+      }
+      for w in self.keys.windows(2) {
+         let (k0, k1) = (w[0], w[1]);
+         if station >= k0.station && station <= k1.station {
+            let t = (station - k0.station) / (k1.station - k0.station);
+            return k0.width + (k1.width - k0.width) * k0.shape.shape(t);
+         }
+      }
+      if station < self.keys[0].station { self.keys[0].width } else { self.keys[self.keys.len() - 1].width }
+   }
+   /// Stations of the first and last keys. None if the lane has less
+   /// than two keys, and then it has constant width
+   pub fn range(&self) -> Option<(f64, f64)> {
+      if self.keys.len() < 2 {
+         return None;
+      }
+      Some((self.keys[0].station, self.keys[self.keys.len() - 1].station))
+   }
+   /// Stations of the keys and of the inner points of the curved tapers
+   fn key_stations(&self) -> Vec<f64> {
+      let mut stations: Vec<f64> = self.keys.iter().map(|k| k.station).collect();
+      for w in self.keys.windows(2) {
+         if w[0].shape != TaperShape::Linear && w[0].width != w[1].width {
+            let step = (w[1].station - w[0].station) / TAPER_PARTS as f64;
+            stations.extend((1..TAPER_PARTS).map(|i| w[0].station + step * i as f64));
+         }
+      }
+      stations
+   }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Side {
+   Left,
+   Right
+}
+
+impl Side {
+   /// Sign of the offsets of the side
+   pub fn sign(&self) -> f64 {
+      match self {
+         Side::Left => -1.0,
+         Side::Right => 1.0
+      }
+   }
+}
+
+/// Lanes of both sides of the axis, from the axis outward
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LaneModel {
+   pub left: Vec<Lane>,
+   pub right: Vec<Lane>
+}
+
+impl LaneModel {
+   pub fn new() -> Self {
+      LaneModel::default()
+   }
+   pub fn add_left(&mut self, lane: Lane) {
+      self.left.push(lane);
+   }
+   pub fn add_right(&mut self, lane: Lane) {
+      self.right.push(lane);
+   }
+   pub fn lanes(&self, side: Side) -> &Vec<Lane> {
+      match side {
+         Side::Left => &self.left,
+         Side::Right => &self.right
+      }
+   }
+   /// Signed offsets of the boundary lines of the side at the station:
+   /// the axis and the outer edge of each lane
+   pub fn offsets_at(&self, side: Side, station: f64) -> Vec<f64> {
+      let mut offsets = vec![0.0];
+      let mut offset = 0.0;
+      for lane in self.lanes(side) {
+         offset += lane.width_at(station);
+         offsets.push(side.sign() * offset);
+      }
+      offsets
+   }
+   /// Stations of the boundary lines of the side between start and end:
+   /// these of the axis at the interval and the keys of the lanes
+   fn stations(&self, axis: &RoadAxis, side: Side, start: f64, end: f64, 
+      interval: f64) -> Result<Vec<f64>, String> {
+      let mut stations = axis.stations_at_interval(interval)?;
+      for lane in self.lanes(side) {
+         stations.extend(lane.key_stations());
+      }
+      stations.push(start);
+      stations.push(end);
+      stations.retain(|s| *s >= start && *s <= end);
+      stations.sort_by(|a, b| a.total_cmp(b));
+      stations.dedup_by(|a, b| axis.tolerances.eq_linear(*a, *b));
+      Ok(stations)
+   }
+   /// Boundary line of the side along the whole axis: 0 is the axis, 
+   /// i is the outer edge of the lane i-1
+   pub fn boundary(&self, axis: &RoadAxis, side: Side, index: usize, 
+      interval: f64) -> Result<Vec<Point>, String> {
+      self.boundary_between(axis, side, index, axis.start_station, axis.end_station(), interval)
+   }
+   fn boundary_between(&self, axis: &RoadAxis, side: Side, index: usize, start: f64, end: f64,
+      interval: f64) -> Result<Vec<Point>, String> {
+      if index > self.lanes(side).len() {
+         return Err(format!("There isn't boundary line {}", index));
+      }
+      let stations = self.stations(axis, side, start, end, interval)?;
+      Ok(stations.iter().map(|s| axis.offset_point_at_station(*s, self.offsets_at(side, *s)[index])).collect())
+   }
+   /// Polygon of the lane, between its first and last keys or along the
+   /// whole axis if it has constant width
+   pub fn lane_polygon(&self, axis: &RoadAxis, side: Side, index: usize, 
+      interval: f64) -> Result<Polygon, String> {
+      let lane = self.lanes(side).get(index).ok_or(format!("There isn't lane {}", index))?;
+      let (start, end) = match lane.range() {
+         Some((s, e)) => (s.max(axis.start_station), e.min(axis.end_station())),
+         None => (axis.start_station, axis.end_station())
+      };
+      if start >= end {
+         return Err(format!("Lane {} is out of the axis", index));
+      }
+      let mut vertices = self.boundary_between(axis, side, index, start, end, interval)?;
+      let mut outer = self.boundary_between(axis, side, index + 1, start, end, interval)?;
+      outer.reverse();
+      vertices.extend(outer);
+      Ok(Polygon::new(vertices))
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use crate::{eq, eq001};
+   use crate::road::roadaxis::tests::sample_axis;
+
+   /// Right lane of 3.5 m and a climbing lane on the first tangent of
+   /// the sample axis, from 120 to 200
+   fn sample_model(shape: TaperShape) -> LaneModel {
+      let mut model = LaneModel::new();
+      model.add_left(Lane::constant(ElementKind::Lane, 3.5));
+      model.add_right(Lane::constant(ElementKind::Lane, 3.5));
+      let mut climbing = Lane::new(ElementKind::Lane);
+      climbing.add_key(120.0, 0.0, TaperShape::Linear);
+      climbing.add_key(150.0, 3.5, TaperShape::Linear);
+      climbing.add_key(180.0, 3.5, shape);
+      climbing.add_key(200.0, 0.0, TaperShape::Linear);
+      model.add_right(climbing);
+      model
+   }
+
+   #[test]
+   fn test_taper_shapes() {
+      for shape in [TaperShape::Linear, TaperShape::ReverseCurve, TaperShape::SShaped] {
+         assert!(eq(shape.shape(0.0), 0.0));
+         assert!(eq(shape.shape(0.5), 0.5));
+         assert!(eq(shape.shape(1.0), 1.0));
+      }
+      assert!(eq(TaperShape::ReverseCurve.shape(0.25), 0.125));
+   }
+   #[test]
+   fn test_width_at() {
+      let model = sample_model(TaperShape::ReverseCurve);
+      let climbing = &model.right[1];
+      assert!(eq001(climbing.width_at(100.0), 0.0));
+      assert!(eq001(climbing.width_at(135.0), 1.75));
+      assert!(eq001(climbing.width_at(165.0), 3.5));
+      assert!(eq001(climbing.width_at(185.0), 3.5 * (1.0 - 0.125)));
+      assert_eq!(model.offsets_at(Side::Right, 135.0), vec![0.0, 3.5, 5.25]);
+      assert_eq!(model.offsets_at(Side::Left, 135.0), vec![0.0, -3.5]);
+   }
+   #[test]
+   #[should_panic]
+   fn test_decreasing_key() {
+      let mut lane = Lane::new(ElementKind::Lane);
+      lane.add_key(100.0, 3.5, TaperShape::Linear);
+      lane.add_key(50.0, 3.5, TaperShape::Linear);
+   }
+   #[test]
+   fn test_boundary() {
+      let axis = sample_axis();
+      let model = sample_model(TaperShape::SShaped);
+      let edge = model.boundary(&axis, Side::Right, 2, 20.0).unwrap();
+      // First tangent goes north from (1000, 1000)
+      let p = edge[0];
+      assert!(eq001(p.x, 1003.5) && eq001(p.y, 1000.0));
+      let p = edge.iter().find(|p| eq001(p.y, 1060.0)).unwrap();
+      assert!(eq001(p.x, 1007.0));
+      let p = edge.iter().find(|p| eq001(p.y, 1090.0)).unwrap();
+      assert!(eq001(p.x, 1003.5 + 3.5 * TaperShape::SShaped.shape(0.5)));
+      assert!(model.boundary(&axis, Side::Right, 3, 20.0).is_err());
+   }
+   #[test]
+   fn test_lane_polygon() {
+      let axis = sample_axis();
+      for shape in [TaperShape::Linear, TaperShape::ReverseCurve, TaperShape::SShaped] {
+         let model = sample_model(shape);
+         let polygon = model.lane_polygon(&axis, Side::Right, 1, 20.0).unwrap();
+         // Tapers of 30 and 20 m and 30 m of full width. Symmetric
+         // tapers have half the area of the rectangle
+         assert!(eq001(polygon.area(), 3.5 * (15.0 + 30.0 + 10.0)));
+         assert!(polygon.contains(Point::new(1005.0, 1060.0)));
+      }
+      // Lane of constant width along the whole axis
+      let model = sample_model(TaperShape::Linear);
+      let polygon = model.lane_polygon(&axis, Side::Left, 0, 20.0).unwrap();
+      // Outer side of the curve: a little more than width x length
+      let area = polygon.area();
+      assert!(area > 3.5 * axis.length() && area < 1.01 * 3.5 * axis.length());
+      assert!(model.lane_polygon(&axis, Side::Left, 1, 20.0).is_err());
+   }
+}
@@ -0,0 +1,267 @@
+//! Taludes, cunetas y bermas: la sección más allá de la plataforma.
+//!
+//! At each side the edge of the platform is in cut if the ground is
+//! over it and in fill otherwise. In cut the section goes down into
+//! the ditch and then up the cut slope until it meets the ground; in
+//! fill it goes down the embankment slope. Slopes are given as the
+//! horizontal run for a unit of height (2.0 is 2H:1V), and a bench of
+//! the given width is placed every time the slope gains or loses the
+//! bench height. The point where the slope meets the ground is the
+//! catch point (daylight), searched up to the maximum width of the
+//! side slope
+use std::fmt;
+use super::template::ElementKind;
+
+/// Default maximum horizontal distance from the edge of the platform to
+/// the catch point
+const MAX_SLOPE_WIDTH: f64 = 100.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SlopeKind {
+   Cut,
+   Fill
+}
+
+/// Cuneta: foreslope down from the edge of the platform and a flat
+/// bottom, only in cut
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ditch {
+   /// Horizontal run of the foreslope by unit of height
+   pub foreslope: f64,
+   pub depth: f64,
+   pub bottom_width: f64
+}
+
+/// Berma: flat bench every height of slope
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bench {
+   pub height: f64,
+   pub width: f64
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SideSlope {
+   /// Horizontal run of the cut slope by unit of height
+   pub cut_slope: f64,
+   /// Horizontal run of the fill slope by unit of height
+   pub fill_slope: f64,
+   pub ditch: Option<Ditch>,
+   pub cut_bench: Option<Bench>,
+   pub fill_bench: Option<Bench>,
+   /// Maximum horizontal distance from the edge of the platform to the
+   /// catch point
+   pub max_width: f64
+}
+
+impl SideSlope {
+   /// Side slopes without ditch and benches, with a maximum width of
+   /// 100 m. Slopes must be greater than zero
+   pub fn new(cut_slope: f64, fill_slope: f64) -> Self {
+      if cut_slope <= 0.0 || fill_slope <= 0.0 {
+         panic!("Side slopes must be greater than zero");
+      }
+      SideSlope{cut_slope, fill_slope, ditch: None, cut_bench: None, fill_bench: None, 
+         max_width: MAX_SLOPE_WIDTH}
+   }
+}
+
+/// Why a side slope has no catch point
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CatchFailure {
+   /// There isn't ground at the edge of the platform
+   NoGround,
+   /// The slope doesn't meet the ground within the maximum width
+   OutOfWidth
+}
+
+impl fmt::Display for CatchFailure {
+   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+      match self {
+         CatchFailure::NoGround => write!(f, "there isn't ground at the edge of the platform"),
+         CatchFailure::OutOfWidth => write!(f, "the side slope doesn't meet the ground within its maximum width")
+      }
+   }
+}
+
+/// Break point of the side slope, at a distance from the edge of the
+/// platform measured outward
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SlopePoint {
+   pub kind: ElementKind,
+   pub distance: f64,
+   pub elevation: f64
+}
+
+/// Ground elevation at the distance, interpolated in the ground line
+fn ground_at(ground: &[(f64, f64)], d: f64) -> Option<f64> {
+   let i = ground.iter().position(|p| p.0 >= d)?;
+   if ground[i].0 == d {
+      return Some(ground[i].1);
+   }
+   if i == 0 {
+      return None;
+   }
+   let (p0, p1) = (ground[i - 1], ground[i]);
+   Some(p0.1 + (p1.1 - p0.1) * (d - p0.0) / (p1.0 - p0.0))
+}
+
+/// First point of the segment a-b, after a, where it meets the ground
+fn ground_crossing(ground: &[(f64, f64)], a: (f64, f64), b: (f64, f64)) -> Option<(f64, f64)> {
+   let design = |d: f64| a.1 + (b.1 - a.1) * (d - a.0) / (b.0 - a.0);
+   let mut ds: Vec<f64> = ground.iter().map(|p| p.0).filter(|d| *d > a.0 && *d < b.0).collect();
+   ds.insert(0, a.0);
+   ds.push(b.0);
+   let mut previous: Option<(f64, f64)> = None;
+   for d in ds {
+      let Some(g) = ground_at(ground, d) else { break };
+      let f = design(d) - g;
+      if let Some((d0, f0)) = previous {
+         if f == 0.0 || f0 * f < 0.0 {
+            let d = d0 + (d - d0) * f0 / (f0 - f);
+            return Some((d, design(d)));
+         }
+      }
+      previous = Some((d, f));
+   }
+   None
+}
+
+/// Side slope from the edge of the platform at the elevation down or up
+/// to the ground line, given as (distance, elevation) pairs from the
+/// edge outward. The last point is the catch point. Err if the ground
+/// at the edge is unknown or the slope doesn't meet the ground line
+/// within the maximum width
+pub fn daylight(edge_elevation: f64, rule: &SideSlope, ground: &[(f64, f64)]) 
+   -> Result<(SlopeKind, Vec<SlopePoint>), CatchFailure> {
+   let g0 = ground_at(ground, 0.0).ok_or(CatchFailure::NoGround)?;
+   let max_distance = ground[ground.len() - 1].0.min(rule.max_width);
+   let kind = if g0 > edge_elevation { SlopeKind::Cut } else { SlopeKind::Fill };
+   let (slope_kind, ratio, sign, bench) = match kind {
+      SlopeKind::Cut => (ElementKind::CutSlope, rule.cut_slope, 1.0, rule.cut_bench),
+      SlopeKind::Fill => (ElementKind::FillSlope, rule.fill_slope, -1.0, rule.fill_bench)
+   };
+   let catch = |p: (f64, f64)| SlopePoint{kind: slope_kind, distance: p.0, elevation: p.1};
+   if g0 == edge_elevation {
+      return Ok((kind, vec![catch((0.0, g0))]));
+   }
+   let mut points = Vec::<SlopePoint>::new();
+   let mut current = (0.0, edge_elevation);
+   if let (SlopeKind::Cut, Some(ditch)) = (kind, rule.ditch) {
+      let bottom = (ditch.depth * ditch.foreslope, edge_elevation - ditch.depth);
+      for end in [bottom, (bottom.0 + ditch.bottom_width, bottom.1)] {
+         if let Some(p) = ground_crossing(ground, current, end) {
+            if p.0 > max_distance {
+               return Err(CatchFailure::OutOfWidth);
+            }
+            points.push(catch(p));
+            return Ok((kind, points));
+         }
+         points.push(SlopePoint{kind: ElementKind::Ditch, distance: end.0, elevation: end.1});
+         current = end;
+      }
+   }
+   loop {
+      let height = match bench {
+         Some(b) => b.height,
+         None => ((max_distance - current.0) / ratio).max(0.0) + 1.0
+      };
+      let end = (current.0 + height * ratio, current.1 + sign * height);
+      if let Some(p) = ground_crossing(ground, current, end) {
+         if p.0 > max_distance {
+            return Err(CatchFailure::OutOfWidth);
+         }
+         points.push(catch(p));
+         return Ok((kind, points));
+      }
+      if end.0 >= max_distance {
+         return Err(CatchFailure::OutOfWidth);
+      }
+      points.push(catch(end));
+      current = end;
+      if let Some(b) = bench {
+         let end = (current.0 + b.width, current.1);
+         if let Some(p) = ground_crossing(ground, current, end) {
+            if p.0 > max_distance {
+               return Err(CatchFailure::OutOfWidth);
+            }
+            points.push(SlopePoint{kind: ElementKind::Bench, distance: p.0, elevation: p.1});
+            return Ok((kind, points));
+         }
+         points.push(SlopePoint{kind: ElementKind::Bench, distance: end.0, elevation: end.1});
+         current = end;
+      }
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use crate::eq001;
+
+   fn flat(z: f64) -> Vec<(f64, f64)> {
+      vec![(0.0, z), (50.0, z)]
+   }
+
+   #[test]
+   fn test_fill() {
+      let rule = SideSlope::new(1.0, 2.0);
+      let (kind, points) = daylight(101.0, &rule, &flat(100.0)).unwrap();
+      assert_eq!(kind, SlopeKind::Fill);
+      assert_eq!(points.len(), 1);
+      assert_eq!(points[0].kind, ElementKind::FillSlope);
+      assert!(eq001(points[0].distance, 2.0));
+      assert!(eq001(points[0].elevation, 100.0));
+   }
+   #[test]
+   fn test_fill_benches() {
+      let mut rule = SideSlope::new(1.0, 2.0);
+      rule.fill_bench = Some(Bench{height: 0.5, width: 1.0});
+      // 2 m of fill: four slopes of 0.5 m and three benches
+      let (_, points) = daylight(102.0, &rule, &flat(100.0)).unwrap();
+      assert_eq!(points.len(), 7);
+      assert_eq!(points.iter().filter(|p| p.kind == ElementKind::Bench).count(), 3);
+      let last = points.last().unwrap();
+      assert!(eq001(last.distance, 7.0));
+      assert!(eq001(last.elevation, 100.0));
+   }
+   #[test]
+   fn test_cut_with_ditch() {
+      let mut rule = SideSlope::new(1.0, 2.0);
+      rule.ditch = Some(Ditch{foreslope: 2.0, depth: 0.5, bottom_width: 1.0});
+      let (kind, points) = daylight(100.0, &rule, &flat(102.0)).unwrap();
+      assert_eq!(kind, SlopeKind::Cut);
+      assert_eq!(points.len(), 3);
+      assert_eq!(points[0].kind, ElementKind::Ditch);
+      assert!(eq001(points[1].distance, 2.0) && eq001(points[1].elevation, 99.5));
+      // Up 2.5 m with 1H:1V from the bottom of the ditch
+      assert_eq!(points[2].kind, ElementKind::CutSlope);
+      assert!(eq001(points[2].distance, 4.5));
+      assert!(eq001(points[2].elevation, 102.0));
+   }
+   #[test]
+   fn test_sloping_ground() {
+      let rule = SideSlope::new(1.0, 2.0);
+      // Ground rising 1:4 from 100.5: cut slope 1:1 meets it at d/4 + 0.5 = d
+      let ground = vec![(0.0, 100.5), (40.0, 110.5)];
+      let (_, points) = daylight(100.0, &rule, &ground).unwrap();
+      assert!(eq001(points[0].distance, 0.5 / 0.75));
+      // Edge on the ground
+      let (_, points) = daylight(100.5, &rule, &ground).unwrap();
+      assert!(eq001(points[0].distance, 0.0));
+      // Ground steeper than the cut slope: no catch point
+      let ground = vec![(0.0, 101.0), (40.0, 181.0)];
+      assert_eq!(daylight(100.0, &rule, &ground).unwrap_err(), CatchFailure::OutOfWidth);
+      assert_eq!(daylight(100.0, &rule, &[]).unwrap_err(), CatchFailure::NoGround);
+      assert_eq!(daylight(100.0, &rule, &[(1.0, 100.0), (2.0, 100.0)]).unwrap_err(), CatchFailure::NoGround);
+   }
+   #[test]
+   fn test_max_width() {
+      let mut rule = SideSlope::new(1.0, 2.0);
+      // 3 m of fill need 6 m of slope
+      assert!(daylight(103.0, &rule, &flat(100.0)).is_ok());
+      rule.max_width = 5.0;
+      assert_eq!(daylight(103.0, &rule, &flat(100.0)).unwrap_err(), CatchFailure::OutOfWidth);
+      rule.max_width = 6.5;
+      assert!(eq001(daylight(103.0, &rule, &flat(100.0)).unwrap().1[0].distance, 6.0));
+   }
+}
@@ -0,0 +1,165 @@
+//! Plantilla de la sección transversal.
+//!
+//! A template is the list of elements of each side of the axis, from
+//! the axis outward. Slopes follow the criteria of the superelevation:
+//! positive if the element rises from the axis toward the edge
+use std::fmt;
+use super::sideslope::SideSlope;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ElementKind {
+   Lane,
+   Shoulder,
+   /// Half of the median, when the axis is in the middle of it
+   Median,
+   /// Break points of the side slopes beyond the platform
+   Ditch,
+   Bench,
+   CutSlope,
+   FillSlope
+}
+
+impl fmt::Display for ElementKind {
+   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+      let name = match self {
+         ElementKind::Lane => "Lane",
+         ElementKind::Shoulder => "Shoulder",
+         ElementKind::Median => "Median",
+         ElementKind::Ditch => "Ditch",
+         ElementKind::Bench => "Bench",
+         ElementKind::CutSlope => "Cut slope",
+         ElementKind::FillSlope => "Fill slope"
+      };
+      write!(f, "{}", name)
+   }
+}
+
+/// Cross slope of an element
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SlopeRule {
+   /// Cross slope of the pavement in its side: the superelevation,
+   /// or the crown of the template if there isn't superelevation
+   Pavement,
+   /// Constant cross slope
+   Fixed(f64)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TemplateElement {
+   pub kind: ElementKind,
+   pub width: f64,
+   pub slope: SlopeRule
+}
+
+impl TemplateElement {
+   /// Creates a new element. The width can't be negative
+   pub fn new(kind: ElementKind, width: f64, slope: SlopeRule) -> Self {
+      if width < 0.0 {
+         panic!("Width of template element can't be negative");
+      }
+      TemplateElement{kind, width, slope}
+   }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+   /// Elements of the left side, from the axis outward
+   pub left: Vec<TemplateElement>,
+   /// Elements of the right side, from the axis outward
+   pub right: Vec<TemplateElement>,
+   /// Crown slope (bombeo) of the pavement without superelevation,
+   /// as a positive fraction
+   pub crown: f64,
+   /// Side slopes beyond the outer element of each side, down to the
+   /// terrain
+   pub left_side_slope: Option<SideSlope>,
+   pub right_side_slope: Option<SideSlope>
+}
+
+impl Template {
+   pub fn new(crown: f64) -> Self {
+      Template{left: Vec::new(), right: Vec::new(), crown, left_side_slope: None, right_side_slope: None}
+   }
+   /// Sets the same side slopes at both sides
+   pub fn set_side_slopes(&mut self, side_slope: SideSlope) {
+      self.left_side_slope = Some(side_slope);
+      self.right_side_slope = Some(side_slope);
+   }
+   /// Template of the inventory sheets: the carriageway divided in lanes
+   /// of equal width, with the axis in the middle, and the shoulders
+   /// at both sides. With an odd number of lanes the right side has
+   /// one lane more. Shoulders follow the pavement slope
+   pub fn from_inventory(lanes: usize, carriageway_width: f64, right_shoulder: f64, 
+      left_shoulder: f64) -> Self {
+      if lanes == 0 || carriageway_width <= 0.0 {
+         panic!("Carriageway must have lanes and width greater than zero");
+      }
+      let lane_width = carriageway_width / lanes as f64;
+      let right_lanes = lanes.div_ceil(2);
+      let mut template = Template::new(0.02);
+      template.left = vec![TemplateElement::new(ElementKind::Lane, lane_width, SlopeRule::Pavement); lanes - right_lanes];
+      template.right = vec![TemplateElement::new(ElementKind::Lane, lane_width, SlopeRule::Pavement); right_lanes];
+      if left_shoulder > 0.0 {
+         template.left.push(TemplateElement::new(ElementKind::Shoulder, left_shoulder, SlopeRule::Pavement));
+      }
+      if right_shoulder > 0.0 {
+         template.right.push(TemplateElement::new(ElementKind::Shoulder, right_shoulder, SlopeRule::Pavement));
+      }
+      template
+   }
+   pub fn add_left(&mut self, element: TemplateElement) {
+      self.left.push(element);
+   }
+   pub fn add_right(&mut self, element: TemplateElement) {
+      self.right.push(element);
+   }
+   pub fn left_width(&self) -> f64 {
+      self.left.iter().map(|e| e.width).sum()
+   }
+   pub fn right_width(&self) -> f64 {
+      self.right.iter().map(|e| e.width).sum()
+   }
+   /// Width of the platform (plataforma), both sides
+   pub fn width(&self) -> f64 {
+      self.left_width() + self.right_width()
+   }
+   /// Width of the lanes of both sides (calzada)
+   pub fn carriageway_width(&self) -> f64 {
+      self.left.iter().chain(self.right.iter())
+         .filter(|e| e.kind == ElementKind::Lane).map(|e| e.width).sum()
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use crate::eq001;
+
+   #[test]
+   fn test_from_inventory() {
+      // First row of InventarioGeometriaM607.xls
+      let t = Template::from_inventory(3, 9.2, 0.8, 0.8);
+      assert_eq!(t.left.len(), 2);
+      assert_eq!(t.right.len(), 3);
+      assert!(eq001(t.width(), 10.8));
+      assert!(eq001(t.carriageway_width(), 9.2));
+      assert!(eq001(t.right_width(), 2.0 * 9.2 / 3.0 + 0.8));
+      let t = Template::from_inventory(2, 9.2, 0.0, 0.8);
+      assert_eq!(t.right.len(), 1);
+      assert!(eq001(t.width(), 10.0));
+   }
+   #[test]
+   fn test_add() {
+      let mut t = Template::new(0.02);
+      t.add_left(TemplateElement::new(ElementKind::Median, 1.0, SlopeRule::Fixed(-0.04)));
+      t.add_left(TemplateElement::new(ElementKind::Lane, 3.5, SlopeRule::Pavement));
+      assert!(eq001(t.left_width(), 4.5));
+      assert!(eq001(t.carriageway_width(), 3.5));
+      assert_eq!(format!("{}", t.left[0].kind), "Median");
+   }
+   #[test]
+   #[should_panic]
+   fn test_element_panic() {
+      let _e = TemplateElement::new(ElementKind::Lane, -3.5, SlopeRule::Pavement);
+   }
+}
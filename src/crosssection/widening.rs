@@ -0,0 +1,256 @@
+//! Sobreancho en curvas.
+//!
+//! The rear wheels of long vehicles run inside the path of the front
+//! ones, so on small radius curves the lanes are widened. The widening
+//! of each lane is
+//!
+//! S = L^2 / (2 R)
+//!
+//! with L the distance from the rear axle to the front of the design
+//! vehicle. It is applied on the inner side of the curve, full along
+//! the circle and growing linearly along the transition curves
+use std::fmt;
+use crate::format_station;
+use crate::road::roadaxis::RoadAxis;
+
+/// Vehicle used to compute the widening
+#[derive(Debug, Clone, Copy)]
+pub struct DesignVehicle {
+   /// Distance from the rear axle to the front of the vehicle
+   pub length: f64
+}
+
+impl DesignVehicle {
+   pub fn new(length: f64) -> Self {
+      if length <= 0.0 {
+         panic!("Vehicle length must be greater than zero");
+      }
+      DesignVehicle{length}
+   }
+   /// Widening of one lane in a curve of the radius
+   pub fn lane_widening(&self, radius: f64) -> f64 {
+      self.length * self.length / 2.0 / radius.abs()
+   }
+}
+
+impl Default for DesignVehicle {
+   /// Vehicle of the Norma 3.1-IC, 9 m from the rear axle to the front
+   fn default() -> Self {
+      DesignVehicle::new(9.0)
+   }
+}
+
+/// Widening (left, right) at a station
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WideningKey {
+   pub station: f64,
+   pub left: f64,
+   pub right: f64
+}
+
+/// Widening of the pavement along the axis, linear between keys in
+/// increasing station order and zero out of them
+#[derive(Debug, Clone, Default)]
+pub struct Widening {
+   pub keys: Vec<WideningKey>
+}
+
+impl Widening {
+   /// Widening (left, right) at the station
+   pub fn widening_at(&self, station: f64) -> (f64, f64) {
+      for w in self.keys.windows(2) {
+         let (k0, k1) = (w[0], w[1]);
+         if station >= k0.station && station <= k1.station {
+            let t = if k1.station > k0.station { (station - k0.station) / (k1.station - k0.station) } else { 0.0 };
+            return (k0.left + (k1.left - k0.left) * t, k0.right + (k1.right - k0.right) * t);
+         }
+      }
+      (0.0, 0.0)
+   }
+}
+
+/// Widening of one curve
+#[derive(Debug, Clone, Copy)]
+pub struct WideningRow {
+   /// Start station of the circle
+   pub station: f64,
+   pub radius: f64,
+   /// Widening of the whole carriageway
+   pub widening: f64,
+   /// Stations where the widening begins, is complete, begins to
+   /// decrease and ends
+   pub start_station: f64,
+   pub full_start_station: f64,
+   pub full_end_station: f64,
+   pub end_station: f64
+}
+
+#[derive(Debug, Clone)]
+pub struct WideningDesign {
+   pub widening: Widening,
+   pub rows: Vec<WideningRow>
+}
+
+/// Widening of a ramp, given by its keys as (station, widening), at the
+/// station. Zero out of the ramp
+fn ramp_value(ramp: &[(f64, f64)], station: f64) -> f64 {
+   ramp.windows(2).filter(|w| station >= w[0].0 && station <= w[1].0).map(|w| {
+      if w[1].0 > w[0].0 {
+         w[0].1 + (w[1].1 - w[0].1) * (station - w[0].0) / (w[1].0 - w[0].0)
+      } else {
+         w[0].1.max(w[1].1)
+      }
+   }).fold(0.0, f64::max)
+}
+
+/// Greatest widening of the ramps at the station
+fn envelope_value(ramps: &[Vec<(f64, f64)>], station: f64) -> f64 {
+   ramps.iter().map(|r| ramp_value(r, station)).fold(0.0, f64::max)
+}
+
+/// Stations where the greatest widening of the ramps can change its
+/// gradient: the keys of the ramps and the crossings between them
+fn envelope_stations(ramps: &[Vec<(f64, f64)>]) -> Vec<f64> {
+   let mut stations: Vec<f64> = ramps.iter().flatten().map(|k| k.0).collect();
+   stations.sort_by(|a, b| a.total_cmp(b));
+   stations.dedup();
+   let mut crossings = Vec::<f64>::new();
+   for w in stations.windows(2) {
+      for (i, a) in ramps.iter().enumerate() {
+         for b in &ramps[i + 1..] {
+            let d0 = ramp_value(a, w[0]) - ramp_value(b, w[0]);
+            let d1 = ramp_value(a, w[1]) - ramp_value(b, w[1]);
+            if d0 * d1 < 0.0 {
+               crossings.push(w[0] + (w[1] - w[0]) * d0 / (d0 - d1));
+            }
+         }
+      }
+   }
+   stations.extend(crossings);
+   stations
+}
+
+/// Computes the widening of the curves of the axis with radius under
+/// max_radius, for a carriageway of the number of lanes. Curves without
+/// transition curves get the widening in the run_out length of the
+/// adjacent sections. Where the ramps of close curves overlap, the
+/// greatest widening is taken
+pub fn design_widening(axis: &RoadAxis, lanes: usize, vehicle: &DesignVehicle, 
+   max_radius: f64, run_out: f64) -> WideningDesign {
+   let tol = &axis.tolerances;
+   let (mut left_ramps, mut right_ramps) = (Vec::<Vec<(f64, f64)>>::new(), Vec::<Vec<(f64, f64)>>::new());
+   let mut rows = Vec::<WideningRow>::new();
+   let n = axis.sections.len();
+   for (i, section) in axis.sections.iter().enumerate() {
+      let r = section.start_radius();
+      if tol.is_zero_radius(r) || !tol.eq_radius(r, section.end_radius()) || r.abs() >= max_radius {
+         continue;
+      }
+      let widening = lanes as f64 * vehicle.lane_widening(r);
+      let full_start_station = axis.section_start_station(i);
+      let full_end_station = full_start_station + section.length();
+      let entry = if i > 0 && axis.is_transition(i - 1, r) { axis.sections[i-1].length() } else { run_out };
+      let exit = if i + 1 < n && axis.is_transition(i + 1, r) { axis.sections[i+1].length() } else { run_out };
+      let row = WideningRow{station: full_start_station, radius: r, widening,
+         start_station: (full_start_station - entry).max(axis.start_station), full_start_station, 
+         full_end_station, end_station: (full_end_station + exit).min(axis.end_station())};
+      let ramp = vec![(row.start_station, 0.0), (row.full_start_station, widening),
+         (row.full_end_station, widening), (row.end_station, 0.0)];
+      // Inner side of right curves is the right side
+      if r > 0.0 { right_ramps.push(ramp) } else { left_ramps.push(ramp) };
+      rows.push(row);
+   }
+   let mut stations = envelope_stations(&left_ramps);
+   stations.extend(envelope_stations(&right_ramps));
+   stations.sort_by(|a, b| a.total_cmp(b));
+   stations.dedup_by(|a, b| tol.eq_linear(*a, *b));
+   let keys = stations.into_iter().map(|station| WideningKey{station,
+      left: envelope_value(&left_ramps, station), right: envelope_value(&right_ramps, station)}).collect();
+   WideningDesign{widening: Widening{keys}, rows}
+}
+
+impl fmt::Display for WideningDesign {
+   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+      writeln!(f, "{:>12} {:>9} {:>9} {:>12} {:>12} {:>12} {:>12}", "PK", "Radius", "Widening",
+         "Start", "Full start", "Full end", "End")?;
+      for row in &self.rows {
+         writeln!(f, "{:>12} {:>9.1} {:>9.3} {:>12} {:>12} {:>12} {:>12}", format_station(row.station),
+            row.radius, row.widening, format_station(row.start_station), 
+            format_station(row.full_start_station), format_station(row.full_end_station),
+            format_station(row.end_station))?;
+      }
+      Ok(())
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use crate::eq001;
+   use crate::geom::point::Point;
+   use crate::road::hcircle::HCircle;
+   use crate::road::hsection::HSection;
+   use crate::road::htangent::HTangent;
+   use crate::road::roadaxis::tests::sample_axis;
+
+   #[test]
+   fn test_lane_widening() {
+      let v = DesignVehicle::default();
+      assert!(eq001(v.lane_widening(-200.0), 81.0 / 400.0));
+   }
+   #[test]
+   fn test_design_widening() {
+      // Sample axis: right curve R=200 from 260 to 360, clothoids of 60 m
+      let axis = sample_axis();
+      let design = design_widening(&axis, 2, &DesignVehicle::default(), 250.0, 30.0);
+      assert_eq!(design.rows.len(), 1);
+      let row = design.rows[0];
+      assert!(eq001(row.widening, 2.0 * 81.0 / 400.0));
+      assert!(eq001(row.start_station, 200.0));
+      assert!(eq001(row.end_station, 420.0));
+      let w = &design.widening;
+      assert_eq!(w.widening_at(300.0), (0.0, row.widening));
+      assert!(eq001(w.widening_at(230.0).1, row.widening / 2.0));
+      assert_eq!(w.widening_at(150.0), (0.0, 0.0));
+      let text = format!("{}", design);
+      assert_eq!(text.lines().count(), 2);
+      assert!(text.contains("0+260.000"));
+      assert!(w.keys.windows(2).all(|k| k[0].station < k[1].station));
+      // No widening over the radius limit
+      assert!(design_widening(&axis, 2, &DesignVehicle::default(), 150.0, 30.0).rows.is_empty());
+   }
+   #[test]
+   fn test_close_curves() {
+      // Right circles R=100 and R=150 of 50 m, 20 m apart and without
+      // transition curves, then a left circle R=150 just after the
+      // second one
+      let mut axis = RoadAxis::new();
+      let t1 = HTangent::new(Point::new(0.0, 0.0), 0.0, 100.0);
+      let c1 = HCircle::new(t1.end_point(), t1.end_azimuth(), 100.0, 50.0);
+      let t2 = HTangent::new(c1.end_point(), c1.end_azimuth(), 20.0);
+      let c2 = HCircle::new(t2.end_point(), t2.end_azimuth(), 150.0, 50.0);
+      let c3 = HCircle::new(c2.end_point(), c2.end_azimuth(), -150.0, 50.0);
+      let t3 = HTangent::new(c3.end_point(), c3.end_azimuth(), 100.0);
+      axis.add_htangent(t1);
+      axis.add_hcircle(c1);
+      axis.add_htangent(t2);
+      axis.add_hcircle(c2);
+      axis.add_hcircle(c3);
+      axis.add_htangent(t3);
+      let design = design_widening(&axis, 2, &DesignVehicle::default(), 250.0, 30.0);
+      assert_eq!(design.rows.len(), 3);
+      let (w1, w2) = (design.rows[0].widening, design.rows[1].widening);
+      let w = &design.widening;
+      assert!(w.keys.windows(2).all(|k| k[0].station < k[1].station));
+      // Full widening of each curve inside it
+      assert!(eq001(w.widening_at(125.0).1, w1));
+      assert!(eq001(w.widening_at(175.0).1, w2));
+      // Exit of the first curve over the entry of the second one
+      assert!(eq001(w.widening_at(160.0).1, w1 * 2.0 / 3.0));
+      assert!(eq001(w.widening_at(168.0).1, w2 * 28.0 / 30.0));
+      // Exit of the second curve along the third one, on the other side
+      assert!(eq001(w.widening_at(230.0).1, w2 * 20.0 / 30.0));
+      assert!(eq001(w.widening_at(230.0).0, w2));
+      assert!(eq001(w.widening_at(270.0).1, 0.0));
+   }
+}
@@ -0,0 +1,5 @@
+//! Movimiento de tierras: áreas de desmonte y terraplén en los perfiles
+//! transversales y cubicación entre perfiles
+
+pub mod volumes;
+pub mod masshaul;
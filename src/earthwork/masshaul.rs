@@ -0,0 +1,291 @@
+//! Diagrama de masas (Brückner).
+//!
+//! The mass ordinate at a station is the sum of the cut volumes minus
+//! the sum of the fill volumes from the start of the table. Cut volumes
+//! are multiplied by the cut factor, the compacted volume given by a
+//! cubic metre of cut (below 1 for shrinkage, over 1 for bulking), and
+//! fill volumes by the fill factor.
+//!
+//! A rising diagram is cut and a falling one is fill. Between two
+//! balance points, where the diagram crosses the balance line, the cut
+//! balances the fill and the area of the loop is the haul in m3 x m.
+//! The free haul chord is the horizontal segment of the free haul
+//! length inside the loop: the volume over it is moved free of charge,
+//! and the rest of the loop is overhaul, paid for the distance beyond
+//! the free haul
+use std::fmt::Write;
+use crate::format_station;
+use super::volumes::VolumeTable;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MassOrdinate {
+   pub station: f64,
+   /// Cut and fill volumes from the previous station, with the factors
+   pub cut: f64,
+   pub fill: f64,
+   pub ordinate: f64
+}
+
+/// Haul between two balance points
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HaulLoop {
+   pub start_station: f64,
+   pub end_station: f64,
+   /// Volume moved between the balance points
+   pub volume: f64,
+   /// Stations of the ends of the free haul chord. None if the loop is
+   /// shorter than the free haul distance
+   pub free_haul_chord: Option<(f64, f64)>,
+   pub free_haul_volume: f64,
+   pub overhaul_volume: f64,
+   /// Overhaul in m3 x m: overhaul volume by its mean haul distance in
+   /// excess of the free haul distance
+   pub overhaul: f64
+}
+
+#[derive(Debug, Clone)]
+pub struct MassHaulDiagram {
+   pub ordinates: Vec<MassOrdinate>
+}
+
+impl MassHaulDiagram {
+   /// Diagram from the volume table with the factors of cut and fill
+   pub fn from_volume_table(table: &VolumeTable, cut_factor: f64, fill_factor: f64) -> Self {
+      let mut ordinate = 0.0;
+      let ordinates = table.rows.iter().map(|r| {
+         let (cut, fill) = (r.cut_volume * cut_factor, r.fill_volume * fill_factor);
+         ordinate += cut - fill;
+         MassOrdinate{station: r.station, cut, fill, ordinate}
+      }).collect();
+      MassHaulDiagram{ordinates}
+   }
+   /// Stations where the diagram crosses the horizontal balance line at
+   /// the ordinate level
+   pub fn balance_points(&self, level: f64) -> Vec<f64> {
+      let mut points = Vec::<f64>::new();
+      for w in self.ordinates.windows(2) {
+         let (d0, d1) = (w[0].ordinate - level, w[1].ordinate - level);
+         if d0 == 0.0 {
+            points.push(w[0].station);
+         } else if d0 * d1 < 0.0 {
+            points.push(w[0].station + (w[1].station - w[0].station) * d0 / (d0 - d1));
+         }
+      }
+      if let Some(last) = self.ordinates.last() {
+         if last.ordinate == level {
+            points.push(last.station);
+         }
+      }
+      points.dedup();
+      points
+   }
+   /// Diagram as (station, ordinate) pairs between the stations, with
+   /// the ordinates interpolated at the ends
+   fn stretch(&self, start: f64, end: f64) -> Vec<(f64, f64)> {
+      let mut points = vec![(start, self.ordinate_at(start))];
+      points.extend(self.ordinates.iter().filter(|o| o.station > start && o.station < end)
+         .map(|o| (o.station, o.ordinate)));
+      points.push((end, self.ordinate_at(end)));
+      points
+   }
+   /// Ordinate at the station, interpolated linearly. A diagram of one
+   /// ordinate is constant and an empty one is zero
+   pub fn ordinate_at(&self, station: f64) -> f64 {
+      match self.ordinates.len() {
+         0 => return 0.0,
+         1 => return self.ordinates[0].ordinate,
+         _ => {}
+      }
+      let i = self.ordinates.iter().position(|o| o.station >= station)
+         .unwrap_or(self.ordinates.len() - 1).max(1);
+      let (o0, o1) = (self.ordinates[i - 1], self.ordinates[i]);
+      o0.ordinate + (o1.ordinate - o0.ordinate) * (station - o0.station) / (o1.station - o0.station)
+   }
+   /// Free haul and overhaul of the loops between the balance points of
+   /// the balance line at the level
+   pub fn haul_analysis(&self, level: f64, free_haul_distance: f64) -> Vec<HaulLoop> {
+      let balance = self.balance_points(level);
+      let mut loops = Vec::<HaulLoop>::new();
+      for w in balance.windows(2) {
+         let (start, end) = (w[0], w[1]);
+         // Heights over the balance line, positive in the loop
+         let sign = if self.ordinate_at((start + end) / 2.0) >= level { 1.0 } else { -1.0 };
+         let points: Vec<(f64, f64)> = self.stretch(start, end).iter()
+            .map(|&(s, m)| (s, sign * (m - level))).collect();
+         let volume = points.iter().map(|p| p.1).fold(0.0, f64::max);
+         if volume <= 0.0 {
+            continue;
+         }
+         let mut haul = HaulLoop{start_station: start, end_station: end, volume, free_haul_chord: None,
+            free_haul_volume: volume, overhaul_volume: 0.0, overhaul: 0.0};
+         if end - start > free_haul_distance {
+            // Height of the chord with the free haul length, by bisection
+            let (mut low, mut high) = (0.0, volume);
+            for _ in 0..100 {
+               let middle = (low + high) / 2.0;
+               let (s1, s2) = chord(&points, middle);
+               if s2 - s1 > free_haul_distance { low = middle } else { high = middle }
+            }
+            let height = (low + high) / 2.0;
+            let (s1, s2) = chord(&points, height);
+            haul.free_haul_chord = Some((s1, s2));
+            haul.free_haul_volume = volume - height;
+            haul.overhaul_volume = height;
+            haul.overhaul = (clipped_area(&points, height) - free_haul_distance * height).max(0.0);
+         }
+         loops.push(haul);
+      }
+      loops
+   }
+   /// Diagram as CSV, with a header line
+   pub fn to_csv(&self) -> String {
+      let mut csv = String::from("station,cut,fill,ordinate\n");
+      for o in &self.ordinates {
+         writeln!(csv, "{:.3},{:.3},{:.3},{:.3}", o.station, o.cut, o.fill, o.ordinate).unwrap();
+      }
+      csv
+   }
+   /// Diagram as SVG drawing of the size in pixels, with the balance
+   /// line at level zero and the balance points
+   pub fn to_svg(&self, width: f64, height: f64) -> String {
+      let margin = 40.0;
+      let mut svg = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" \
+         viewBox=\"0 0 {} {}\">\n", width, height, width, height);
+      if self.ordinates.len() < 2 {
+         svg.push_str("</svg>\n");
+         return svg;
+      }
+      let (s0, s1) = (self.ordinates[0].station, self.ordinates[self.ordinates.len() - 1].station);
+      let (m0, m1) = self.ordinates.iter()
+         .fold((0.0_f64, 0.0_f64), |(a, b), o| (a.min(o.ordinate), b.max(o.ordinate)));
+      let range = if m1 > m0 { m1 - m0 } else { 1.0 };
+      let x = |s: f64| margin + (s - s0) / (s1 - s0) * (width - 2.0 * margin);
+      let y = |m: f64| height - margin - (m - m0) / range * (height - 2.0 * margin);
+      writeln!(svg, "<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"gray\"/>",
+         x(s0), y(0.0), x(s1), y(0.0)).unwrap();
+      let points: Vec<String> = self.ordinates.iter()
+         .map(|o| format!("{:.1},{:.1}", x(o.station), y(o.ordinate))).collect();
+      writeln!(svg, "<polyline points=\"{}\" fill=\"none\" stroke=\"black\"/>", points.join(" ")).unwrap();
+      for b in self.balance_points(0.0) {
+         writeln!(svg, "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"3\" fill=\"red\"><title>{}</title></circle>",
+            x(b), y(0.0), format_station(b)).unwrap();
+      }
+      writeln!(svg, "<text x=\"{:.1}\" y=\"{:.1}\" font-size=\"12\">{}</text>", x(s0), height - margin / 3.0,
+         format_station(s0)).unwrap();
+      writeln!(svg, "<text x=\"{:.1}\" y=\"{:.1}\" font-size=\"12\" text-anchor=\"end\">{}</text>", x(s1),
+         height - margin / 3.0, format_station(s1)).unwrap();
+      svg.push_str("</svg>\n");
+      svg
+   }
+}
+
+/// First and last stations where the loop reaches the height
+fn chord(points: &[(f64, f64)], height: f64) -> (f64, f64) {
+   let cross = |p0: (f64, f64), p1: (f64, f64)| p0.0 + (p1.0 - p0.0) * (height - p0.1) / (p1.1 - p0.1);
+   let i = points.iter().position(|p| p.1 >= height).unwrap_or(0).max(1);
+   let j = points.iter().rposition(|p| p.1 >= height).unwrap_or(0).min(points.len() - 2);
+   (cross(points[i - 1], points[i]), cross(points[j + 1], points[j]))
+}
+
+/// Area under the loop clipped at the height
+fn clipped_area(points: &[(f64, f64)], height: f64) -> f64 {
+   let mut area = 0.0;
+   for w in points.windows(2) {
+      let ((s0, h0), (s1, h1)) = (w[0], w[1]);
+      if (h0 - height) * (h1 - height) < 0.0 {
+         let s = s0 + (s1 - s0) * (height - h0) / (h1 - h0);
+         area += (s - s0) * (h0.min(height) + height) / 2.0 + (s1 - s) * (height + h1.min(height)) / 2.0;
+      } else {
+         area += (s1 - s0) * (h0.min(height) + h1.min(height)) / 2.0;
+      }
+   }
+   area
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use crate::eq001;
+   use crate::earthwork::volumes::{VolumeMethod, VolumeRow};
+
+   /// Table of 20 m intervals with 100 m of cut of 50 m3 each interval
+   /// followed by 100 m of fill of 50 m3 each interval
+   fn sample_table() -> VolumeTable {
+      let mut rows = Vec::<VolumeRow>::new();
+      let (mut cumulative_cut, mut cumulative_fill) = (0.0, 0.0);
+      for i in 0..=10 {
+         let (cut_volume, fill_volume) = match i {
+            0 => (0.0, 0.0),
+            1..=5 => (50.0, 0.0),
+            _ => (0.0, 50.0)
+         };
+         cumulative_cut += cut_volume;
+         cumulative_fill += fill_volume;
+         rows.push(VolumeRow{station: 20.0 * i as f64, cut_area: 0.0, fill_area: 0.0, cut_volume,
+            fill_volume, cumulative_cut, cumulative_fill});
+      }
+      VolumeTable{method: VolumeMethod::AverageEndArea, rows}
+   }
+
+   #[test]
+   fn test_ordinates() {
+      let diagram = MassHaulDiagram::from_volume_table(&sample_table(), 1.0, 1.0);
+      assert_eq!(diagram.ordinates.len(), 11);
+      assert!(eq001(diagram.ordinates[5].ordinate, 250.0));
+      assert!(eq001(diagram.ordinates[10].ordinate, 0.0));
+      assert!(eq001(diagram.ordinate_at(90.0), 225.0));
+      // Shrinkage of the cut: 10% less available for the fill
+      let diagram = MassHaulDiagram::from_volume_table(&sample_table(), 0.9, 1.0);
+      assert!(eq001(diagram.ordinates[10].ordinate, -25.0));
+      let balance = diagram.balance_points(0.0);
+      assert_eq!(balance.len(), 2);
+      assert!(eq001(balance[1], 190.0));
+   }
+   #[test]
+   fn test_short_diagrams() {
+      let mut table = sample_table();
+      table.rows.truncate(1);
+      let diagram = MassHaulDiagram::from_volume_table(&table, 1.0, 1.0);
+      assert!(eq001(diagram.ordinate_at(10.0), 0.0));
+      assert!(diagram.haul_analysis(0.0, 100.0).is_empty());
+      table.rows.clear();
+      let diagram = MassHaulDiagram::from_volume_table(&table, 1.0, 1.0);
+      assert!(eq001(diagram.ordinate_at(10.0), 0.0));
+      assert!(diagram.balance_points(0.0).is_empty());
+      assert!(diagram.to_svg(400.0, 300.0).ends_with("</svg>\n"));
+   }
+   #[test]
+   fn test_haul_analysis() {
+      let diagram = MassHaulDiagram::from_volume_table(&sample_table(), 1.0, 1.0);
+      let loops = diagram.haul_analysis(0.0, 100.0);
+      assert_eq!(loops.len(), 1);
+      let haul = loops[0];
+      assert!(eq001(haul.start_station, 0.0));
+      assert!(eq001(haul.end_station, 200.0));
+      assert!(eq001(haul.volume, 250.0));
+      // Triangle of base 200 and height 250: the chord of 100 m is at 125
+      let (s1, s2) = haul.free_haul_chord.unwrap();
+      assert!(eq001(s1, 50.0) && eq001(s2, 150.0));
+      assert!(eq001(haul.free_haul_volume, 125.0));
+      assert!(eq001(haul.overhaul_volume, 125.0));
+      // Trapezoid under the chord (200 + 100) / 2 x 125 less 100 x 125
+      assert!(eq001(haul.overhaul, 150.0 * 125.0 - 100.0 * 125.0));
+      // Loop shorter than the free haul distance
+      let haul = diagram.haul_analysis(0.0, 300.0)[0];
+      assert!(haul.free_haul_chord.is_none());
+      assert!(eq001(haul.free_haul_volume, 250.0));
+      assert!(eq001(haul.overhaul, 0.0));
+   }
+   #[test]
+   fn test_export() {
+      let diagram = MassHaulDiagram::from_volume_table(&sample_table(), 1.0, 1.0);
+      let csv = diagram.to_csv();
+      assert_eq!(csv.lines().count(), 12);
+      assert!(csv.contains("100.000,50.000,0.000,250.000"));
+      let svg = diagram.to_svg(800.0, 400.0);
+      assert!(svg.starts_with("<svg"));
+      assert!(svg.contains("<polyline"));
+      assert_eq!(svg.matches("<circle").count(), 2);
+      assert!(svg.trim_end().ends_with("</svg>"));
+   }
+}
@@ -0,0 +1,335 @@
+//! Cubicación de desmonte y terraplén.
+//!
+//! The cut and fill areas of each cross section are the areas between
+//! the design line and the ground line: fill where the design is over
+//! the ground and cut where it is under it. The volume between two
+//! sections is computed by the average end area method
+//!
+//! V = L (A0 + A1) / 2
+//!
+//! or by the prismoidal formula, with the section in the middle
+//!
+//! V = L (A0 + 4 Am + A1) / 6
+//!
+//! In curves the centroid of each area travels a path other than the
+//! axis (theorem of Pappus). With the curvature correction the areas
+//! are multiplied by (1 - e k), e being the offset of the centroid and
+//! k the curvature of the axis, both positive to the right
+use std::fmt;
+use crate::format_station;
+use crate::crosssection::corridor::Corridor;
+use crate::road::roadaxis::RoadAxis;
+use crate::terrain::TerrainModel;
+use crate::terrain::groundsection::ground_section;
+
+/// Sampling interval of the ground line for smooth terrain models.
+/// Triangulated models are also sampled at their edges
+const GROUND_INTERVAL: f64 = 1.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VolumeMethod {
+   AverageEndArea,
+   Prismoidal
+}
+
+/// Cut and fill areas of a cross section, with the offsets of their
+/// centroids
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SectionAreas {
+   pub station: f64,
+   pub cut: f64,
+   pub fill: f64,
+   pub cut_centroid: f64,
+   pub fill_centroid: f64
+}
+
+impl SectionAreas {
+   /// Areas (cut, fill) multiplied by the curvature correction factor
+   /// (1 - e k) of their centroids
+   pub fn corrected(&self, curvature: f64) -> (f64, f64) {
+      (self.cut * (1.0 - self.cut_centroid * curvature), self.fill * (1.0 - self.fill_centroid * curvature))
+   }
+}
+
+/// Elevation of the polyline at the offset, interpolated linearly
+fn interpolate(line: &[(f64, f64)], x: f64) -> f64 {
+   let i = line.iter().position(|p| p.0 >= x).unwrap_or(line.len() - 1).max(1);
+   let (p0, p1) = (line[i - 1], line[i]);
+   if p1.0 == p0.0 {
+      return p1.1;
+   }
+   p0.1 + (p1.1 - p0.1) * (x - p0.0) / (p1.0 - p0.0)
+}
+
+/// Cut and fill areas between the design line and the ground line, both
+/// as (offset, elevation) pairs from left to right, in the range of
+/// offsets common to both lines
+pub fn section_areas(station: f64, design: &[(f64, f64)], ground: &[(f64, f64)]) -> SectionAreas {
+   let mut areas = SectionAreas{station, cut: 0.0, fill: 0.0, cut_centroid: 0.0, fill_centroid: 0.0};
+   if design.len() < 2 || ground.len() < 2 {
+      return areas;
+   }
+   let start = design[0].0.max(ground[0].0);
+   let end = design[design.len() - 1].0.min(ground[ground.len() - 1].0);
+   if start >= end {
+      return areas;
+   }
+   let mut xs: Vec<f64> = design.iter().chain(ground.iter()).map(|p| p.0)
+      .filter(|x| *x > start && *x < end).collect();
+   xs.push(start);
+   xs.push(end);
+   xs.sort_by(|a, b| a.total_cmp(b));
+   xs.dedup();
+   let (mut cut_moment, mut fill_moment) = (0.0, 0.0);
+   // Area and first moment of the linear difference d over [x0, x1]
+   let mut add = |x0: f64, x1: f64, d0: f64, d1: f64| {
+      let area = (x1 - x0) * (d0 + d1) / 2.0;
+      let moment = (x1 - x0) / 6.0 * (d0 * (2.0 * x0 + x1) + d1 * (x0 + 2.0 * x1));
+      if area > 0.0 {
+         areas.fill += area;
+         fill_moment += moment;
+      } else {
+         areas.cut -= area;
+         cut_moment -= moment;
+      }
+   };
+   for w in xs.windows(2) {
+      let (x0, x1) = (w[0], w[1]);
+      let d0 = interpolate(design, x0) - interpolate(ground, x0);
+      let d1 = interpolate(design, x1) - interpolate(ground, x1);
+      if d0 * d1 < 0.0 {
+         let xm = x0 + (x1 - x0) * d0 / (d0 - d1);
+         add(x0, xm, d0, 0.0);
+         add(xm, x1, 0.0, d1);
+      } else {
+         add(x0, x1, d0, d1);
+      }
+   }
+   if areas.cut > 0.0 {
+      areas.cut_centroid = cut_moment / areas.cut;
+   }
+   if areas.fill > 0.0 {
+      areas.fill_centroid = fill_moment / areas.fill;
+   }
+   areas
+}
+
+/// Cut and fill areas of the corridor at the station, against the ground
+/// line of the terrain under the design line
+pub fn corridor_section_areas(corridor: &Corridor, terrain: &dyn TerrainModel,
+   station: f64) -> Result<SectionAreas, String> {
+   if corridor.axis_elevation_at(station).is_none() {
+      return Err(format!("There isn't profile at station {:.3}", station));
+   }
+   let cs = corridor.cross_section_at(station)
+      .ok_or(format!("There isn't template at station {:.3}", station))?;
+   if let Some(failure) = cs.catch_failure() {
+      return Err(format!("Side slope without catch point at station {:.3}: {}", station, failure));
+   }
+   let design = cs.design_line().ok_or(String::from("Corridor without profile"))?;
+   let (left, right) = (-design[0].0, design[design.len() - 1].0);
+   let ground = ground_section(&corridor.axis, terrain, station, left, right, GROUND_INTERVAL)?;
+   Ok(section_areas(station, &design, &ground.pairs()))
+}
+
+/// Row of the volume table. Volumes are these from the previous row
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VolumeRow {
+   pub station: f64,
+   pub cut_area: f64,
+   pub fill_area: f64,
+   pub cut_volume: f64,
+   pub fill_volume: f64,
+   pub cumulative_cut: f64,
+   pub cumulative_fill: f64
+}
+
+#[derive(Debug, Clone)]
+pub struct VolumeTable {
+   pub method: VolumeMethod,
+   pub rows: Vec<VolumeRow>
+}
+
+impl VolumeTable {
+   /// Total volumes (cut, fill)
+   pub fn totals(&self) -> (f64, f64) {
+      self.rows.last().map(|r| (r.cumulative_cut, r.cumulative_fill)).unwrap_or((0.0, 0.0))
+   }
+}
+
+impl fmt::Display for VolumeTable {
+   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+      writeln!(f, "{:>12} {:>10} {:>10} {:>12} {:>12} {:>12} {:>12}", "PK", "Cut area", "Fill area",
+         "Cut vol", "Fill vol", "Cum. cut", "Cum. fill")?;
+      for r in &self.rows {
+         writeln!(f, "{:>12} {:>10.2} {:>10.2} {:>12.1} {:>12.1} {:>12.1} {:>12.1}", format_station(r.station),
+            r.cut_area, r.fill_area, r.cut_volume, r.fill_volume, r.cumulative_cut, r.cumulative_fill)?;
+      }
+      Ok(())
+   }
+}
+
+/// Volume table from the areas of the sections, in increasing station
+/// order. With the prismoidal method the sections must be alternately
+/// end and middle sections (an odd number of them), and the rows are
+/// given at the end sections. With curvature correction the areas are
+/// corrected with the curvature of the axis at each section
+pub fn volume_table(axis: &RoadAxis, sections: &[SectionAreas], method: VolumeMethod,
+   curvature_correction: bool) -> Result<VolumeTable, String> {
+   if sections.is_empty() {
+      return Err(String::from("There are no sections"));
+   }
+   if sections.windows(2).any(|w| w[1].station <= w[0].station) {
+      return Err(String::from("Sections must be in increasing station order"));
+   }
+   let areas = |s: &SectionAreas| {
+      if curvature_correction { s.corrected(axis.curvature_at_station(s.station)) } else { (s.cut, s.fill) }
+   };
+   let step = match method {
+      VolumeMethod::AverageEndArea => 1,
+      VolumeMethod::Prismoidal => {
+         if sections.len().is_multiple_of(2) {
+            return Err(String::from("Prismoidal method needs an odd number of sections"));
+         }
+         for w in sections.windows(3).step_by(2) {
+            if !axis.tolerances.eq_linear(w[1].station, (w[0].station + w[2].station) / 2.0) {
+               return Err(format!("Section at {:.3} isn't in the middle of its neighbours", w[1].station));
+            }
+         }
+         2
+      }
+   };
+   let first = &sections[0];
+   let mut rows = vec![VolumeRow{station: first.station, cut_area: first.cut, fill_area: first.fill,
+      cut_volume: 0.0, fill_volume: 0.0, cumulative_cut: 0.0, cumulative_fill: 0.0}];
+   for i in (step..sections.len()).step_by(step) {
+      let (s0, s1) = (&sections[i - step], &sections[i]);
+      let length = s1.station - s0.station;
+      let (c0, f0) = areas(s0);
+      let (c1, f1) = areas(s1);
+      let (cut_volume, fill_volume) = match method {
+         VolumeMethod::AverageEndArea => (length * (c0 + c1) / 2.0, length * (f0 + f1) / 2.0),
+         VolumeMethod::Prismoidal => {
+            let (cm, fm) = areas(&sections[i - 1]);
+            (length * (c0 + 4.0 * cm + c1) / 6.0, length * (f0 + 4.0 * fm + f1) / 6.0)
+         }
+      };
+      let last = rows[rows.len() - 1];
+      rows.push(VolumeRow{station: s1.station, cut_area: s1.cut, fill_area: s1.fill, cut_volume,
+         fill_volume, cumulative_cut: last.cumulative_cut + cut_volume,
+         cumulative_fill: last.cumulative_fill + fill_volume});
+   }
+   Ok(VolumeTable{method, rows})
+}
+
+/// Volume table of the corridor against the terrain at the stations.
+/// For the prismoidal method the middle sections are added
+pub fn corridor_volume_table(corridor: &Corridor, terrain: &dyn TerrainModel, stations: &[f64],
+   method: VolumeMethod, curvature_correction: bool) -> Result<VolumeTable, String> {
+   let mut all = stations.to_vec();
+   if method == VolumeMethod::Prismoidal {
+      all.extend(stations.windows(2).map(|w| (w[0] + w[1]) / 2.0));
+      all.sort_by(|a, b| a.total_cmp(b));
+   }
+   let sections = all.iter().map(|&s| corridor_section_areas(corridor, terrain, s))
+      .collect::<Result<Vec<SectionAreas>, String>>()?;
+   volume_table(&corridor.axis, &sections, method, curvature_correction)
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use crate::eq001;
+   use crate::geom::point3d::Point3D;
+   use crate::crosssection::corridor::tests::sample_corridor;
+   use crate::road::roadaxis::tests::sample_axis;
+   use crate::road::verticalalignment::VerticalAlignment;
+   use crate::road::vgrade::VGrade;
+   use crate::terrain::tin::Tin;
+
+   fn fill(station: f64, area: f64, centroid: f64) -> SectionAreas {
+      SectionAreas{station, cut: 0.0, fill: area, cut_centroid: 0.0, fill_centroid: centroid}
+   }
+
+   #[test]
+   fn test_section_areas() {
+      let ground = [(-10.0, 100.0), (10.0, 100.0)];
+      let areas = section_areas(0.0, &[(-5.0, 101.0), (5.0, 101.0)], &ground);
+      assert!(eq001(areas.fill, 10.0));
+      assert!(eq001(areas.cut, 0.0));
+      assert!(eq001(areas.fill_centroid, 0.0));
+      // Design crosses the ground at the axis
+      let areas = section_areas(0.0, &[(-5.0, 99.5), (5.0, 100.5)], &ground);
+      assert!(eq001(areas.cut, 1.25));
+      assert!(eq001(areas.fill, 1.25));
+      assert!(eq001(areas.cut_centroid, -10.0 / 3.0));
+      assert!(eq001(areas.fill_centroid, 10.0 / 3.0));
+      // Ground narrower than the design
+      let areas = section_areas(0.0, &[(-5.0, 101.0), (5.0, 101.0)], &[(0.0, 100.0), (2.0, 100.0)]);
+      assert!(eq001(areas.fill, 2.0));
+   }
+   #[test]
+   fn test_volume_table() {
+      let axis = sample_axis();
+      let sections = [fill(100.0, 10.0, 0.0), fill(120.0, 20.0, 0.0), fill(140.0, 20.0, 0.0)];
+      let table = volume_table(&axis, &sections, VolumeMethod::AverageEndArea, false).unwrap();
+      assert_eq!(table.rows.len(), 3);
+      assert!(eq001(table.rows[1].fill_volume, 300.0));
+      assert!(eq001(table.totals().1, 700.0));
+      assert!(eq001(table.totals().0, 0.0));
+      let text = format!("{}", table);
+      assert_eq!(text.lines().count(), 4);
+      assert!(text.contains("0+140.000"));
+      // Prismoidal: the middle section is 14 instead of the mean 15
+      let sections = [fill(100.0, 10.0, 0.0), fill(110.0, 14.0, 0.0), fill(120.0, 20.0, 0.0)];
+      let table = volume_table(&axis, &sections, VolumeMethod::Prismoidal, false).unwrap();
+      assert_eq!(table.rows.len(), 2);
+      assert!(eq001(table.totals().1, 20.0 / 6.0 * (10.0 + 56.0 + 20.0)));
+      assert!(volume_table(&axis, &sections[..2], VolumeMethod::Prismoidal, false).is_err());
+      let uneven = [sections[0], fill(105.0, 14.0, 0.0), sections[2]];
+      assert!(volume_table(&axis, &uneven, VolumeMethod::Prismoidal, false).is_err());
+      assert!(volume_table(&axis, &[sections[1], sections[0]], VolumeMethod::AverageEndArea, false).is_err());
+   }
+   #[test]
+   fn test_curvature_correction() {
+      // Circle of R = 200 to the right from 260 to 360. Fill on the
+      // inner side travels a shorter path
+      let axis = sample_axis();
+      let sections = [fill(280.0, 10.0, 4.0), fill(300.0, 10.0, 4.0)];
+      let table = volume_table(&axis, &sections, VolumeMethod::AverageEndArea, true).unwrap();
+      assert!(eq001(table.totals().1, 200.0 * (1.0 - 4.0 / 200.0)));
+      let sections = [fill(280.0, 10.0, -4.0), fill(300.0, 10.0, -4.0)];
+      let table = volume_table(&axis, &sections, VolumeMethod::AverageEndArea, true).unwrap();
+      assert!(eq001(table.totals().1, 200.0 * (1.0 + 4.0 / 200.0)));
+      // No correction on the tangent
+      let sections = [fill(120.0, 10.0, 4.0), fill(140.0, 10.0, 4.0)];
+      let table = volume_table(&axis, &sections, VolumeMethod::AverageEndArea, true).unwrap();
+      assert!(eq001(table.totals().1, 200.0));
+   }
+   #[test]
+   fn test_corridor_volume_table() {
+      let mut corridor = sample_corridor();
+      let mut profile = VerticalAlignment::new();
+      profile.add_vgrade(VGrade::new(100.0, 101.0, 0.0, 420.0));
+      corridor.set_profile(profile).unwrap();
+      let mut points = Vec::<Point3D>::new();
+      for i in 0..=10 {
+         for j in 0..=30 {
+            points.push(Point3D::new(950.0 + 10.0 * i as f64, 950.0 + 10.0 * j as f64, 100.0));
+         }
+      }
+      let tin = Tin::new(&points).unwrap();
+      // Lanes of 3.5 m at -2% and shoulders of 1.5 m at -4%, 1 m over the ground
+      let side = 3.5 * (1.0 - 0.035) + 1.5 * (0.93 - 0.03);
+      let areas = corridor_section_areas(&corridor, &tin, 150.0).unwrap();
+      assert!(eq001(areas.fill, 2.0 * side));
+      assert!(eq001(areas.fill_centroid, 0.0));
+      for method in [VolumeMethod::AverageEndArea, VolumeMethod::Prismoidal] {
+         let table = corridor_volume_table(&corridor, &tin, &[120.0, 140.0, 160.0], method, true).unwrap();
+         assert_eq!(table.rows.len(), 3);
+         assert!(eq001(table.totals().1, 40.0 * 2.0 * side));
+      }
+      corridor.profile = None;
+      assert!(corridor_section_areas(&corridor, &tin, 150.0).is_err());
+   }
+}
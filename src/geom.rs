@@ -1,6 +1,10 @@
 pub mod point;
+pub mod point3d;
+pub mod polygon;
 pub mod circle;
 pub mod vector;
 pub mod line;
 pub mod clothoid;
+pub mod fresnel;
+pub mod quadrature;
 pub mod angles;
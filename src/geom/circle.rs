@@ -24,15 +24,15 @@ mod tests {
       let p = Point::new(0.0, 0.0);
       let r = 10.0_f64;
       let c = Circle::new(p, r);
-      assert_eq!(true, c.center.x-0.0< 0.001);
-      assert_eq!(true, c.center.y-0.0< 0.001);
-      assert_eq!(true, c.radius-10.0< 0.001);
+      assert!(c.center.x-0.0< 0.001);
+      assert!(c.center.y-0.0< 0.001);
+      assert!(c.radius-10.0< 0.001);
       let p = Point::new(1.0, -1.0);
       let r = 1.0_f64;
       let c = Circle::new(p, r);
-      assert_eq!(true, c.center.x-1.0< 0.001);
-      assert_eq!(true, c.center.y-(-1.0)< 0.001);
-      assert_eq!(true, c.radius-1.0< 0.001);
+      assert!(c.center.x-1.0< 0.001);
+      assert!(c.center.y-(-1.0)< 0.001);
+      assert!(c.radius-1.0< 0.001);
    }
    #[test]
    #[should_panic]
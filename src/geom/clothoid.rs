@@ -1,9 +1,7 @@
-use crate::*;
+use crate::solve_newton_bisection;
+use crate::geom::fresnel::fresnel;
+use crate::tolerances::Tolerances;
 use std::f64::consts::PI;
-//use factorial::Factorial;
-
-/// Número de iteraciones en el desarrollo de las integrales de Fresnel
-const NUMITER:i32 = 6_i32;
 
 /// Generic clothoid segment starting in the origin (0, 0) 
 /// with horizontal tangent and infinity radius at this point.
@@ -20,32 +18,87 @@ pub struct Clothoid {
 
 impl Clothoid {
    pub fn new(parameter: f64, end_radius: f64) -> Self {
-      if eq001(end_radius, 0.0_f64) {
+      Clothoid::new_with_tolerances(parameter, end_radius, &Tolerances::default())
+   }
+   /// Creates a new Clothoid. The end_radius is checked against
+   /// the radius tolerance and the parameter against the linear one
+   pub fn new_with_tolerances(parameter: f64, end_radius: f64, tol: &Tolerances) -> Self {
+      if tol.is_zero_radius(end_radius) {
          panic!("Clothoid creation error: end_radius can't be zero");
       }
-      if eq001(parameter, 0.0_f64) || parameter < 0.0{
+      if tol.is_zero_length(parameter) || parameter < 0.0{
          panic!("Clothoid creation error: parameter can't be zero or negative");
       }      
       Clothoid{parameter, end_radius}
    }
    
-   /// Clotoide obtenida a partir del radio del círculo y el retranqueo
-   /// Es un procedimiento iterativo aproximado
-   // pub fn from_end_radius_and_retranqueo(end_radius: f64, setback: f64) -> Self {
-   //    let mut length: f64 = (24.0*end_radius*setback).sqrt();
-   //    let mut alpha_l: f64 = length / 2.0 / end_radius;
-   //    let mut ret: f64=0.0;
-   //    let mut contador = 0i32;
-   //    while (ret-setback).abs()>0.001 && contador<20 {
-   //       contador=contador+1;
-   //       alpha_l = length / 2.0 / end_radius;
-   //       let factor = alpha_l/12.0 - alpha_l.powi(3)/336.0 + alpha_l.powi(5)/15840.0 - alpha_l.powi(7)/1209600.0;
-   //       ret = length * factor;
-   //       length = ret / factor;
-   //    }
-   //    let parameter = (length*end_radius).sqrt();
-   //    Clothoid { parameter, end_radius}
-   // }
+   /// Clothoid from the end radius and the length.
+   /// The parameter is A = sqrt(abs(R)*L)
+   pub fn from_end_radius_and_length(end_radius: f64, length: f64) -> Result<Self, String> {
+      let tol = Tolerances::default();
+      if tol.is_zero_radius(end_radius) {
+         return Err(String::from("end_radius can't be zero"));
+      }
+      if length <= 0.0 || tol.is_zero_length(length) {
+         return Err(String::from("length must be greater than zero"));
+      }
+      Ok(Clothoid::new((end_radius.abs()*length).sqrt(), end_radius))
+   }
+   /// Clothoid from the end radius and the spiral angle, that is, 
+   /// the tangent deflection between the origin and the end point,
+   /// in radians. The length is L = 2*abs(R)*spiral_angle
+   pub fn from_end_radius_and_spiral_angle(end_radius: f64, spiral_angle: f64) -> Result<Self, String> {
+      if spiral_angle <= 0.0 {
+         return Err(String::from("spiral_angle must be greater than zero"));
+      }
+      Clothoid::from_end_radius_and_length(end_radius, 2.0*end_radius.abs()*spiral_angle)
+   }
+   /// Clothoid from the length and the spiral angle in radians.
+   /// The end radius is R = L/(2*spiral_angle). A positive spiral angle
+   /// gives a positive end_radius (clockwise clothoid) and a negative one
+   /// a negative end_radius
+   pub fn from_length_and_spiral_angle(length: f64, spiral_angle: f64) -> Result<Self, String> {
+      if spiral_angle == 0.0 {
+         return Err(String::from("spiral_angle can't be zero"));
+      }
+      Clothoid::from_end_radius_and_length(length/2.0/spiral_angle, length)
+   }
+   /// Clotoide obtenida a partir del radio del círculo y el retranqueo.
+   /// 
+   /// The length is solved with Newton's method safeguarded with 
+   /// bisection in the interval of spiral angles (0, PI], where the
+   /// setback grows with the length. It returns an error if the setback
+   /// is too large for the radius or if the solver doesn't converge
+   pub fn from_end_radius_and_setback(end_radius: f64, setback: f64) -> Result<Self, String> {
+      let tol = Tolerances::default();
+      if tol.is_zero_radius(end_radius) {
+         return Err(String::from("end_radius can't be zero"));
+      }
+      if setback <= 0.0 {
+         return Err(String::from("setback must be greater than zero"));
+      }
+      let r = end_radius.abs();
+      let setback_at = |length: f64| -> f64 {
+         if length <= 0.0 {
+            0.0
+         } else {
+            Clothoid{parameter: (r*length).sqrt(), end_radius: r}.retranqueo()
+         }
+      };
+      let f = |length: f64| -> (f64, f64) {
+         // Derivada numérica centrada
+         let h = 1e-6 * length.max(1.0);
+         let df = (setback_at(length + h) - setback_at((length - h).max(0.0))) 
+            / (length + h - (length - h).max(0.0));
+         (setback_at(length) - setback, df)
+      };
+      let max_length = 2.0*PI*r;
+      if setback > setback_at(max_length) {
+         return Err(format!("setback {} too large for radius {}", setback, end_radius));
+      }
+      let length = solve_newton_bisection(f, 0.0, max_length, 1e-9*max_length)?;
+      Clothoid::from_end_radius_and_length(end_radius, length)
+   }
    
    /// the length is always positive
    pub fn length(&self) -> f64 {
@@ -71,28 +124,32 @@ impl Clothoid {
          s*s / 2.0 / self.parameter.powi(2)
       }
    }
+   /// Local coordinates (x, y) at a given arc length s. 
+   /// They are obtained from the Fresnel integrals, so they 
+   /// are accurate for any spiral angle, even several turns:\
+   /// x = A*sqrt(PI)*C(s/(A*sqrt(PI))), y = A*sqrt(PI)*S(s/(A*sqrt(PI)))
+   pub fn xy(&self, s: f64) -> (f64, f64) {
+      let k = self.parameter * PI.sqrt();
+      let (c, sn) = fresnel(s / k);
+      let x = k * c;
+      let mut y = k * sn;
+      if self.end_radius > 0_f64 {
+         y = -y;
+      }
+      (x, y)
+   }
    /// The x coordinate at a given arc length s
    pub fn x(&self, s:f64) -> f64 {
-      let alpha = self.alpha(s).abs();
-      let mut x =0_f64;
-      for n in 0..NUMITER+1 {
-         x = x + (-1.0_f64).powi(n)*alpha.powi(2*n) / (4*n+1) as f64 / factorial(2*n as u64) as f64;
-      }
-      x = x*s;
-      x
+      self.xy(s).0
    }
    /// The y coordinate at a given arc length s
    pub fn y(&self, s: f64) -> f64 {
-      let alpha = self.alpha(s).abs();
-      let mut y = 0_f64;
-      for n in 0..NUMITER {
-         y = y + (-1.0_f64).powi(n) * alpha.powi(2*n+1) / (4*n+3) as f64 / factorial((2*n+1) as u64) as f64;
-      }
-      y=y*s;
-      if self.end_radius > 0_f64 {
-         y = -y;
-      }
-      y
+      self.xy(s).1
+   }
+   /// Spiral angle in radians: the tangent deflection between the
+   /// origin and the end point. It's always positive
+   pub fn spiral_angle(&self) -> f64 {
+      self.alpha(self.length()).abs()
    }
    /// El retranqueo siempre es una cantidad positiva
    pub fn retranqueo(&self) -> f64 {
@@ -107,15 +164,16 @@ impl Clothoid {
 #[cfg(test)]
 mod tests {
    use super::*;
+   use crate::{eq001, deg2rad};
 
    #[test]
    fn test_new() {
       let c = Clothoid::new(190.0, 450.0);
-      assert_eq!(true, eq001(190.0, c.parameter));
-      assert_eq!(true, eq001(450.0, c.end_radius));      
+      assert!(eq001(190.0, c.parameter));
+      assert!(eq001(450.0, c.end_radius));      
       let c = Clothoid::new(190.0, -450.0);
-      assert_eq!(true, eq001(190.0, c.parameter));
-      assert_eq!(true, eq001(-450.0, c.end_radius));      
+      assert!(eq001(190.0, c.parameter));
+      assert!(eq001(-450.0, c.end_radius));      
    }
    #[test]
    #[should_panic]
@@ -135,27 +193,73 @@ mod tests {
    fn test_new_panic_3() {
       let _c = Clothoid::new(1000.0, 0.0);
    }
+   #[test]
+   #[should_panic]
+   /// end_radius is zero within the radius tolerance
+   fn test_new_with_tolerances_panic() {
+      let tol = Tolerances::new(0.001, 1e-6, 0.5);
+      let _c = Clothoid::new_with_tolerances(1000.0, 0.1, &tol);
+   }
 
-   // #[test]
-   // fn test_from_end_radius_and_setback() {
-   //    let cl = Clothoid::new(150.0, 250.0);
-   //    let setback = cl.retranqueo();
-   //    let cl2 = Clothoid::from_end_radius_and_retranqueo(cl.end_radius, setback);
-   //    println!("{}", cl2.length());
-   //    assert!(eq001(cl2.length(), 90.0));
-   //    assert!(eq001(cl2.parameter, 150.0));
-   //    assert!(eq001(cl2.end_radius, 250.0));
-      
-   //    let cl = Clothoid::from_end_radius_and_retranqueo(200.0, 1.396);
-   //    println!("{}", cl.length());
-   // }
+   #[test]
+   fn test_from_end_radius_and_setback() {
+      let cl = Clothoid::new(150.0, 250.0);
+      let setback = cl.retranqueo();
+      let cl2 = Clothoid::from_end_radius_and_setback(cl.end_radius, setback).unwrap();
+      assert!(eq001(cl2.length(), 90.0));
+      assert!(eq001(cl2.parameter, 150.0));
+      assert!(eq001(cl2.end_radius, 250.0));
+
+      let cl = Clothoid::from_end_radius_and_setback(-200.0, 1.396).unwrap();
+      assert!(eq001(cl.retranqueo(), 1.396));
+      assert!(cl.end_radius < 0.0);
+
+      // Large spiral angle: 150 degrees
+      let cl = Clothoid::from_end_radius_and_spiral_angle(30.0, deg2rad(150.0)).unwrap();
+      let cl2 = Clothoid::from_end_radius_and_setback(30.0, cl.retranqueo()).unwrap();
+      assert!(eq001(cl2.length(), cl.length()));
+   }
+   #[test]
+   fn test_from_end_radius_and_setback_errors() {
+      assert!(Clothoid::from_end_radius_and_setback(0.0, 1.0).is_err());
+      assert!(Clothoid::from_end_radius_and_setback(200.0, -1.0).is_err());
+      // The setback can't exceed the value for a spiral angle of PI
+      assert!(Clothoid::from_end_radius_and_setback(10.0, 20.0).is_err());
+   }
+   #[test]
+   fn test_from_end_radius_and_length() {
+      let cl = Clothoid::from_end_radius_and_length(250.0, 90.0).unwrap();
+      assert!(eq001(cl.parameter, 150.0));
+      let cl = Clothoid::from_end_radius_and_length(-250.0, 90.0).unwrap();
+      assert!(eq001(cl.parameter, 150.0));
+      assert!(eq001(cl.end_radius, -250.0));
+      assert!(Clothoid::from_end_radius_and_length(250.0, 0.0).is_err());
+      assert!(Clothoid::from_end_radius_and_length(0.0, 90.0).is_err());
+   }
+   #[test]
+   fn test_from_end_radius_and_spiral_angle() {
+      let cl = Clothoid::from_end_radius_and_spiral_angle(250.0, 0.18).unwrap();
+      assert!(eq001(cl.length(), 90.0));
+      assert!(eq001(cl.parameter, 150.0));
+      assert!(eq001(cl.spiral_angle(), 0.18));
+      assert!(Clothoid::from_end_radius_and_spiral_angle(250.0, -0.18).is_err());
+   }
+   #[test]
+   fn test_from_length_and_spiral_angle() {
+      let cl = Clothoid::from_length_and_spiral_angle(90.0, 0.18).unwrap();
+      assert!(eq001(cl.end_radius, 250.0));
+      assert!(eq001(cl.parameter, 150.0));
+      let cl = Clothoid::from_length_and_spiral_angle(90.0, -0.18).unwrap();
+      assert!(eq001(cl.end_radius, -250.0));
+      assert!(Clothoid::from_length_and_spiral_angle(90.0, 0.0).is_err());
+   }
 
    #[test]
    fn test_length() {
       let c = Clothoid::new(190.0, 450.0);
-      assert_eq!(true, (c.length()-80.222).abs()<0.001);
+      assert!((c.length()-80.222).abs()<0.001);
       let c = Clothoid::new(190.0, -450.0);
-      assert_eq!(true, (c.length()-80.222).abs()<0.001);
+      assert!((c.length()-80.222).abs()<0.001);
 
       let cl = Clothoid::new(150.0, 250.0);
       assert!(eq001(cl.length(), 90.0));
@@ -267,4 +371,24 @@ mod tests {
       let cl = Clothoid::new(150.0, -250.0);
       assert!(eq001(cl.y(45.0), 0.675));
    }
+   #[test]
+   fn test_xy_large_spiral_angle() {
+      // Reference values computed with 30 significant digits
+      // for A = 50 and spiral angles of PI/2, one turn and three turns
+      let cl = Clothoid::new(50.0, 1.0);
+      let cases = [
+         (88.6226925452758, 69.11625303968485, 38.83970567433012),
+         (177.2453850905516, 43.27033149079862, 30.434422078853352),
+         (306.99801238394656, 44.09752402374466, 36.184763405635955),
+      ];
+      for (s, x, y) in cases {
+         let (cx, cy) = cl.xy(s);
+         assert!((cx - x).abs() < 1e-6);
+         assert!((cy + y).abs() < 1e-6);
+      }
+      let cl = Clothoid::new(50.0, -1.0);
+      let (cx, cy) = cl.xy(306.99801238394656);
+      assert!((cx - 44.09752402374466).abs() < 1e-6);
+      assert!((cy - 36.184763405635955).abs() < 1e-6);
+   }
 }
\ No newline at end of file
@@ -1,9 +1,9 @@
 
 use core::panic;
 
-use crate::*;
 use crate::geom::vector::Vector;
 use crate::geom::point::Point;
+use crate::tolerances::Tolerances;
 
 /// Straight line in the form ax + by + c = 0
 #[derive(Debug, Clone, Copy)]
@@ -14,17 +14,40 @@ pub struct Line {
 }
 impl Line {
    pub fn new(a:f64, b:f64, c:f64) -> Self {
-      if eq(a, 0.0) && eq(b,0.0) {
+      Line::new_with_tolerances(a, b, c, &Tolerances::default())
+   }
+   /// Creates a new Line. The normal vector (a, b) can't be zero
+   /// within the linear tolerance
+   pub fn new_with_tolerances(a:f64, b:f64, c:f64, tol: &Tolerances) -> Self {
+      if tol.is_zero_length(a.hypot(b)) {
          panic!("Coeficients are zero!");
       }
       Line{a,b,c}
    }
+   /// The line is vertical if its angle with the Y axis is less
+   /// than the angular tolerance
+   pub fn is_vertical_with_tolerances(&self, tol: &Tolerances) -> bool {
+      (self.b / self.a.hypot(self.b)).abs() < tol.angular
+   }
+   /// The line is horizontal if its angle with the X axis is less
+   /// than the angular tolerance
+   pub fn is_horizontal_with_tolerances(&self, tol: &Tolerances) -> bool {
+      (self.a / self.a.hypot(self.b)).abs() < tol.angular
+   }
    pub fn from_point_vector(p:Point, u:Vector) -> Self {
-      // Line from a Point and a Vector
+      Line::from_point_vector_with_tolerances(p, u, &Tolerances::default())
+   }
+   /// Line from a Point and a Vector. The vector is taken as
+   /// vertical if its angle with the Y axis is less than
+   /// the angular tolerance
+   pub fn from_point_vector_with_tolerances(p:Point, u:Vector, tol: &Tolerances) -> Self {
       let a:f64;
       let b:f64;
       let c: f64;
-      if eq001(u.vx, 0.0) {
+      if u.length() < tol.linear {
+         panic!("Vector length is zero!");
+      }
+      if (u.vx / u.length()).abs() < tol.angular {
          a = 1.0;
          b = 0.0;
          c = -p.x;
@@ -36,26 +59,28 @@ impl Line {
       Line::new(a, b, c)
    }
    pub fn from_two_points(p1: Point, p2: Point) -> Self {
-      let a;
-      let b;
-      let c;
-      if eq(p2.x-p1.x, 0.0) {
-         a = 1.0;
-         c = -p1.x;
-         b = 0.0;
-      } else {
-         a = (p2.y-p1.y)/(p2.x-p1.x);
-         c = p1.y-p1.x*(p2.y-p1.y)/(p2.x-p1.x);
-         b = -1.0;
+      Line::from_two_points_with_tolerances(p1, p2, &Tolerances::default())
+   }
+   /// Line through two points. The points can't be closer than
+   /// the linear tolerance
+   pub fn from_two_points_with_tolerances(p1: Point, p2: Point, tol: &Tolerances) -> Self {
+      if p1.dist_to(p2) < tol.linear {
+         panic!("Points are coincident!");
       }
-      Line::new(a, b, c).canonical()
+      Line::from_point_vector_with_tolerances(p1, Vector::from_two_points(p1, p2), tol).canonical()
    }
+   /// Line through a point with the given slope (dy/dx)
    pub fn from_point_and_slope(p: Point, slope: f64) -> Self {
-      todo!();
+      Line::new(slope, -1.0, p.y - slope*p.x).canonical()
    }
    pub fn unitary_vector(&self) -> Vector {
+      self.unitary_vector_with_tolerances(&Tolerances::default())
+   }
+   /// Unitary vector of the line, in the first or fourth quadrant.
+   /// Vertical lines within the angular tolerance give (0, 1)
+   pub fn unitary_vector_with_tolerances(&self, tol: &Tolerances) -> Vector {
       let mut uv: Vector; 
-      if eq(self.b, 0.0) {
+      if self.is_vertical_with_tolerances(tol) {
          uv = Vector::new(0.0, 1.0);
       } else {
          let slope = -self.a / self.b;
@@ -63,35 +88,62 @@ impl Line {
          uv = Vector::new(angle.cos(), angle.sin());
          if uv.vx < 0.0 {
             // Convierto siempre a primer o tercer cuadrante
-            uv.vx = uv.vx * -1.0;
-            uv.vy = uv.vy * -1.0;
+            uv.vx = -uv.vx;
+            uv.vy = -uv.vy;
          }
       }
       uv
    }
    pub fn y_intercept(&self) -> f64 {
-      if eq(self.b, 0.0) {
+      self.y_intercept_with_tolerances(&Tolerances::default())
+   }
+   /// Ordinate at x = 0, infinite if the line is vertical within
+   /// the angular tolerance
+   pub fn y_intercept_with_tolerances(&self, tol: &Tolerances) -> f64 {
+      if self.is_vertical_with_tolerances(tol) {
          f64::INFINITY
       } else {
          -self.c / self.b
       }
    }
    pub fn slope(&self) -> f64{
-      if eq(self.b, 0.0) {
+      self.slope_with_tolerances(&Tolerances::default())
+   }
+   /// Slope dy/dx, infinite if the line is vertical within the
+   /// angular tolerance
+   pub fn slope_with_tolerances(&self, tol: &Tolerances) -> f64{
+      if self.is_vertical_with_tolerances(tol) {
          f64::INFINITY
       } else {
          -self.a / self.b
       }
    }
    pub fn canonical(&self) -> Self {
-      if eq(self.a, 0.0) {
-         Line::new(0.0, 1.0, self.c/self.b)
+      self.canonical_with_tolerances(&Tolerances::default())
+   }
+   /// Same line with a = 1, or with a = 0 and b = 1 if it is
+   /// horizontal within the angular tolerance
+   pub fn canonical_with_tolerances(&self, tol: &Tolerances) -> Self {
+      if self.is_horizontal_with_tolerances(tol) {
+         Line::new_with_tolerances(0.0, 1.0, self.c/self.b, tol)
       } else {
-         Line::new(self.a/self.a, self.b/self.a, self.c/self.a)
+         Line::new_with_tolerances(1.0, self.b/self.a, self.c/self.a, tol)
       }
    }
    pub fn contains_point(&self, p: Point) -> bool {
-      eq(self.a*p.x + self.b*p.y + self.c, 0.0)
+      self.contains_point_with_tolerances(p, &Tolerances::default())
+   }
+   /// The point is in the line if its distance to it
+   /// is less than the linear tolerance
+   pub fn contains_point_with_tolerances(&self, p: Point, tol: &Tolerances) -> bool {
+      p.dist_to_line(*self) < tol.linear
+   }
+   /// Two lines are parallel if the sine of the angle
+   /// between them is less than the angular tolerance
+   pub fn is_parallel_with_tolerances(&self, other: Self, tol: &Tolerances) -> bool {
+      let det = self.a*other.b - other.a*self.b;
+      let norm = self.a.hypot(self.b) * other.a.hypot(other.b);
+      (det / norm).abs() < tol.angular
    }
    pub fn parallel_by_distance(&self, distance: f64) -> (Line, Line) {
       let a = self.a;
@@ -105,25 +157,18 @@ impl Line {
       (r1, r2)
    }
    pub fn intersection(&self, other: Self) -> Point {
-      if eq(self.a, 0.0) && eq(other.a, 0.0) {
-         panic!("lines are parallel!");
-      }
-      if eq(self.b, 0.0) && eq(other.b, 0.0) {
-         panic!("lines are parallel!");
-      }
-      if eq(self.a/other.a, self.b/other.b) {
+      self.intersection_with_tolerances(other, &Tolerances::default())
+   }
+   /// Intersection point of two lines. It panics if the lines are
+   /// parallel within the angular tolerance
+   pub fn intersection_with_tolerances(&self, other: Self, tol: &Tolerances) -> Point {
+      if self.is_parallel_with_tolerances(other, tol) {
          panic!("lines are parallel!");
       }
-      if eq(self.b, 0.0) {
-         let x = -self.c/self.a;
-         let y = -other.a*x/other.b -other.c/other.b;
-         Point::new(x, y)
-      } else {
-         let denom = other.a - self.a*other.b/self.b;
-         let x = (-other.c + self.c*other.b/self.b)/denom;
-         let y = (-self.a*x -self.c)/self.b;
-         Point { x, y }
-      }
+      let det = self.a*other.b - other.a*self.b;
+      let x = (self.b*other.c - other.b*self.c) / det;
+      let y = (other.a*self.c - self.a*other.c) / det;
+      Point { x, y }
    }
    pub fn perpendicular_by_point(&self, p: Point) -> Self {
       let v = self.unitary_vector().left_normal_vector();
@@ -138,6 +183,7 @@ impl Line {
 #[cfg(test)]
 mod tests {
    use super::*;
+   use crate::*;
 
    #[test]
    fn test_from_two_points() {
@@ -172,11 +218,83 @@ mod tests {
    }
    #[test]
    fn test_from_point_and_slope() {
-      todo!();
+      let r = Line::from_point_and_slope(Point::new(0.0, 1.0), 0.0);
+      assert!(eq(r.a, 0.0));
+      assert!(eq(r.b, 1.0));
+      assert!(eq(r.c, -1.0));
+
+      let r = Line::from_point_and_slope(Point::new(1.0, 2.0), 2.0);
+      assert!(eq(r.slope(), 2.0));
+      assert!(r.contains_point(Point::new(1.0, 2.0)));
+      assert!(r.contains_point(Point::new(0.0, 0.0)));
    }
    #[test]
    fn test_y_intercept() {
-      todo!();
+      let r = Line::new(1.0, -1.0, 3.0);
+      assert!(eq(r.y_intercept(), 3.0));
+
+      let r = Line::new(0.0, 2.0, -4.0);
+      assert!(eq(r.y_intercept(), 2.0));
+
+      let r = Line::new(1.0, 0.0, 1.0);
+      assert!(r.y_intercept().is_infinite());
+   }
+   #[test]
+   fn test_contains_point_with_tolerances() {
+      let r = Line::new(0.0, 1.0, 0.0);
+      let p = Point::new(5.0, 0.005);
+      assert!(!r.contains_point(p));
+      let tol = Tolerances::new(0.01, 1e-6, 0.001);
+      assert!(r.contains_point_with_tolerances(p, &tol));
+   }
+   #[test]
+   fn test_intersection_with_tolerances() {
+      // Lines forming an angle of 1e-5 radians
+      let r1 = Line::new(0.0, 1.0, 0.0);
+      let r2 = Line::new(1e-5, -1.0, 0.0);
+      let p = r1.intersection(r2);
+      assert!(eq(p.x, 0.0));
+      assert!(eq(p.y, 0.0));
+      let tol = Tolerances::new(0.001, 1e-4, 0.001);
+      assert!(r1.is_parallel_with_tolerances(r2, &tol));
+   }
+   #[test]
+   #[should_panic]
+   fn test_intersection_with_tolerances_panic() {
+      let r1 = Line::new(0.0, 1.0, 0.0);
+      let r2 = Line::new(1e-5, -1.0, 10.0);
+      let tol = Tolerances::new(0.001, 1e-4, 0.001);
+      let _p = r1.intersection_with_tolerances(r2, &tol);
+   }
+   #[test]
+   #[should_panic]
+   fn test_from_two_points_panic() {
+      let p = Point::new(1.0, 1.0);
+      let _r = Line::from_two_points(p, Point::new(1.0, 1.0005));
+   }
+   #[test]
+   fn test_with_tolerances() {
+      // Line forming an angle of 1e-5 radians with the Y axis
+      let r = Line::new(1.0, 1e-5, 0.0);
+      assert!(!r.slope().is_infinite());
+      assert!(!r.y_intercept().is_infinite());
+      let tol = Tolerances::new(0.001, 1e-4, 0.001);
+      assert!(r.is_vertical_with_tolerances(&tol));
+      assert!(r.slope_with_tolerances(&tol).is_infinite());
+      assert!(r.y_intercept_with_tolerances(&tol).is_infinite());
+      let uv = r.unitary_vector_with_tolerances(&tol);
+      assert!(eq(uv.vx, 0.0) && eq(uv.vy, 1.0));
+      // Line forming an angle of 1e-5 radians with the X axis
+      let r = Line::new(2e-5, 2.0, 2.0);
+      assert!(eq(r.canonical().a, 1.0));
+      let r = r.canonical_with_tolerances(&tol);
+      assert!(eq(r.a, 0.0) && eq(r.b, 1.0) && eq(r.c, 1.0));
+   }
+   #[test]
+   #[should_panic]
+   fn test_new_with_tolerances_panic() {
+      let tol = Tolerances::new(0.01, 1e-6, 0.001);
+      let _r = Line::new_with_tolerances(0.005, 0.0, 1.0, &tol);
    }
    #[test]
    fn test_slope() {
@@ -357,9 +475,9 @@ mod tests {
    #[test] 
    fn test_new() {
       let r1 = Line::new(1.0,1.0,1.0);
-      assert_eq!(true, eq001(r1.a, 1.0));
-      assert_eq!(true, eq001(r1.b, 1.0));
-      assert_eq!(true, eq001(r1.c, 1.0));
+      assert!(eq001(r1.a, 1.0));
+      assert!(eq001(r1.b, 1.0));
+      assert!(eq001(r1.c, 1.0));
    }
    #[test]
    #[should_panic]
@@ -371,15 +489,15 @@ mod tests {
       let p = Point::new(0.0, 0.0);
       let u = Vector::new(1.0,1.0);
       let r = Line::from_point_vector(p, u);
-      assert_eq!(true, eq001(r.a, 1.0));
-      assert_eq!(true, eq001(r.b, -1.0));
-      assert_eq!(true, eq001(r.c, 0.0));
+      assert!(eq001(r.a, 1.0));
+      assert!(eq001(r.b, -1.0));
+      assert!(eq001(r.c, 0.0));
       let p = Point::new(0.0, -10.0);
       let u = Vector::new(0.0,1.0);
       let r = Line::from_point_vector(p, u);
-      assert_eq!(true, eq001(r.a, 1.0));
-      assert_eq!(true, eq001(r.b, 0.0));
-      assert_eq!(true, eq001(r.c, 0.0));
+      assert!(eq001(r.a, 1.0));
+      assert!(eq001(r.b, 0.0));
+      assert!(eq001(r.c, 0.0));
       println!("{:?}", p);
       println!("{:?}", u);
       println!("{:?}", r);
@@ -13,6 +13,16 @@ impl Point {
    pub fn dist_to(&self, other: Point) -> f64 {
       ((other.x-self.x).powf(2.0) + (other.y-self.y).powf(2.0)).sqrt()
    }
+   /// Azimuth in sexagesimal degrees from the instance point to
+   /// the other point. Zero if both points are equal
+   pub fn azimuth_to(&self, other: Point) -> f64 {
+      let dx = other.x - self.x;
+      let dy = other.y - self.y;
+      if dx == 0.0 && dy == 0.0 {
+         return 0.0;
+      }
+      crate::normalize_360(dx.atan2(dy).to_degrees())
+   }
    pub fn dist_to_line(&self, line: Line) -> f64 {
       let d = (line.a*self.x + line.b*self.y + line.c) / (line.a*line.a + line.b*line.b).sqrt();
       d.abs()
@@ -57,29 +67,38 @@ mod tests {
    #[test]
    fn test_new() {
       let p = Point::new(3.0, -1.5);
-      assert_eq!(true, p.x-3.0<0.001);
-      assert_eq!(true, p.y-(-1.5)<0.001);
+      assert!(p.x-3.0<0.001);
+      assert!(p.y-(-1.5)<0.001);
    }
    #[test]
    fn test_dist_to() {
       let p1 = Point::new(0.0,0.0);
       let p2 = Point::new(10.0,0.0);
-      assert_eq!(true, p1.dist_to(p2)-p2.dist_to(p1)<0.001);
+      assert!(p1.dist_to(p2)-p2.dist_to(p1)<0.001);
       //assert_eq!(true, &p1.dist_to(p2)-10.0<0.001);
       //let p3 = Point::new(-1.0,-1.0);
       //assert_eq!(true, &p1.dist_to(p3)-2.0_f64.sqrt()<0.001);           
    }
    #[test]
+   fn test_azimuth_to() {
+      let p = Point::new(10.0, 10.0);
+      assert!(eq001(p.azimuth_to(Point::new(10.0, 20.0)), 0.0));
+      assert!(eq001(p.azimuth_to(Point::new(20.0, 20.0)), 45.0));
+      assert!(eq001(p.azimuth_to(Point::new(10.0, 0.0)), 180.0));
+      assert!(eq001(p.azimuth_to(Point::new(0.0, 10.0)), 270.0));
+      assert!(eq001(p.azimuth_to(p), 0.0));
+   }
+   #[test]
    fn test_dist_to_line() {
       let r = Line::new(0.0, 1.0, 0.0);
       let p = Point::new(0.0, 10.0);
-      assert_eq!(true, eq001(p.dist_to_line(r), 10.0));
+      assert!(eq001(p.dist_to_line(r), 10.0));
       let r = Line::new(1.0, 0.0, 3.0);
       let p = Point::new(0.0, 10.0);
-      assert_eq!(true, eq001(p.dist_to_line(r), 3.0));
+      assert!(eq001(p.dist_to_line(r), 3.0));
       let r = Line::new(1.0, -1.0, 0.0);
       let p = Point::new(0.0, 0.0);
-      assert_eq!(true, eq001(p.dist_to_line(r), 0.0));
+      assert!(eq001(p.dist_to_line(r), 0.0));
  
    }
    #[test]
@@ -87,34 +106,34 @@ mod tests {
       let p1 = Point::new(0.0, 0.0);
       let other = Point::new(10.0, 0.0);
       let mp = p1.middle_point(other);
-      assert_eq!(true, eq001(mp.x, 5.0));
+      assert!(eq001(mp.x, 5.0));
       let other = Point::new(10.0, 10.0);
       let mp = p1.middle_point(other);
-      assert_eq!(true, eq001(mp.x, 5.0));
-      assert_eq!(true, eq001(mp.y, 5.0));
+      assert!(eq001(mp.x, 5.0));
+      assert!(eq001(mp.y, 5.0));
       let p1 = Point::new(-1.0, -1.0);
       let other = Point::new(-2.0, -2.0);
       let mp = p1.middle_point(other);
-      assert_eq!(true, eq001(mp.x, -1.5));
-      assert_eq!(true, eq001(mp.y, -1.5));
+      assert!(eq001(mp.x, -1.5));
+      assert!(eq001(mp.y, -1.5));
    }
    #[test]
    fn test_rotate_axis() {
       let p = Point::new(1.0, 0.0);
       let pprim = p.rotate_axis(PI);
-      assert_eq!(true, (pprim.x-(-1.0_f64)).abs()<1e-10);
-      assert_eq!(true, (pprim.y-0.0_f64).abs()<1e-10);
+      assert!((pprim.x-(-1.0_f64)).abs()<1e-10);
+      assert!((pprim.y-0.0_f64).abs()<1e-10);
       let p = Point::new(1.0, 0.0);
       let pprim = p.rotate_axis(PI/2.0);
-      assert_eq!(true, (pprim.x-0.0_f64).abs()<1e-10);
-      assert_eq!(true, (pprim.y-(-1.0_f64)).abs()<1e-10);
+      assert!((pprim.x-0.0_f64).abs()<1e-10);
+      assert!((pprim.y-(-1.0_f64)).abs()<1e-10);
    }
    #[test]
    fn test_traslate_axis() {
       let p = Point::new(1.0, 0.0);
       let pprim = p.traslate_point(10.0, -10.0);
-      assert_eq!(true, eq001(pprim.x, 11.0_f64));
-      assert_eq!(true, eq001(pprim.y,-10.0_f64));      
+      assert!(eq001(pprim.x, 11.0_f64));
+      assert!(eq001(pprim.y,-10.0_f64));      
    }
    #[test]
    fn test_1() {
@@ -0,0 +1,79 @@
+use super::point::Point;
+
+/// Point with elevation. The z coordinate is the elevation
+#[derive(Copy, Clone, Debug)]
+pub struct Point3D {
+   pub x: f64,
+   pub y: f64,
+   pub z: f64
+}
+
+impl Point3D {
+   pub fn new(x: f64, y: f64, z: f64) -> Self {
+      Point3D{x, y, z}
+   }
+   /// Point3D from a plan point and its elevation
+   pub fn from_point(p: Point, z: f64) -> Self {
+      Point3D::new(p.x, p.y, z)
+   }
+   /// Projection on the horizontal plane
+   pub fn plan_point(&self) -> Point {
+      Point::new(self.x, self.y)
+   }
+   pub fn dist_to(&self, other: Point3D) -> f64 {
+      ((other.x-self.x).powi(2) + (other.y-self.y).powi(2) + (other.z-self.z).powi(2)).sqrt()
+   }
+}
+
+/// 3 D vector
+#[derive(Clone, Copy, Debug)]
+pub struct Vector3D {
+   pub vx: f64,
+   pub vy: f64,
+   pub vz: f64
+}
+
+impl Vector3D {
+   pub fn new(vx: f64, vy: f64, vz: f64) -> Self {
+      Vector3D{vx, vy, vz}
+   }
+   pub fn length(&self) -> f64 {
+      (self.vx*self.vx + self.vy*self.vy + self.vz*self.vz).sqrt()
+   }
+   /// It returns a new Vector3D with same direction and length 1.
+   /// It panics if the length is zero
+   pub fn unit_vector(&self) -> Self {
+      let length = self.length();
+      if length == 0.0 {
+         panic!("Trying unit_vector() of vector with length() zero")
+      }
+      Vector3D::new(self.vx/length, self.vy/length, self.vz/length)
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use crate::eq001;
+
+   #[test]
+   fn test_point3d() {
+      let p = Point3D::from_point(Point::new(1.0, 2.0), 3.0);
+      assert!(eq001(p.z, 3.0));
+      assert!(eq001(p.dist_to(Point3D::new(3.0, 5.0, 9.0)), 7.0));
+      assert!(eq001(p.plan_point().y, 2.0));
+   }
+   #[test]
+   fn test_vector3d() {
+      let v = Vector3D::new(2.0, 3.0, 6.0);
+      assert!(eq001(v.length(), 7.0));
+      let u = v.unit_vector();
+      assert!(eq001(u.length(), 1.0));
+      assert!(eq001(u.vz, 6.0/7.0));
+   }
+   #[test]
+   #[should_panic]
+   fn test_unit_vector_panic() {
+      let _u = Vector3D::new(0.0, 0.0, 0.0).unit_vector();
+   }
+}
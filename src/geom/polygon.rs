@@ -0,0 +1,356 @@
+use super::point::Point;
+
+/// Polygon. The last vertex is joined to the first one, it isn't
+/// repeated. The edges may cross, see regions
+#[derive(Clone, Debug)]
+pub struct Polygon {
+   pub vertices: Vec<Point>
+}
+
+impl Polygon {
+   pub fn new(vertices: Vec<Point>) -> Self {
+      Polygon{vertices}
+   }
+   /// Signed area, positive if the vertices are counterclockwise
+   pub fn signed_area(&self) -> f64 {
+      let n = self.vertices.len();
+      (0..n).map(|i| {
+         let (p, q) = (self.vertices[i], self.vertices[(i + 1) % n]);
+         p.x * q.y - q.x * p.y
+      }).sum::<f64>() / 2.0
+   }
+   pub fn area(&self) -> f64 {
+      self.signed_area().abs()
+   }
+   pub fn perimeter(&self) -> f64 {
+      let n = self.vertices.len();
+      (0..n).map(|i| self.vertices[i].dist_to(self.vertices[(i + 1) % n])).sum()
+   }
+   /// True if the point is inside the polygon (ray casting)
+   pub fn contains(&self, p: Point) -> bool {
+      let n = self.vertices.len();
+      let mut inside = false;
+      let mut j = n.wrapping_sub(1);
+      for i in 0..n {
+         let (a, b) = (self.vertices[i], self.vertices[j]);
+         if (a.y > p.y) != (b.y > p.y) && p.x < (b.x - a.x) * (p.y - a.y) / (b.y - a.y) + a.x {
+            inside = !inside;
+         }
+         j = i;
+      }
+      inside
+   }
+   /// Edge i of the polygon, from vertex i to the next one
+   fn edge(&self, i: usize) -> (Point, Point) {
+      (self.vertices[i], self.vertices[(i + 1) % self.vertices.len()])
+   }
+   /// True if no two edges of the polygon cross
+   pub fn is_simple(&self) -> bool {
+      find_crossings(std::slice::from_ref(self)).is_empty()
+   }
+   /// Regions enclosed by the polygon, that may cross itself (see
+   /// nonzero_regions)
+   pub fn regions(&self) -> Vec<Region> {
+      nonzero_regions(std::slice::from_ref(self))
+   }
+}
+
+/// Polygon with holes
+#[derive(Clone, Debug)]
+pub struct Region {
+   pub outer: Polygon,
+   pub holes: Vec<Polygon>
+}
+
+impl Region {
+   pub fn area(&self) -> f64 {
+      self.outer.area() - self.holes.iter().map(|h| h.area()).sum::<f64>()
+   }
+}
+
+/// Crossing of two edges, as (polygon, edge, parameter along the edge)
+/// of each one, and the crossing point
+struct Crossing {
+   edges: [(usize, usize, f64); 2],
+   point: Point
+}
+
+/// Regular grid over the edges of the polygons with the edges whose
+/// bounding box overlaps each cell
+struct EdgeGrid {
+   x0: f64,
+   y0: f64,
+   cell: f64,
+   nx: usize,
+   ny: usize,
+   cells: Vec<Vec<usize>>
+}
+
+impl EdgeGrid {
+   fn new(edges: &[(usize, usize, Point, Point)]) -> Self {
+      let (mut xmin, mut ymin, mut xmax, mut ymax) = (f64::MAX, f64::MAX, f64::MIN, f64::MIN);
+      for (_, _, a, _) in edges {
+         xmin = xmin.min(a.x);
+         ymin = ymin.min(a.y);
+         xmax = xmax.max(a.x);
+         ymax = ymax.max(a.y);
+      }
+      let (w, h) = ((xmax - xmin).max(f64::MIN_POSITIVE), (ymax - ymin).max(f64::MIN_POSITIVE));
+      let cell = (w * h / edges.len() as f64).sqrt().max(w.max(h) / 4096.0);
+      let nx = ((w / cell).ceil() as usize).max(1);
+      let ny = ((h / cell).ceil() as usize).max(1);
+      let mut grid = EdgeGrid{x0: xmin, y0: ymin, cell, nx, ny, cells: vec![Vec::new(); nx * ny]};
+      for (e, (_, _, a, b)) in edges.iter().enumerate() {
+         let (i0, j0, i1, j1) = grid.range(*a, *b);
+         for j in j0..=j1 {
+            for i in i0..=i1 {
+               grid.cells[j * nx + i].push(e);
+            }
+         }
+      }
+      grid
+   }
+   /// Range of cells (i0, j0, i1, j1) that overlap the bounding box of
+   /// the segment
+   fn range(&self, a: Point, b: Point) -> (usize, usize, usize, usize) {
+      let i = |x: f64| (((x - self.x0) / self.cell).max(0.0) as usize).min(self.nx - 1);
+      let j = |y: f64| (((y - self.y0) / self.cell).max(0.0) as usize).min(self.ny - 1);
+      (i(a.x.min(b.x)), j(a.y.min(b.y)), i(a.x.max(b.x)), j(a.y.max(b.y)))
+   }
+}
+
+/// Crossings of the edges of the polygons, but for adjacent edges of the
+/// same polygon. Each edge includes its start point and not its end
+/// point, so a crossing at a vertex is found once
+fn find_crossings(polygons: &[Polygon]) -> Vec<Crossing> {
+   let edges: Vec<(usize, usize, Point, Point)> = polygons.iter().enumerate()
+      .flat_map(|(k, p)| (0..p.vertices.len()).map(move |i| {
+         let (a, b) = p.edge(i);
+         (k, i, a, b)
+      })).collect();
+   let mut crossings = Vec::<Crossing>::new();
+   if edges.is_empty() {
+      return crossings;
+   }
+   let grid = EdgeGrid::new(&edges);
+   for (c, cell) in grid.cells.iter().enumerate() {
+      let (ci, cj) = (c % grid.nx, c / grid.nx);
+      for (m, &e) in cell.iter().enumerate() {
+         for &f in &cell[m + 1..] {
+            let ((ka, ia, a0, a1), (kb, ib, b0, b1)) = (edges[e], edges[f]);
+            // Each pair is tested in the first cell common to both
+            let (ra, rb) = (grid.range(a0, a1), grid.range(b0, b1));
+            if (ra.0.max(rb.0), ra.1.max(rb.1)) != (ci, cj) {
+               continue;
+            }
+            let n = polygons[ka].vertices.len();
+            if ka == kb && (ia.abs_diff(ib) == 1 || ia.abs_diff(ib) == n - 1) {
+               continue;
+            }
+            let (dax, day) = (a1.x - a0.x, a1.y - a0.y);
+            let (dbx, dby) = (b1.x - b0.x, b1.y - b0.y);
+            let det = dax * dby - day * dbx;
+            if det.abs() <= 1e-12 * dax.hypot(day) * dbx.hypot(dby) {
+               continue;
+            }
+            let t = ((b0.x - a0.x) * dby - (b0.y - a0.y) * dbx) / det;
+            let u = ((b0.x - a0.x) * day - (b0.y - a0.y) * dax) / det;
+            if (0.0..1.0).contains(&t) && (0.0..1.0).contains(&u) {
+               crossings.push(Crossing{edges: [(ka, ia, t), (kb, ib, u)], 
+                  point: Point::new(a0.x + t * dax, a0.y + t * day)});
+            }
+         }
+      }
+   }
+   crossings
+}
+
+/// Regions where the winding number of the polygons is not zero. The
+/// polygons may cross themselves and each other. Every crossing is
+/// resolved joining each incoming edge with the outgoing edge of the
+/// other one, which keeps the winding numbers and leaves loops that
+/// don't cross. A loop with winding zero on one side and not on the
+/// other is the outer edge of a region or one of its holes; the others,
+/// as the swallowtails of offset lines on the inner side of tight
+/// curves, which are covered twice, are dropped
+pub fn nonzero_regions(polygons: &[Polygon]) -> Vec<Region> {
+   let crossings = find_crossings(polygons);
+   // Vertices with the crossings inserted and the positions of each
+   // crossing in them
+   let mut on_edge: Vec<Vec<Vec<(f64, usize, usize)>>> = polygons.iter()
+      .map(|p| vec![Vec::new(); p.vertices.len()]).collect();
+   for (c, crossing) in crossings.iter().enumerate() {
+      for (side, &(k, i, t)) in crossing.edges.iter().enumerate() {
+         on_edge[k][i].push((t, c, side));
+      }
+   }
+   let mut points = Vec::<Point>::new();
+   let mut next = Vec::<usize>::new();
+   let mut positions = vec![[0; 2]; crossings.len()];
+   for (k, polygon) in polygons.iter().enumerate() {
+      let start = points.len();
+      for (i, v) in polygon.vertices.iter().enumerate() {
+         points.push(*v);
+         on_edge[k][i].sort_by(|a, b| a.0.total_cmp(&b.0));
+         for &(_, c, side) in &on_edge[k][i] {
+            positions[c][side] = points.len();
+            points.push(crossings[c].point);
+         }
+      }
+      next.extend(start + 1..points.len());
+      if points.len() > start {
+         next.push(start);
+      }
+   }
+   for [p, q] in positions {
+      next.swap(p, q);
+   }
+   // Loops, with their sign and a point of their edge
+   let mut loops = Vec::<(Polygon, f64, Point)>::new();
+   let mut visited = vec![false; points.len()];
+   for first in 0..points.len() {
+      let mut vertices = Vec::<Point>::new();
+      let mut p = first;
+      while !visited[p] {
+         visited[p] = true;
+         vertices.push(points[p]);
+         p = next[p];
+      }
+      if vertices.len() < 3 {
+         continue;
+      }
+      let polygon = Polygon::new(vertices);
+      let area = polygon.signed_area();
+      if area == 0.0 {
+         continue;
+      }
+      let i = (0..polygon.vertices.len()).max_by(|&i, &j| {
+         let (a, b) = (polygon.edge(i), polygon.edge(j));
+         a.0.dist_to(a.1).total_cmp(&b.0.dist_to(b.1))
+      }).unwrap();
+      let (a, b) = polygon.edge(i);
+      loops.push((polygon, area.signum(), Point::new((a.x + b.x) / 2.0, (a.y + b.y) / 2.0)));
+   }
+   // Loops that contain each loop and winding numbers out and in
+   let boxes: Vec<(f64, f64, f64, f64)> = loops.iter().map(|l| l.0.vertices.iter()
+      .fold((f64::MAX, f64::MAX, f64::MIN, f64::MIN), |b, v| (b.0.min(v.x), b.1.min(v.y), b.2.max(v.x), b.3.max(v.y))))
+      .collect();
+   let containers: Vec<Vec<usize>> = loops.iter().enumerate().map(|(i, l)| {
+      let p = l.2;
+      (0..loops.len()).filter(|&j| {
+         let b = boxes[j];
+         j != i && p.x >= b.0 && p.y >= b.1 && p.x <= b.2 && p.y <= b.3 && loops[j].0.contains(p)
+      }).collect()
+   }).collect();
+   let winding: Vec<(f64, f64)> = loops.iter().zip(&containers).map(|(l, c)| {
+      let out = c.iter().map(|&j| loops[j].1).sum::<f64>();
+      (out, out + l.1)
+   }).collect();
+   let mut regions = Vec::<Region>::new();
+   let mut region_of = vec![usize::MAX; loops.len()];
+   for (i, l) in loops.iter().enumerate() {
+      if winding[i].0 == 0.0 && winding[i].1 != 0.0 {
+         region_of[i] = regions.len();
+         regions.push(Region{outer: l.0.clone(), holes: Vec::new()});
+      }
+   }
+   for (i, l) in loops.iter().enumerate() {
+      if winding[i].0 != 0.0 && winding[i].1 == 0.0 {
+         // Hole of the smallest outer loop that contains it
+         let outer = containers[i].iter().filter(|&&j| region_of[j] != usize::MAX)
+            .min_by(|&&a, &&b| loops[a].0.area().total_cmp(&loops[b].0.area()));
+         if let Some(&j) = outer {
+            regions[region_of[j]].holes.push(l.0.clone());
+         }
+      }
+   }
+   regions
+}
+
+/// Intersection of the segments a0-a1 and b0-b1. None if they don't
+/// cross or are parallel
+pub fn segment_intersection(a0: Point, a1: Point, b0: Point, b1: Point) -> Option<Point> {
+   let (dax, day) = (a1.x - a0.x, a1.y - a0.y);
+   let (dbx, dby) = (b1.x - b0.x, b1.y - b0.y);
+   let det = dax * dby - day * dbx;
+   if det.abs() < 1e-12 {
+      return None;
+   }
+   let t = ((b0.x - a0.x) * dby - (b0.y - a0.y) * dbx) / det;
+   let u = ((b0.x - a0.x) * day - (b0.y - a0.y) * dax) / det;
+   if (0.0..=1.0).contains(&t) && (0.0..=1.0).contains(&u) {
+      Some(Point::new(a0.x + t * dax, a0.y + t * day))
+   } else {
+      None
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use crate::eq001;
+
+   #[test]
+   fn test_polygon() {
+      let square = Polygon::new(vec![Point::new(0.0, 0.0), Point::new(10.0, 0.0),
+         Point::new(10.0, 10.0), Point::new(0.0, 10.0)]);
+      assert!(eq001(square.signed_area(), 100.0));
+      assert!(eq001(square.perimeter(), 40.0));
+      assert!(square.contains(Point::new(5.0, 5.0)));
+      assert!(!square.contains(Point::new(15.0, 5.0)));
+      let mut vertices = square.vertices.clone();
+      vertices.reverse();
+      assert!(eq001(Polygon::new(vertices).signed_area(), -100.0));
+      assert!(square.is_simple());
+   }
+   #[test]
+   fn test_segment_intersection() {
+      let p = segment_intersection(Point::new(0.0, 0.0), Point::new(10.0, 10.0), 
+         Point::new(0.0, 10.0), Point::new(10.0, 0.0)).unwrap();
+      assert!(eq001(p.x, 5.0) && eq001(p.y, 5.0));
+      assert!(segment_intersection(Point::new(0.0, 0.0), Point::new(1.0, 1.0), 
+         Point::new(0.0, 10.0), Point::new(10.0, 0.0)).is_none());
+   }
+   #[test]
+   fn test_regions() {
+      // Bow tie with lobes of 2.5 and 22.5, crossing at (2.5, 1.5)
+      let polygon = Polygon::new(vec![Point::new(0.0, 0.0), Point::new(10.0, 6.0),
+         Point::new(10.0, 0.0), Point::new(0.0, 2.0)]);
+      assert!(!polygon.is_simple());
+      let mut regions = polygon.regions();
+      regions.sort_by(|a, b| a.area().total_cmp(&b.area()));
+      assert_eq!(regions.len(), 2);
+      assert!(eq001(regions[0].area(), 2.5) && eq001(regions[1].area(), 22.5));
+      assert!(regions.iter().all(|r| r.outer.is_simple() && r.holes.is_empty()));
+      // Square with a loop inside, in the same direction: the inner
+      // square is covered twice and is dropped, and the corner out of
+      // the crossing at (2, 8) isn't in the region
+      let polygon = Polygon::new(vec![Point::new(0.0, 0.0), Point::new(10.0, 0.0), Point::new(10.0, 10.0),
+         Point::new(2.0, 10.0), Point::new(2.0, 4.0), Point::new(6.0, 4.0), Point::new(6.0, 8.0),
+         Point::new(0.0, 8.0)]);
+      let regions = polygon.regions();
+      assert_eq!(regions.len(), 1);
+      assert!(regions[0].holes.is_empty());
+      assert!(eq001(regions[0].area(), 96.0));
+      assert!(regions[0].outer.contains(Point::new(4.0, 6.0)));
+   }
+   #[test]
+   fn test_nonzero_regions() {
+      // Ring from two squares, the inner one reversed
+      let outer = Polygon::new(vec![Point::new(0.0, 0.0), Point::new(10.0, 0.0),
+         Point::new(10.0, 10.0), Point::new(0.0, 10.0)]);
+      let inner = Polygon::new(vec![Point::new(2.0, 2.0), Point::new(2.0, 8.0),
+         Point::new(8.0, 8.0), Point::new(8.0, 2.0)]);
+      let regions = nonzero_regions(&[outer.clone(), inner]);
+      assert_eq!(regions.len(), 1);
+      assert_eq!(regions[0].holes.len(), 1);
+      assert!(eq001(regions[0].area(), 64.0));
+      // Long polygon with many crossings
+      let n = 2000;
+      let mut vertices: Vec<Point> = (0..n).map(|i| Point::new(i as f64, if i % 2 == 0 { 0.0 } else { 1.0 })).collect();
+      vertices.extend((0..n).rev().map(|i| Point::new(i as f64, if i % 2 == 0 { 1.0 } else { 0.0 })));
+      let polygon = Polygon::new(vertices);
+      assert_eq!(find_crossings(std::slice::from_ref(&polygon)).len(), n - 1);
+      assert!(eq001(polygon.regions().iter().map(|r| r.area()).sum::<f64>(), (n - 1) as f64 / 2.0));
+   }
+}
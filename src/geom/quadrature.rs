@@ -0,0 +1,55 @@
+//! Numerical integration with the composite Gauss-Legendre rule
+
+/// Positive nodes and weights of the 10 points Gauss-Legendre rule
+/// in [-1, 1]. The negative nodes are symmetric with the same weights
+const GAUSS_LEGENDRE_10: [(f64, f64); 5] = [
+   (0.9739065285171717, 0.06667134430868814),
+   (0.8650633666889845, 0.1494513491505806),
+   (0.6794095682990244, 0.21908636251598204),
+   (0.4333953941292472, 0.26926671930999635),
+   (0.14887433898163122, 0.29552422471475287),
+];
+
+/// Integral of f between a and b with the 10 points Gauss-Legendre
+/// rule applied over a number of equal panels.
+/// It is exact for polynomials up to degree 19 in each panel
+pub fn gauss_legendre<F>(f: F, a: f64, b: f64, panels: usize) -> f64
+   where F: Fn(f64) -> f64 {
+   if panels == 0 {
+      panic!("Number of panels must be greater than zero");
+   }
+   let h = (b - a) / panels as f64;
+   let mut sum = 0.0;
+   for i in 0..panels {
+      let mid = a + (i as f64 + 0.5) * h;
+      let half = h / 2.0;
+      for (node, weight) in GAUSS_LEGENDRE_10 {
+         sum += weight * (f(mid - half*node) + f(mid + half*node)) * half;
+      }
+   }
+   sum
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use crate::eq;
+   use std::f64::consts::PI;
+
+   #[test]
+   fn test_gauss_legendre() {
+      // Polynomial of degree 19 is exact with one panel
+      let x = gauss_legendre(|x| 20.0*x.powi(19), 0.0, 1.0, 1);
+      assert!(eq(x, 1.0));
+      let x = gauss_legendre(|x| x.sin(), 0.0, PI, 4);
+      assert!(eq(x, 2.0));
+      // Reversed interval
+      let x = gauss_legendre(|x| x.exp(), 1.0, 0.0, 2);
+      assert!(eq(x, 1.0 - 1.0_f64.exp()));
+   }
+   #[test]
+   #[should_panic]
+   fn test_gauss_legendre_panic() {
+      let _x = gauss_legendre(|x| x, 0.0, 1.0, 0);
+   }
+}
@@ -1,5 +1,6 @@
 use std::f64::consts::PI;
 use crate::*;
+use crate::tolerances::Tolerances;
 
 use super::point::Point;
 
@@ -32,8 +33,13 @@ impl Vector {
    
    /// It returns a new Vector with same direction and length 1
    pub fn unit_vector(&self) -> Self {
+      self.unit_vector_with_tolerances(&Tolerances::default())
+   }
+   /// It returns a new Vector with same direction and length 1.
+   /// It panics if the length is zero within the linear tolerance
+   pub fn unit_vector_with_tolerances(&self, tol: &Tolerances) -> Self {
       let length = self.length();
-      if eq001(length, 0.0) {
+      if tol.is_zero_length(length) {
          panic!("Trying unit_vector() of vector with length() zero")
       }
       Vector::new(self.vx/length, self.vy/length)
@@ -52,41 +58,44 @@ impl Vector {
 
    /// Angle in radians with X axis 
    pub fn angle(&self) -> f64 {
-      let result:f64;
-      if eq001(self.vx, 0.0) && eq001(self.vy, 0.0) {
-         result = 0.0_f64;         
+      self.angle_with_tolerances(&Tolerances::default())
+   }
+   /// Angle in radians with X axis, between 0 and 2*PI.
+   /// A vector with length zero within the linear tolerance
+   /// has angle zero. Directions closer to the axis than the
+   /// angular tolerance are snapped to the axis
+   pub fn angle_with_tolerances(&self, tol: &Tolerances) -> f64 {
+      let length = self.length();
+      if tol.is_zero_length(length) {
+         return 0.0_f64;
+      }
+      if (self.vx/length).abs() < tol.angular {
+         if self.vy > 0.0 {
+            PI/2.0
+         } else {
+            3.0*PI/2.0
+         }
+      } else if (self.vy/length).abs() < tol.angular {
+         if self.vx < 0.0 {
+            PI
+         } else {
+            0.0
+         }
       } else {
-         if eq001(self.vx, 0.0) {
-            if self.vy > 0.0 {
-               result=PI/2.0;
+         let tangent = self.vy/self.vx;
+         let angle = tangent.atan();
+         if angle > 0.0 {
+            if self.vx<0.0 && self.vy<0.0 {
+               PI + angle
             } else {
-               result = 3.0*PI/2.0;
-            } 
-         } else if eq001(self.vy, 0.0) {
-            if self.vx < 0.0 {
-               result = PI;
-            } else {
-               result = 0.0;
+               angle
             }
+         } else if self.vx > 0.0 {
+            2.0*PI - (-angle)
          } else {
-            let tangent = self.vy/self.vx;
-            let angle = tangent.atan();
-            if angle > 0.0 {
-               if self.vx<0.0 && self.vy<0.0 {
-                  result = PI + angle;
-               } else {
-                  result = angle;
-               }
-            } else {
-               if self.vx > 0.0 {
-                  result = 2.0*PI - (-angle);
-               } else {
-                  result = PI - (-angle);
-               }
-            }
+            PI - (-angle)
          }
       }
-      result
    }
 
    /// Vector unitario que forma un ángulo en radianes hacia la derecha
@@ -108,9 +117,33 @@ mod tests {
    use super::*;
    #[test]
    fn test_from_two_points() {
-      todo!();
+      let v = Vector::from_two_points(Point::new(1.0, 1.0), Point::new(4.0, -3.0));
+      assert!(eq001(v.vx, 3.0));
+      assert!(eq001(v.vy, -4.0));
+      assert!(eq001(v.length(), 5.0));
+      let v = Vector::from_two_points(Point::new(1.0, 1.0), Point::new(1.0, 1.0));
+      assert!(eq001(v.length(), 0.0));
    }   
    #[test]
+   fn test_angle_with_tolerances() {
+      let v = Vector::new(1.0, 0.0005);
+      assert!(!eq(v.angle(), 0.0));
+      let tol = Tolerances::new(0.001, 0.001, 0.001);
+      assert!(eq(v.angle_with_tolerances(&tol), 0.0));
+      // Small vectors keep their direction
+      let v = Vector::new(0.0, -0.01);
+      assert!(eq(v.angle(), 3.0*PI/2.0));
+      let tol = Tolerances::new(0.1, 1e-6, 0.001);
+      assert!(eq(v.angle_with_tolerances(&tol), 0.0));
+   }
+   #[test]
+   #[should_panic]
+   fn test_unit_vector_with_tolerances_panic() {
+      let v = Vector::new(0.005, 0.0);
+      let tol = Tolerances::new(0.01, 1e-6, 0.001);
+      let _w = v.unit_vector_with_tolerances(&tol);
+   }
+   #[test]
    fn test_right_angle_vector() {
       let v = Vector::new(1.0, 0.0);
       let angle = PI/4.0;
@@ -315,27 +348,27 @@ mod tests {
    #[test]
    fn test_angle() {
       let v = Vector::new(0.0, 0.0);
-      assert_eq!(true, v.angle() == 0.0);
+      assert!(v.angle() == 0.0);
       let v = Vector::new(1.0, 0.0);
-      assert_eq!(true, v.angle() == 0.0);
+      assert!(v.angle() == 0.0);
       let v = Vector::new(-1.0, 0.0);
-      assert_eq!(true, v.angle() == PI);
+      assert!(v.angle() == PI);
       let v = Vector::new(0.0, 1.0);
-      assert_eq!(true, v.angle() == PI/2.0);
+      assert!(v.angle() == PI/2.0);
       let v = Vector::new(0.0, -1.0);
-      assert_eq!(true, v.angle() == 3.0*PI/2.0);
+      assert!(v.angle() == 3.0*PI/2.0);
       let v = Vector::new(1.0, 1.0);
-      assert_eq!(true, v.angle() == PI/4.0);
+      assert!(v.angle() == PI/4.0);
       let v = Vector::new(1.0, -1.0);
-      assert_eq!(true, v.angle() == 2.0*PI - PI/4.0);
+      assert!(v.angle() == 2.0*PI - PI/4.0);
       let v = Vector::new(-1.0, 1.0);
-      assert_eq!(true, v.angle() == PI/2.0 + PI/4.0);
+      assert!(v.angle() == PI/2.0 + PI/4.0);
       let v = Vector::new(-1.0, -1.0);
-      assert_eq!(true, v.angle() == PI + PI/4.0);
+      assert!(v.angle() == PI + PI/4.0);
       // The following test fails if not uses approx_eq!(), 
       // caused for the problem with ==
       let v = Vector::new(0.15*6.0+0.10, 1.0);
       println!("{} {}", v.angle(), PI/4.0);
-      assert_eq!(true, eq001(v.angle(), PI/4.0));
+      assert!(eq001(v.angle(), PI/4.0));
    }
 }
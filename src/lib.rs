@@ -4,38 +4,32 @@
 
 use std::f64::consts::PI;
 
+mod crosssection;
+mod earthwork;
 mod geom;
 mod road;
+mod stakeout;
+mod terrain;
+mod tolerances;
 
-// Value used in equal comparisons with eq001
+// Value used in equal comparisons with eq. It is a pure numerical
+// tolerance, geometric checks use tolerances::Tolerances
 const EPS:f64 = 1e-8;
 
 /// Compares two f64 and returns true if
 /// the diference is less than EPS in absolute value
 pub fn eq(x: f64, y:f64) -> bool {
-   if (x-y).abs() < EPS {
-      true
-   } else {
-      false
-   }
+   (x-y).abs() < EPS
 }
 /// Compares two f64 and returns true if
 /// the diference is less than 0.001 in absolute value
 pub fn eq001(x:f64, y:f64) -> bool {
-   if (x-y).abs() < 0.001 {
-      true
-   } else {
-      false
-   }
+   (x-y).abs() < 0.001
 }
 /// Compares two f64 and returns true if
-/// the diference is less than 0.1 in absolute value
+/// the diference is less than 0.01 in absolute value
 pub fn eq01(x:f64, y:f64) -> bool {
-   if (x-y).abs() < 0.01 {
-      true
-   } else {
-      false
-   }
+   (x-y).abs() < 0.01
 }
 pub fn factorial(num: u64) -> u64 {
    match num {
@@ -46,7 +40,13 @@ pub fn factorial(num: u64) -> u64 {
 }
 /// Devuelve las soluciones de una ecuación de segundo grado ax2 + bx + c = 0
 pub fn second_degree_equation(a: f64, b: f64, c: f64) -> (f64, f64) {
-   if eq001(a, 0.0) && eq001(b, 0.0) && eq001(c, 0.0) {
+   second_degree_equation_with_tolerances(a, b, c, &tolerances::Tolerances::default())
+}
+/// Soluciones de ax2 + bx + c = 0. Los coeficientes son nulos si lo
+/// son dentro de la tolerancia lineal
+pub fn second_degree_equation_with_tolerances(a: f64, b: f64, c: f64, 
+   tol: &tolerances::Tolerances) -> (f64, f64) {
+   if tol.is_zero_length(a) && tol.is_zero_length(b) && tol.is_zero_length(c) {
       panic!("Coeficientes nulos!");
    }
    let disc = b*b - 4.0*a * c;
@@ -58,6 +58,53 @@ pub fn second_degree_equation(a: f64, b: f64, c: f64) -> (f64, f64) {
    let x2 = (-b - disc)/2.0/a;
    (x1, x2)
 }
+/// Máximo número de iteraciones de solve_newton_bisection()
+pub const MAX_SOLVER_ITERATIONS: usize = 100;
+
+/// Finds a root of f in the interval [lo, hi] using Newton's method
+/// safeguarded with bisection. The closure returns the value of the
+/// function and its derivative. The Newton step is rejected when it
+/// falls outside the current bracket, and bisection is used instead.
+///
+/// It returns an error if f(lo) and f(hi) have the same sign or if the
+/// root isn't found with precision xtol in MAX_SOLVER_ITERATIONS
+pub fn solve_newton_bisection<F>(f: F, lo: f64, hi: f64, xtol: f64) -> Result<f64, String> 
+   where F: Fn(f64) -> (f64, f64) {
+   let (flo, _) = f(lo);
+   let (fhi, _) = f(hi);
+   if flo == 0.0 {
+      return Ok(lo);
+   }
+   if fhi == 0.0 {
+      return Ok(hi);
+   }
+   if flo.signum() == fhi.signum() {
+      return Err(format!("Root not bracketed in [{}, {}]", lo, hi));
+   }
+   // a is always the end of the bracket where f is negative
+   let (mut a, mut b) = if flo < 0.0 { (lo, hi) } else { (hi, lo) };
+   let mut x = (lo + hi) / 2.0;
+   for _i in 0..MAX_SOLVER_ITERATIONS {
+      let (fx, dfx) = f(x);
+      if fx == 0.0 {
+         return Ok(x);
+      }
+      if fx < 0.0 {
+         a = x;
+      } else {
+         b = x;
+      }
+      let newton = x - fx / dfx;
+      let inside = dfx != 0.0 && newton.is_finite() 
+         && (newton - a) * (newton - b) < 0.0;
+      let next = if inside { newton } else { (a + b) / 2.0 };
+      if (next - x).abs() < xtol {
+         return Ok(next);
+      }
+      x = next;
+   }
+   Err(format!("No convergence after {} iterations", MAX_SOLVER_ITERATIONS))
+}
 /// If the angle's absolute value is greater than 2*PI, 
 /// it is changed by its equivalent between 0 and 2*PI. 
 /// If the angle is negative, it is changed by its 
@@ -68,7 +115,7 @@ pub fn normalize_radian(rad_value: f64) -> f64 {
       newangle = rad_value % (2.0*PI);
    }
    if newangle < 0.0 {
-      newangle = 2.0*PI + newangle;
+      newangle += 2.0*PI;
    }
    newangle  
 }
@@ -82,7 +129,7 @@ pub fn normalize_360(deg_value: f64) -> f64 {
       newangle = deg_value % (360.0);
    }
    if newangle < 0.0 {
-      newangle = 360.0 + newangle;
+      newangle += 360.0;
    }
    newangle  
 }
@@ -124,6 +171,15 @@ pub fn rotation(x:f64, y: f64, theta: f64) -> (f64, f64) {
    let yprim = -x*theta.sin() + y* theta.cos();
    (xprim, yprim)
 }
+/// Formats a station (PK) in the usual form kilometers+meters,
+/// with three decimals: 1234.5 -> "1+234.500"
+pub fn format_station(station: f64) -> String {
+   let millis = (station.abs() * 1000.0).round() as u64;
+   let km = millis / 1_000_000;
+   let m = (millis % 1_000_000) as f64 / 1000.0;
+   let sign = if station < 0.0 && millis > 0 { "-" } else { "" };
+   format!("{}{}+{:07.3}", sign, km, m)
+}
 
 
 #[cfg(test)]
@@ -159,11 +215,11 @@ mod tests {
    #[test]
    fn test_factorial() {
       let n = 0;
-      assert_eq!(true, factorial(n)==1);
+      assert!(factorial(n)==1);
       let n = 1;
-      assert_eq!(true, factorial(n)==1);
+      assert!(factorial(n)==1);
       let n = 5;
-      assert_eq!(true, factorial(n)==120);
+      assert!(factorial(n)==120);
       let x = factorial(5) as f64;
       println!("{}", x*2.0);
    }
@@ -183,17 +239,45 @@ mod tests {
    #[should_panic]
    fn test_second_degree_equation_panic_1() {
       let (a, b, c) = (0.0f64, 0.0f64, 0.0f64);
-      let (x1, x2) = second_degree_equation(a, b, c);
+      let (x1, _x2) = second_degree_equation(a, b, c);
       assert!(eq001(0.0, x1));
    }
    #[test]
    #[should_panic]
+   fn test_second_degree_equation_with_tolerances_panic() {
+      let tol = tolerances::Tolerances::new(0.01, 1e-6, 0.001);
+      let (_x1, _x2) = second_degree_equation_with_tolerances(0.005, 0.0, -0.005, &tol);
+   }
+   #[test]
+   #[should_panic]
    fn test_second_degree_equation_panic_2() {
       let (a, b, c) = (10.0f64, 4.0f64, 1.0f64);
-      let (x1, x2) = second_degree_equation(a, b, c);
+      let (x1, _x2) = second_degree_equation(a, b, c);
       assert!(eq001(0.0, x1));
    }
 
+   #[test]
+   fn test_solve_newton_bisection() {
+      let f = |x: f64| (x*x - 2.0, 2.0*x);
+      let x = solve_newton_bisection(f, 0.0, 2.0, 1e-12).unwrap();
+      assert!(eq(x, 2.0_f64.sqrt()));
+      // Decreasing function
+      let f = |x: f64| (1.0 - x.exp(), -x.exp());
+      let x = solve_newton_bisection(f, -1.0, 3.0, 1e-12).unwrap();
+      assert!(eq(x, 0.0));
+      // Zero derivative at the start point falls back to bisection
+      let f = |x: f64| (x.powi(3) - 1.0, 3.0*x*x);
+      let x = solve_newton_bisection(f, -2.0, 2.0, 1e-12).unwrap();
+      assert!(eq(x, 1.0));
+   }
+   #[test]
+   fn test_solve_newton_bisection_errors() {
+      let f = |x: f64| (x*x + 1.0, 2.0*x);
+      assert!(solve_newton_bisection(f, -1.0, 1.0, 1e-12).is_err());
+      // Discontinuous function: the bracket shrinks but never converges
+      let f = |x: f64| (if x < 0.3 { -1.0 } else { 1.0 }, 0.0);
+      assert!(solve_newton_bisection(f, 0.0, 1.0, 0.0).is_err());
+   }
    #[test]
    fn test_rad2deg() {
       let x = 0.0f64;
@@ -285,7 +369,15 @@ mod tests {
       let y = 1.0;
       let theta = deg2rad(45.0);
       let (xprim, yprim) = rotation(x, y, theta);
-      assert!(eq001(xprim, 1.4142));
+      assert!(eq001(xprim, 2.0_f64.sqrt()));
       assert!(eq001(yprim, 0.0));
    }
+   #[test]
+   fn test_format_station() {
+      assert_eq!(format_station(1234.5), "1+234.500");
+      assert_eq!(format_station(0.0), "0+000.000");
+      assert_eq!(format_station(25.0004), "0+025.000");
+      assert_eq!(format_station(12999.9999), "13+000.000");
+      assert_eq!(format_station(-20.0), "-0+020.000");
+   }
 }
@@ -2,6 +2,19 @@ pub mod hsection;
 pub mod htangent;
 pub mod hcircle;
 pub mod hclothoid;
+pub mod htransition;
+pub mod hcubicparabola;
 pub mod roadaxis;
+pub mod roadaxis3d;
+pub mod vsection;
+pub mod vgrade;
+pub mod vparabola;
+pub mod vcircle;
+pub mod vasymmetricparabola;
+pub mod verticalalignment;
+pub mod sightdistance;
+pub mod superelevation;
+pub mod superelevationdesign;
+pub mod drainage;
 
 
@@ -0,0 +1,246 @@
+/// Zonas de drenaje deficiente de la calzada.
+///
+/// Water runs on the pavement along the line of maximum slope, whose
+/// gradient is the resultant of the longitudinal grade and the cross
+/// slope:
+///
+/// s = sqrt(i^2 + q^2)
+///
+/// Where the superelevation passes through zero on a transition and
+/// the longitudinal grade is small the resultant is small too and the
+/// water ponds. The longitudinal grade of an edge is the grade of the
+/// axis plus the relative gradient of the edge. On the centreline the
+/// cross slope is the one of the side where the pavement falls
+use std::fmt;
+use crate::format_station;
+use crate::road::verticalalignment::VerticalAlignment;
+use crate::road::superelevation::Superelevation;
+
+/// Line of the pavement where the drainage is checked
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DrainageLine {
+   LeftEdge,
+   Centreline,
+   RightEdge
+}
+
+impl fmt::Display for DrainageLine {
+   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+      match self {
+         DrainageLine::LeftEdge => write!(f, "Left edge"),
+         DrainageLine::Centreline => write!(f, "Centreline"),
+         DrainageLine::RightEdge => write!(f, "Right edge")
+      }
+   }
+}
+
+/// Stretch of a line of the pavement with the drainage gradient below
+/// the threshold
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DrainageZone {
+   pub line: DrainageLine,
+   pub start_station: f64,
+   pub end_station: f64,
+   /// Minimum drainage gradient found in the zone
+   pub min_gradient: f64
+}
+
+impl DrainageZone {
+   pub fn length(&self) -> f64 {
+      self.end_station - self.start_station
+   }
+}
+
+impl fmt::Display for DrainageZone {
+   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+      write!(f, "{}: {} to {}, minimum gradient {:.2}%", self.line, format_station(self.start_station),
+         format_station(self.end_station), self.min_gradient * 100.0)
+   }
+}
+
+const LINES: [DrainageLine; 3] = [DrainageLine::LeftEdge, DrainageLine::Centreline, DrainageLine::RightEdge];
+
+/// Drainage gradients (left edge, centreline, right edge) at the
+/// station, for edges at distance width from the axis
+pub fn drainage_gradients_at(profile: &VerticalAlignment, superelevation: &Superelevation,
+   station: f64, width: f64) -> (f64, f64, f64) {
+   let grade = profile.grade_at(station);
+   let (left, right) = superelevation.cross_slopes_at(station);
+   let (left_relative, right_relative) = superelevation.relative_gradient_at(station, width);
+   let centre_cross = (-left).max(-right).max(0.0);
+   ((grade + left_relative).hypot(left), grade.hypot(centre_cross), (grade + right_relative).hypot(right))
+}
+
+/// Zones of the left edge, the centreline and the right edge where the 
+/// drainage gradient is below the threshold. The lines are checked at the
+/// stations multiple of interval, the section starts, the superelevation
+/// keys and the stations where the gradient is lowest: where a cross
+/// slope or the grade passes through zero. The ends of the zones are
+/// refined by bisection
+pub fn poor_drainage_zones(profile: &VerticalAlignment, superelevation: &Superelevation,
+   width: f64, threshold: f64, interval: f64) -> Result<Vec<DrainageZone>, String> {
+   if interval <= 0.0 || profile.tolerances.is_zero_length(interval) {
+      return Err(String::from("Interval must be greater than zero"));
+   }
+   if profile.sections.is_empty() {
+      return Err(String::from("Vertical alignment without sections"));
+   }
+   if superelevation.keys.is_empty() {
+      return Err(String::from("Superelevation without keys"));
+   }
+   let (start, end) = (profile.start_station(), profile.end_station());
+   let mut stations: Vec<f64> = profile.sections.iter().map(|s| s.start_station()).collect();
+   stations.push(end);
+   stations.extend(superelevation.keys.iter().map(|k| k.station).filter(|s| *s > start && *s < end));
+   stations.extend(superelevation.zero_slope_stations().into_iter().filter(|s| *s > start && *s < end));
+   stations.extend(profile.high_low_points().iter().map(|p| p.station));
+   let mut k = (start / interval).ceil();
+   while k * interval < end {
+      stations.push(k * interval);
+      k += 1.0;
+   }
+   stations.sort_by(|a, b| a.total_cmp(b));
+   stations.dedup_by(|a, b| profile.tolerances.eq_linear(*a, *b));
+
+   let gradient = |line: DrainageLine, station: f64| {
+      let (left, centre, right) = drainage_gradients_at(profile, superelevation, station, width);
+      match line {
+         DrainageLine::LeftEdge => left,
+         DrainageLine::Centreline => centre,
+         DrainageLine::RightEdge => right
+      }
+   };
+   // Station between s0 (in a zone or not) and s1 (the opposite) where
+   // the gradient crosses the threshold
+   let boundary = |line: DrainageLine, mut s0: f64, mut s1: f64| {
+      let inside = gradient(line, s0) < threshold;
+      while !profile.tolerances.eq_linear(s0, s1) {
+         let middle = (s0 + s1) / 2.0;
+         if (gradient(line, middle) < threshold) == inside {
+            s0 = middle;
+         } else {
+            s1 = middle;
+         }
+      }
+      (s0 + s1) / 2.0
+   };
+   let mut zones = Vec::<DrainageZone>::new();
+   for line in LINES {
+      let mut current: Option<DrainageZone> = None;
+      let mut previous = stations[0];
+      for &station in &stations {
+         let g = gradient(line, station);
+         match (current.as_mut(), g < threshold) {
+            (Some(zone), true) => zone.min_gradient = zone.min_gradient.min(g),
+            (Some(zone), false) => {
+               zone.end_station = boundary(line, previous, station);
+               zones.push(*zone);
+               current = None;
+            },
+            (None, true) => {
+               let start_station = if station == stations[0] { station } else { boundary(line, previous, station) };
+               current = Some(DrainageZone{line, start_station, end_station: station, min_gradient: g});
+            },
+            (None, false) => {}
+         }
+         previous = station;
+      }
+      if let Some(zone) = current.as_mut() {
+         zone.end_station = end;
+         zones.push(*zone);
+      }
+   }
+   Ok(zones)
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use crate::eq001;
+   use crate::road::vgrade::VGrade;
+   use crate::road::vparabola::VParabola;
+   use crate::road::superelevation::SlopeTransition;
+
+   /// Grade of 0.1% and a rightward curve from 200 to 300 with 6%
+   fn sample() -> (VerticalAlignment, Superelevation) {
+      let mut va = VerticalAlignment::new();
+      va.add_vgrade(VGrade::new(0.0, 100.0, 0.001, 500.0));
+      let mut se = Superelevation::new();
+      se.add_key(120.0, -0.02, -0.02, SlopeTransition::Linear);
+      se.add_key(140.0, 0.0, -0.02, SlopeTransition::Linear);
+      se.add_key(200.0, 0.06, -0.06, SlopeTransition::Linear);
+      se.add_key(300.0, 0.06, -0.06, SlopeTransition::Linear);
+      se.add_key(360.0, -0.02, -0.02, SlopeTransition::Linear);
+      (va, se)
+   }
+
+   #[test]
+   fn test_drainage_gradients() {
+      let (va, se) = sample();
+      let (left, centre, right) = drainage_gradients_at(&va, &se, 140.0, 3.5);
+      // Left edge: flat in cross section, rising 0.001 * 3.5 on the axis grade
+      assert!(eq001(left, 0.0045));
+      assert!(eq001(centre, 0.001_f64.hypot(0.02)));
+      assert!(eq001(right, 0.001_f64.hypot(0.02)));
+   }
+   #[test]
+   fn test_poor_drainage_zones() {
+      let (va, se) = sample();
+      let zones = poor_drainage_zones(&va, &se, 3.5, 0.005, 20.0).unwrap();
+      assert_eq!(zones.len(), 2);
+      let zone = zones[0];
+      assert_eq!(zone.line, DrainageLine::LeftEdge);
+      // |q| < sqrt(0.005^2 - 0.0045^2) around the zero cross slope at 140
+      let half = (0.005_f64.powi(2) - 0.0045_f64.powi(2)).sqrt() / 0.001;
+      assert!(eq001(zone.start_station, 140.0 - half));
+      assert!(eq001(zone.end_station, 140.0 + half));
+      assert!(eq001(zone.min_gradient, 0.0045));
+      assert!(format!("{}", zone).starts_with("Left edge: 0+137.8"));
+      // Zero left cross slope at 345 on the way out of the curve, with the
+      // slope changing 0.08 in 60 m
+      let zone = zones[1];
+      assert_eq!(zone.line, DrainageLine::LeftEdge);
+      let edge_grade: f64 = 0.001 - 0.08 * 3.5 / 60.0;
+      let half = (0.005_f64.powi(2) - edge_grade.powi(2)).sqrt() / (0.08 / 60.0);
+      assert!(eq001(zone.start_station, 345.0 - half));
+      assert!(eq001(zone.end_station, 345.0 + half));
+      assert!(eq001(zone.min_gradient, -edge_grade));
+      assert!(poor_drainage_zones(&va, &se, 3.5, 0.003, 20.0).unwrap().is_empty());
+      assert!(poor_drainage_zones(&va, &se, 3.5, 0.005, 0.0).is_err());
+   }
+   #[test]
+   fn test_zones_between_samples() {
+      // Rollovers from 120 to 200 and from 300 to 380: the left cross
+      // slope is zero at 140 and 360, out of the stations every 25 m
+      let (va, _se) = sample();
+      let mut se = Superelevation::new();
+      se.add_key(120.0, -0.02, -0.02, SlopeTransition::Linear);
+      se.add_key(200.0, 0.06, -0.06, SlopeTransition::Linear);
+      se.add_key(300.0, 0.06, -0.06, SlopeTransition::Linear);
+      se.add_key(380.0, -0.02, -0.02, SlopeTransition::Linear);
+      for interval in [20.0, 25.0] {
+         let zones = poor_drainage_zones(&va, &se, 3.5, 0.005, interval).unwrap();
+         assert_eq!(zones.len(), 2);
+         assert!(zones.iter().all(|z| z.line == DrainageLine::LeftEdge));
+         // Edge grades 0.001 + 0.0035 and 0.001 - 0.0035
+         let half = (0.005_f64.powi(2) - 0.0045_f64.powi(2)).sqrt() / 0.001;
+         assert!(eq001(zones[0].start_station, 140.0 - half));
+         assert!(eq001(zones[0].min_gradient, 0.0045));
+         let half = (0.005_f64.powi(2) - 0.0025_f64.powi(2)).sqrt() / 0.001;
+         assert!(eq001(zones[1].end_station, 360.0 + half));
+         assert!(eq001(zones[1].min_gradient, 0.0025));
+      }
+   }
+   #[test]
+   fn test_zone_at_high_point() {
+      // Crest without cross slope zero: the lowest gradient is at the
+      // high point, between the stations every 30 m
+      let mut va = VerticalAlignment::new();
+      va.add_vparabola(VParabola::new(0.0, 100.0, 0.01, -0.01, 200.0));
+      let mut se = Superelevation::new();
+      se.add_key(0.0, -0.002, -0.002, SlopeTransition::Linear);
+      let zones = poor_drainage_zones(&va, &se, 3.5, 0.0021, 30.0).unwrap();
+      assert_eq!(zones.len(), 3);
+      assert!(eq001(zones[0].min_gradient, 0.002));
+   }
+}
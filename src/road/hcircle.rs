@@ -1,6 +1,7 @@
 use crate::{normalize_360, azimuth_to_angle, angle_to_azimuth};
 use crate::geom::point::Point;
 use crate::geom::vector::Vector;
+use crate::tolerances::Tolerances;
 use super::hsection::HSection;
 use std::f64::consts::PI;
 
@@ -18,7 +19,13 @@ pub struct HCircle {
 
 impl HCircle {
    pub fn new(start_point: Point, start_azimuth: f64, radius: f64, length: f64) -> Self {
-      if crate::eq001(radius, 0.0) || length < 0.0_f64 {
+      HCircle::new_with_tolerances(start_point, start_azimuth, radius, length, &Tolerances::default())
+   }
+   /// Creates a new HCircle. The radius can't be zero within
+   /// the radius tolerance
+   pub fn new_with_tolerances(start_point: Point, start_azimuth: f64, radius: f64, 
+      length: f64, tol: &Tolerances) -> Self {
+      if tol.is_zero_radius(radius) || length < 0.0_f64 {
          panic!("Radius zero or negative length");
       }
       let az = normalize_360(start_azimuth);
@@ -27,13 +34,11 @@ impl HCircle {
    pub fn center(&self) -> Point {
       let angle = azimuth_to_angle(self.start_azimuth());
       let direction = Vector::from_angle(angle);
-      let normal: Vector;
-      
-      if self.radius > 0.0 {
-         normal = direction.right_normal_vector();
+      let normal = if self.radius > 0.0 {
+         direction.right_normal_vector()
       } else {
-         normal = direction.left_normal_vector();
-      }
+         direction.left_normal_vector()
+      };
       let distance = self.radius.abs();
       let x = self.start_x() + distance * normal.angle().cos();
       let y = self.start_y() + distance * normal.angle().sin();
@@ -79,18 +84,20 @@ impl HSection for HCircle {
       // Azimuth at point s
       let az = self.azimuth_at_s(s);
       let v = Vector::from_angle(azimuth_to_angle(az));
-      let normal: Vector;
       // normal is the vector from center to point at s
-      if self.radius > 0.0 {
-         normal = v.left_normal_vector();
+      let normal = if self.radius > 0.0 {
+         v.left_normal_vector()
       } else {
-         normal = v.right_normal_vector();
-      }
+         v.right_normal_vector()
+      };
       let direction = normal.angle();
       let x = self.center().x + self.radius.abs()*direction.cos();
       let y = self.center().y + self.radius.abs()*direction.sin();
       Point::new(x, y)      
    }
+   fn curvature_at_s(&self, _s:f64) -> f64 {
+      1.0 / self.radius
+   }
 }
 
 #[cfg(test)]
@@ -112,6 +119,14 @@ mod tests {
       HCircle::new(Point::new(0.0, 0.0), 0.0, 100.0, -100.0);
    }
 
+   #[test]
+   #[should_panic]
+   fn test_new_with_tolerances_panic() {
+      // radius zero within the radius tolerance
+      let tol = Tolerances::new(0.001, 1e-6, 1.0);
+      HCircle::new_with_tolerances(Point::new(0.0, 0.0), 0.0, 0.5, 100.0, &tol);
+   }
+
    #[test]
    /// tests HCircle::new() and some other methods of trait HSection
    fn test_new() {
@@ -260,6 +275,12 @@ mod tests {
       assert!(eq001(end_pt.y, circle.point_at_s(length).y));
    }
    #[test]
+   fn test_curvature_at_s() {
+      let c = HCircle::new(Point::new(0.0, 0.0), 0.0, -400.0, 100.0);
+      assert!(eq001(c.curvature_at_s(50.0), -1.0/400.0));
+      assert!(eq001(c.radius_at_s(50.0), -400.0));
+   }
+   #[test]
    fn test_end_point() {
       // Q1, R>0
       let start_pt = Point::new(0.0, 400.0);
@@ -1,11 +1,9 @@
 use crate::geom::clothoid::Clothoid;
-use crate::geom::line::Line;
 use crate::geom::point::Point;
 //use crate::geom::vector::Vector;
 use crate::*;
-use crate::geom::vector::Vector;
-use super::hsection::HSection;
-use std::f64::consts::PI;
+use crate::tolerances::Tolerances;
+use super::hsection::{HSection, point_and_azimuth_from_local_origin};
 
 pub struct HClothoid {
    pub start_point: Point,
@@ -20,34 +18,46 @@ impl HClothoid {
    /// One of the radius must be zero, but not both
    pub fn new(start_point: Point, start_azimuth: f64, start_radius: f64, 
       end_radius: f64, length: f64) -> Self {
-         if eq001(0.0, start_radius) {
-            if eq001(0.0, end_radius) {
+         HClothoid::new_with_tolerances(start_point, start_azimuth, start_radius,
+            end_radius, length, &Tolerances::default())
+      }
+   /// Creates a new HClothoid checking the radius against the
+   /// radius tolerance and the length against the linear tolerance
+   pub fn new_with_tolerances(start_point: Point, start_azimuth: f64, start_radius: f64, 
+      end_radius: f64, length: f64, tol: &Tolerances) -> Self {
+         if tol.is_zero_radius(start_radius) {
+            if tol.is_zero_radius(end_radius) {
                panic!("Clothoid can't have start and end radius equal zero");
             } 
-         } else {
-            if !eq001(0.0, end_radius) {
-               panic!("Clothoid must have one of the radius equals zero");
-            } 
+         } else if !tol.is_zero_radius(end_radius) {
+            panic!("Clothoid must have one of the radius equals zero");
          }
-         if eq001(length, 0.0) {
+         if tol.is_zero_length(length) {
             panic!("Clothoid can't have length equals zero");
          }
          HClothoid{start_point, start_azimuth, start_radius, end_radius, length}
       }
 }
 impl HClothoid {
+   /// True if the clothoid starts with infinite radius, that is, if the
+   /// local origin of the clothoid is the start point. The radius
+   /// were checked against the tolerances when the section was built,
+   /// so the one nearest to zero is the infinite one
+   pub fn is_direct(&self) -> bool {
+      self.start_radius.abs() < self.end_radius.abs()
+   }
    pub fn clothoid(&self) -> Clothoid {
-      if eq(self.start_radius, 0.0) {
+      if self.is_direct() {
          Clothoid { parameter: self.parameter(), end_radius: self.end_radius}
       } else {
          // Si el origen local de la clotoide está al final de la alineación,
          // en ejes locales la clotoide tiene el radio con signo contrario
-         Clothoid { parameter: self.parameter(), end_radius: self.start_radius*-1.0}
+         Clothoid { parameter: self.parameter(), end_radius: -self.start_radius}
       }
 
    }
    pub fn radius_in_tangent_to_circle_point(&self) -> f64 {
-      if eq001(self.start_radius, 0.0) {
+      if self.is_direct() {
          self.end_radius
       } else {
          self.start_radius
@@ -69,8 +79,7 @@ impl HClothoid {
       }
       let theta = azimuth_to_angle(self.start_azimuth);
       let (_x, yc) = rotation(0.0, yc_local, -theta);
-      let yc_global = self.start_point.y + yc;
-      yc_global
+      self.start_point.y + yc
    }
    pub fn center(&self) -> Point {
       Point{x: self.center_x(), y: self.center_y()}
@@ -86,14 +95,26 @@ impl HClothoid {
    }
    pub fn parameter(&self) -> f64 {
       let r = self.radius_in_tangent_to_circle_point();
-      let a = (r.abs()* self.length).sqrt();
-      a
+      (r.abs()* self.length).sqrt()
    }
 
+   /// Point and azimuth at arc length s, computed from the
+   /// local axes of the clothoid
+   fn point_and_azimuth_at_s(&self, s: f64) -> (Point, f64) {
+      if s < 0.0 || s > self.length {
+         panic!("s less than zero or greater than length");
+      }
+      let cl = self.clothoid();
+      let local = |sigma: f64| {
+         let (x, y) = cl.xy(sigma);
+         (x, y, cl.alpha(sigma))
+      };
+      point_and_azimuth_from_local_origin(self.start_point, self.start_azimuth, 
+         self.length, self.is_direct(), local, s)
+   }
    pub fn origin_point(&self) -> Point {
       // De una alineación clotoide en la que el punto inicial no es el origen de la clotoide
       todo!();
-      Point{x: 0.0, y: 0.0} 
    }
 }
 impl HSection for HClothoid {
@@ -101,34 +122,8 @@ impl HSection for HClothoid {
       self.start_point
    }
    fn end_point(&self) -> Point {
-      if eq001(self.start_radius, 0.0) {
-         // Cálculo directo
-         let xprim = self.clothoid().x(self.length);
-         let yprim = self.clothoid().y(self.length);
-         let theta = azimuth_to_angle(self.start_azimuth);
-         let (x, y) = rotation(xprim, yprim, normalize_radian(-theta));
-         let end_x = self.start_point.x + x;
-         let end_y = self.start_point.y + y;
-         Point{x: end_x, y: end_y}
-      } else {
-         let x_local = self.clothoid().x(self.length);
-         let y_local = self.clothoid().y(self.length);
-         let theta = azimuth_to_angle(self.start_azimuth);
-         let az_vector = Vector::new(theta.cos(), theta.sin());
-         let alpha_l = self.length / 2.0 / self.start_radius;
-         // ux_vector y uy_vector van en el sentido negativo
-         // de los ejes locales
-         let ux_vector = az_vector.right_angle_vector(alpha_l);
-         let uy_vector = ux_vector.left_angle_vector(PI/2.0);
-         let qx = self.start_point.x + y_local*uy_vector.vx;
-         let qy = self.start_point.y + y_local*uy_vector.vy;
-         let _q_point = Point::new(qx, qy);
-         let olx = qx + x_local * ux_vector.vx;
-         let oly = qy + x_local * ux_vector.vy;
-         Point{x: olx, y: oly}
-         }
-         
-      }
+      self.point_at_s(self.length)
+   }
    fn start_radius(&self) -> f64 {
       self.start_radius
    }
@@ -141,27 +136,25 @@ impl HSection for HClothoid {
    fn start_azimuth(&self) -> f64 {
       self.start_azimuth
    }
+   /// The azimuth increment is L/(2R) radians, positive
+   /// in rightward clothoids
    fn end_azimuth(&self) -> f64 {
-      let inc_az: f64;
-      if self.start_radius.abs() > 0.0 {
-         inc_az = self.length() / 2.0 / self.start_radius();
-      } else {
-         inc_az = self.length() / 2.0 / self.end_radius();
-      }
-      let mut end_az = self.start_azimuth() + inc_az;
-      if end_az < 0.0 {
-         end_az = 2.0 * PI  + end_az;
-      } 
-      if end_az > 2.0 * PI {
-         end_az = end_az - 2.0 * PI;
-      }
-      end_az
+      let inc_az = rad2deg(self.length() / 2.0 / self.radius_in_tangent_to_circle_point());
+      normalize_360(self.start_azimuth() + inc_az)
    }
-   fn azimuth_at_s(&self, _s:f64) -> f64 {
-      todo!();
+   fn azimuth_at_s(&self, s:f64) -> f64 {
+      self.point_and_azimuth_at_s(s).1
    }
-   fn point_at_s(&self, _s:f64) -> Point {
-      todo!();
+   fn point_at_s(&self, s:f64) -> Point {
+      self.point_and_azimuth_at_s(s).0
+   }
+   /// The curvature varies linearly with the arc length
+   fn curvature_at_s(&self, s:f64) -> f64 {
+      if self.is_direct() {
+         s / self.length / self.end_radius
+      } else {
+         (1.0 - s / self.length) / self.start_radius
+      }
    }
 }
 #[cfg(test)]
@@ -337,6 +330,15 @@ mod tests {
    }
    #[test]
    #[should_panic]
+   /// Start radius is zero within the radius tolerance,
+   /// so both radius are zero
+   fn test_panic_new_with_tolerances() {
+      let tol = Tolerances::new(0.001, 1e-6, 0.01);
+      let _cl = HClothoid::new_with_tolerances(Point::new(0.0, 0.0), 90.0, 0.005, 
+         0.0, 80.22, &tol); 
+   }
+   #[test]
+   #[should_panic]
    /// Length can't be zero
    fn test_panic_3_new() {
       let start_point = Point::new(0.0,0.0);
@@ -356,12 +358,12 @@ mod tests {
       let length = 80.22;
       let cl = HClothoid::new(start_point, start_azimuth, start_radius, 
          end_radius, length); 
-      assert_eq!(true, eq001(start_point.x, cl.start_point.x));
-      assert_eq!(true, eq001(start_point.y, cl.start_point.y));
-      assert_eq!(true, eq001(start_azimuth, cl.start_azimuth));
-      assert_eq!(true, eq001(start_radius, cl.start_radius));
-      assert_eq!(true, eq001(end_radius, cl.end_radius));
-      assert_eq!(true, eq001(length, cl.length));
+      assert!(eq001(start_point.x, cl.start_point.x));
+      assert!(eq001(start_point.y, cl.start_point.y));
+      assert!(eq001(start_azimuth, cl.start_azimuth));
+      assert!(eq001(start_radius, cl.start_radius));
+      assert!(eq001(end_radius, cl.end_radius));
+      assert!(eq001(length, cl.length));
    }
    #[test]
    fn test_end_azimuth() {
@@ -372,12 +374,12 @@ mod tests {
       let length = 80.22;
       let cl = HClothoid::new(start_point, start_azimuth, start_radius, 
          end_radius, length); 
-      assert_eq!(true, eq001(0.08913, cl.end_azimuth()));
-      // end azimuth greater than 2PI
-      let start_azimuth = 2.0*PI - 0.05;
+      assert!(eq001(rad2deg(0.08913), cl.end_azimuth()));
+      // end azimuth greater than 360
+      let start_azimuth = 360.0 - 0.05;
       let cl = HClothoid::new(start_point, start_azimuth, start_radius, 
          end_radius, length); 
-      assert_eq!(true, eq001(0.03913, cl.end_azimuth()));
+      assert!(eq001(rad2deg(0.08913) - 0.05, cl.end_azimuth()));
       // end azimuth less than zero
       let start_point = Point::new(0.0,0.0);
       let start_azimuth = 0.0;
@@ -386,7 +388,60 @@ mod tests {
       let length = 80.22;
       let cl = HClothoid::new(start_point, start_azimuth, start_radius, 
          end_radius, length); 
-      assert_eq!(true, eq001(6.194055, cl.end_azimuth()));     
+      assert!(eq001(360.0 - rad2deg(0.08913), cl.end_azimuth()));     
+   }
+   #[test]
+   fn test_azimuth_at_s() {
+      let cl = sample_clothoid_direct_positive_radius();
+      assert!(eq001(cl.azimuth_at_s(0.0), cl.start_azimuth));
+      assert!(eq001(cl.azimuth_at_s(cl.length), cl.end_azimuth()));
+      // The azimuth increment grows with s^2
+      let s = cl.length / 2.0;
+      assert!(eq001(cl.azimuth_at_s(s) - cl.start_azimuth, rad2deg(s*s/2.0/190.0_f64.powi(2))));
+      let cl = sample_clothoid_inverse_negative_radius();
+      assert!(eq001(cl.azimuth_at_s(0.0), cl.start_azimuth));
+      assert!(eq001(cl.azimuth_at_s(cl.length), cl.end_azimuth()));
+      let cl = sample_clothoid_inverse_positive_radius();
+      assert!(eq001(cl.azimuth_at_s(cl.length), cl.end_azimuth()));
+   }
+   #[test]
+   fn test_point_at_s() {
+      let samples = [sample_clothoid_direct_positive_radius(), 
+         sample_clothoid_direct_negative_radius(), 
+         sample_clothoid_direct_negative_radius_2(),
+         sample_clothoid_inverse_positive_radius(), 
+         sample_clothoid_inverse_negative_radius()];
+      for cl in samples {
+         let p = cl.point_at_s(0.0);
+         assert!(eq001(p.x, cl.start_point.x));
+         assert!(eq001(p.y, cl.start_point.y));
+         let p = cl.point_at_s(cl.length);
+         let q = cl.end_point();
+         assert!(eq001(p.x, q.x));
+         assert!(eq001(p.y, q.y));
+      }
+      // Direct clothoid with start azimuth 90: local and global axes are equal
+      let hcl = HClothoid::new(Point{x:0.0, y: 0.0}, 90.0, 0.0, 250.0, 90.0);
+      let p = hcl.point_at_s(45.0);
+      assert!(eq001(p.x, 44.991));
+      assert!(eq001(p.y, -0.675));
+   }
+   #[test]
+   #[should_panic]
+   fn test_point_at_s_panic() {
+      let hcl = HClothoid::new(Point{x:0.0, y: 0.0}, 90.0, 0.0, 250.0, 90.0);
+      let _p = hcl.point_at_s(90.1);
+   }
+   #[test]
+   fn test_curvature_at_s() {
+      let cl = sample_clothoid_direct_positive_radius();
+      assert!(eq(cl.curvature_at_s(0.0), 0.0));
+      assert!(eq(cl.curvature_at_s(cl.length), 1.0/450.0));
+      assert!(eq(cl.curvature_at_s(cl.length/2.0), 0.5/450.0));
+      let cl = sample_clothoid_inverse_negative_radius();
+      assert!(eq(cl.curvature_at_s(0.0), -1.0/450.0));
+      assert!(eq(cl.curvature_at_s(cl.length), 0.0));
+      assert!(eq001(cl.radius_at_s(cl.length/2.0), -900.0));
    }
    #[test]
    fn test_radius_in_tangent_to_circle_point() {
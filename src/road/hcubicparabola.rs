@@ -0,0 +1,245 @@
+/// Cubic parabola transition, the classic railway transition curve.
+/// In local axes with origin in the tangent point it is
+///
+/// y = x^3 / (6 R X)
+///
+/// where R is the nominal radius of the adjoining circular curve and
+/// X the projection of the transition on the tangent. The section is
+/// given by its true arc length, and X is solved from it.
+///
+/// The curvature isn't linear: it is exactly
+/// k = 6cx / (1 + 9c^2x^4)^(3/2), with c = 1/(6RX), so it is a bit less
+/// than 1/R at the end. Only transitions with X^2 < 0.8 R^2 are allowed,
+/// where the curvature grows along the whole curve
+use crate::normalize_360;
+use crate::solve_newton_bisection;
+use crate::geom::point::Point;
+use crate::geom::quadrature::gauss_legendre;
+use crate::tolerances::Tolerances;
+use super::hsection::{HSection, point_and_azimuth_from_local_origin};
+
+/// Number of Gauss-Legendre panels in the arc length integrals
+const ARC_PANELS: usize = 4;
+
+pub struct HCubicParabola {
+   pub start_point: Point,
+   pub start_azimuth: f64,
+   pub start_radius: f64,
+   pub end_radius: f64,
+   pub length: f64,
+   /// Projection X of the transition on the tangent
+   pub x_length: f64
+}
+
+impl HCubicParabola {
+   /// Creates a new HCubicParabola. One of the radius must be zero,
+   /// but not both. The length is the true arc length
+   pub fn new(start_point: Point, start_azimuth: f64, start_radius: f64,
+      end_radius: f64, length: f64) -> Self {
+      HCubicParabola::new_with_tolerances(start_point, start_azimuth, start_radius,
+         end_radius, length, &Tolerances::default())
+   }
+   /// Creates a new HCubicParabola checking the radius against the
+   /// radius tolerance and the length against the linear tolerance
+   pub fn new_with_tolerances(start_point: Point, start_azimuth: f64, start_radius: f64,
+      end_radius: f64, length: f64, tol: &Tolerances) -> Self {
+      if tol.is_zero_radius(start_radius) == tol.is_zero_radius(end_radius) {
+         panic!("Cubic parabola must have one, and only one, of the radius equals zero");
+      }
+      if length < 0.0 || tol.is_zero_length(length) {
+         panic!("Cubic parabola can't have length zero or negative");
+      }
+      let r = start_radius.abs().max(end_radius.abs());
+      // Arc length minus length as a function of X
+      let f = |x_length: f64| -> f64 {
+         let c = 1.0 / (6.0 * r * x_length);
+         arc_length(c, x_length) - length
+      };
+      let g = |x_length: f64| -> (f64, f64) {
+         let h = 1e-7 * length;
+         (f(x_length), (f(x_length + h) - f(x_length - h)) / 2.0 / h)
+      };
+      let lo = length / (1.0 + length*length / (4.0*r*r)).sqrt();
+      let x_length = match solve_newton_bisection(g, lo, length, 1e-10*length) {
+         Ok(x) => x,
+         Err(e) => panic!("Cubic parabola can't be solved: {}", e)
+      };
+      if x_length*x_length >= 0.8*r*r {
+         panic!("Cubic parabola too long for the radius");
+      }
+      HCubicParabola{start_point, start_azimuth: normalize_360(start_azimuth),
+         start_radius, end_radius, length, x_length}
+   }
+   /// True if the local origin of the parabola is the start point
+   pub fn is_direct(&self) -> bool {
+      self.start_radius.abs() < self.end_radius.abs()
+   }
+   /// The radius not equal to zero
+   pub fn nominal_radius(&self) -> f64 {
+      if self.is_direct() {
+         self.end_radius
+      } else {
+         self.start_radius
+      }
+   }
+   /// Coefficient c of y = c*x^3
+   pub fn coefficient(&self) -> f64 {
+      1.0 / (6.0 * self.nominal_radius().abs() * self.x_length)
+   }
+   /// Local abscissa x of the point at arc length sigma from the origin
+   pub fn x_at_arc(&self, sigma: f64) -> f64 {
+      let c = self.coefficient();
+      if sigma <= 0.0 {
+         return 0.0;
+      }
+      if sigma >= self.length {
+         return self.x_length;
+      }
+      let f = |x: f64| (arc_length(c, x) - sigma, (1.0 + 9.0*c*c*x.powi(4)).sqrt());
+      solve_newton_bisection(f, 0.0, self.x_length, 1e-12*self.length)
+         .expect("Arc length of cubic parabola can't be inverted")
+   }
+   /// Arc length from the local origin of the point at arc
+   /// length s from the start of the section
+   fn sigma(&self, s: f64) -> f64 {
+      self.check_s(s);
+      if self.is_direct() {
+         s
+      } else {
+         self.length - s
+      }
+   }
+   fn check_s(&self, s: f64) {
+      if s < 0.0 || s > self.length {
+         panic!("s less than zero or greater than length");
+      }
+   }
+   fn point_and_azimuth_at_s(&self, s: f64) -> (Point, f64) {
+      self.check_s(s);
+      let c = self.coefficient();
+      // Local curve turns as the clothoid: rightward (y<0) if the
+      // local radius is positive. In reverse sections the local radius
+      // has the opposite sign of the start radius
+      let local_radius = if self.is_direct() { self.end_radius } else { -self.start_radius };
+      let sign = -local_radius.signum();
+      let local = |sigma: f64| {
+         let x = self.x_at_arc(sigma);
+         (x, sign*c*x.powi(3), sign*(3.0*c*x*x).atan())
+      };
+      point_and_azimuth_from_local_origin(self.start_point, self.start_azimuth,
+         self.length, self.is_direct(), local, s)
+   }
+}
+
+/// Arc length of y = c*x^3 between 0 and x
+fn arc_length(c: f64, x: f64) -> f64 {
+   gauss_legendre(|t| (1.0 + 9.0*c*c*t.powi(4)).sqrt(), 0.0, x, ARC_PANELS)
+}
+
+impl HSection for HCubicParabola {
+   fn start_point(&self) -> Point {
+      self.start_point
+   }
+   fn end_point(&self) -> Point {
+      self.point_at_s(self.length)
+   }
+   fn start_radius(&self) -> f64 {
+      self.start_radius
+   }
+   fn end_radius(&self) -> f64 {
+      self.end_radius
+   }
+   fn length(&self) -> f64 {
+      self.length
+   }
+   fn start_azimuth(&self) -> f64 {
+      self.start_azimuth
+   }
+   fn end_azimuth(&self) -> f64 {
+      self.azimuth_at_s(self.length)
+   }
+   fn azimuth_at_s(&self, s:f64) -> f64 {
+      self.point_and_azimuth_at_s(s).1
+   }
+   fn point_at_s(&self, s:f64) -> Point {
+      self.point_and_azimuth_at_s(s).0
+   }
+   fn curvature_at_s(&self, s:f64) -> f64 {
+      let x = self.x_at_arc(self.sigma(s));
+      let c = self.coefficient();
+      let k = 6.0*c*x / (1.0 + 9.0*c*c*x.powi(4)).powf(1.5);
+      k * self.nominal_radius().signum()
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use crate::{eq, eq001, rad2deg};
+
+   #[test]
+   fn test_new() {
+      let cp = HCubicParabola::new(Point::new(0.0, 0.0), 90.0, 0.0, 300.0, 100.0);
+      // Arc length of the solved parabola equals the length
+      assert!(eq(arc_length(cp.coefficient(), cp.x_length), 100.0));
+      assert!(cp.x_length < 100.0);
+      assert!(eq001(cp.nominal_radius(), 300.0));
+   }
+   #[test]
+   #[should_panic]
+   fn test_new_panic_too_long() {
+      let _cp = HCubicParabola::new(Point::new(0.0, 0.0), 90.0, 0.0, 50.0, 60.0);
+   }
+   #[test]
+   #[should_panic]
+   fn test_new_panic_radius() {
+      let _cp = HCubicParabola::new(Point::new(0.0, 0.0), 90.0, 0.0, 0.0, 60.0);
+   }
+   #[test]
+   fn test_end_point() {
+      // With start azimuth 90 local and global axes are equal
+      let cp = HCubicParabola::new(Point::new(0.0, 0.0), 90.0, 0.0, 300.0, 100.0);
+      let x = cp.x_length;
+      let p = cp.end_point();
+      assert!(eq001(p.x, x));
+      assert!(eq001(p.y, -x*x/6.0/300.0));
+      assert!(eq001(cp.end_azimuth(), 90.0 + rad2deg((x/2.0/300.0).atan())));
+      let cp = HCubicParabola::new(Point::new(0.0, 0.0), 90.0, 0.0, -300.0, 100.0);
+      let p = cp.end_point();
+      assert!(eq001(p.y, x*x/6.0/300.0));
+      assert!(eq001(cp.end_azimuth(), 90.0 - rad2deg((x/2.0/300.0).atan())));
+   }
+   #[test]
+   fn test_reverse() {
+      // Reverse parabola run from the end of a direct one
+      // returns to its start point
+      let cp = HCubicParabola::new(Point::new(10.0, 20.0), 45.0, 0.0, 250.0, 80.0);
+      let rev = HCubicParabola::new(cp.end_point(), cp.end_azimuth() + 180.0, -250.0, 0.0, 80.0);
+      assert!(eq001(rev.end_point().x, 10.0));
+      assert!(eq001(rev.end_point().y, 20.0));
+      assert!(eq001(rev.end_azimuth(), 225.0));
+      assert!(eq001(rev.azimuth_at_s(0.0), cp.end_azimuth() + 180.0));
+      assert!(eq(rev.curvature_at_s(30.0), -cp.curvature_at_s(50.0)));
+   }
+   #[test]
+   fn test_point_at_s() {
+      let cp = HCubicParabola::new(Point::new(0.0, 0.0), 90.0, 0.0, 300.0, 100.0);
+      let p = cp.point_at_s(50.0);
+      let c = cp.coefficient();
+      assert!(eq(arc_length(c, p.x), 50.0));
+      assert!(eq001(p.y, -c*p.x.powi(3)));
+   }
+   #[test]
+   fn test_curvature_at_s() {
+      let cp = HCubicParabola::new(Point::new(0.0, 0.0), 90.0, 0.0, 300.0, 100.0);
+      assert!(eq(cp.curvature_at_s(0.0), 0.0));
+      let x = cp.x_length;
+      let k = 1.0/300.0 / (1.0 + (x/2.0/300.0).powi(2)).powf(1.5);
+      assert!(eq(cp.curvature_at_s(100.0), k));
+      // Curvature grows along the parabola
+      assert!(cp.curvature_at_s(60.0) > cp.curvature_at_s(40.0));
+      let cp = HCubicParabola::new(Point::new(0.0, 0.0), 90.0, -300.0, 0.0, 100.0);
+      assert!(eq(cp.curvature_at_s(0.0), -k));
+      assert!(eq(cp.curvature_at_s(100.0), 0.0));
+   }
+}
@@ -1,4 +1,6 @@
 use crate::geom::point::Point;
+use crate::{azimuth_to_angle, angle_to_azimuth};
+use std::f64::consts::PI;
 
 /// Trait for horizontal alignment sections: Tangent, Spiral and CircularCurve\
 ///
@@ -8,8 +10,9 @@ use crate::geom::point::Point;
 ///    
 /// The criteria used is:    
 ///    
-/// **Radius:** 0 is for infinitus radius. Positive is for leftward curves\
-/// **Azimuth:** is measured in sexagesimal degrees from the North counterclock-wise
+/// **Radius:** 0 is for infinitus radius. Positive is for rightward curves\
+/// **Curvature:** 1/radius, 0 in tangents. Positive is for rightward curves\
+/// **Azimuth:** is measured in sexagesimal degrees from the North toward the East
 pub trait HSection {
    fn start_point(&self) -> Point;
    fn start_x(&self) -> f64 {
@@ -37,4 +40,48 @@ pub trait HSection {
       self.end_azimuth() - self.start_azimuth()
    }
    fn point_at_s(&self, s:f64) -> Point;
+   /// Signed curvature at arc length s
+   fn curvature_at_s(&self, s:f64) -> f64;
+   /// Signed radius at arc length s. Zero for infinite radius
+   fn radius_at_s(&self, s:f64) -> f64 {
+      let k = self.curvature_at_s(s);
+      if k == 0.0 {
+         0.0
+      } else {
+         1.0 / k
+      }
+   }
+}
+
+/// Point and azimuth at arc length s of a transition section whose
+/// shape is given in local axes with origin in the point of infinite
+/// radius, the X axis along the tangent in that point.
+///
+/// `local(sigma)` returns (x, y, alpha): the local coordinates and the
+/// angle in radians of the tangent with the X axis at arc length sigma
+/// from the local origin.\
+/// If `direct` is true the origin is the start point of the section,
+/// else it is the end point, and the local curve is run backward from
+/// sigma = length to sigma = 0.
+pub fn point_and_azimuth_from_local_origin<F>(start_point: Point, start_azimuth: f64,
+   length: f64, direct: bool, local: F, s: f64) -> (Point, f64) 
+   where F: Fn(f64) -> (f64, f64, f64) {
+   let start_angle = azimuth_to_angle(start_azimuth);
+   if direct {
+      let (x, y, alpha) = local(s);
+      let (sin, cos) = start_angle.sin_cos();
+      let p = Point::new(start_point.x + x*cos - y*sin, start_point.y + x*sin + y*cos);
+      (p, angle_to_azimuth(start_angle + alpha))
+   } else {
+      // Rotation of the local axes so that the reversed tangent at
+      // sigma = length has the start direction
+      let (xl, yl, alpha_l) = local(length);
+      let beta = start_angle - alpha_l - PI;
+      let (sin, cos) = beta.sin_cos();
+      let origin = Point::new(start_point.x - (xl*cos - yl*sin), 
+         start_point.y - (xl*sin + yl*cos));
+      let (x, y, alpha) = local(length - s);
+      let p = Point::new(origin.x + x*cos - y*sin, origin.y + x*sin + y*cos);
+      (p, angle_to_azimuth(beta + alpha + PI))
+   }
 }
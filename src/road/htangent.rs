@@ -3,7 +3,8 @@ use crate::geom::point::Point;
 use crate::geom::vector::Vector;
 //use crate::geom::angles::Azimuth;
 use super::hsection::HSection;
-use crate::{eq001, normalize_360, azimuth_to_angle};
+use crate::{normalize_360, azimuth_to_angle};
+use crate::tolerances::Tolerances;
 
 
 pub struct HTangent {
@@ -14,7 +15,12 @@ pub struct HTangent {
 }
 impl HTangent {
    pub fn new(start_point: Point, azimuth_value: f64, length: f64) -> Self {
-      if length < 0.0_f64 || eq001(length, 0.0) {
+      HTangent::new_with_tolerances(start_point, azimuth_value, length, &Tolerances::default())
+   }
+   /// Creates a new HTangent. The length must be greater than
+   /// the linear tolerance
+   pub fn new_with_tolerances(start_point: Point, azimuth_value: f64, length: f64, tol: &Tolerances) -> Self {
+      if length < 0.0_f64 || tol.is_zero_length(length) {
          panic!("Length must be greater than zero");
       }
       HTangent{start_point, azimuth: normalize_360(azimuth_value), length}
@@ -63,6 +69,9 @@ impl HSection for HTangent {
       let y = self.start_y() + s*self.angle().sin();
       Point::new(x, y)
    }
+   fn curvature_at_s(&self, _s:f64) -> f64 {
+      0.0
+   }
 }
 
 #[cfg(test)]
@@ -80,6 +89,13 @@ mod tests {
       let _r1 = HTangent::new(p, angle, -100.0);
    }
 
+   #[test]
+   #[should_panic]
+   fn test_new_with_tolerances_panic() {
+      // Length shorter than the linear tolerance
+      let tol = Tolerances::new(0.1, 1e-6, 0.001);
+      let _r1 = HTangent::new_with_tolerances(Point::new(0.0, 0.0), 0.0, 0.05, &tol);
+   }
    #[test]
    fn test_new() {
       let p = Point::new(-1.0, -1.0);
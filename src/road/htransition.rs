@@ -0,0 +1,291 @@
+/// Transition curves whose curvature varies between the start
+/// and end values following a shape function of the arc length:
+///
+/// k(s) = k0 + (k1 - k0) * g(s/L)
+///
+/// The azimuth is obtained integrating the curvature in closed form
+/// and the coordinates integrating the direction numerically with
+/// the Gauss-Legendre rule
+use crate::{normalize_360, rad2deg, deg2rad};
+use crate::geom::point::Point;
+use crate::geom::quadrature::gauss_legendre;
+use crate::tolerances::Tolerances;
+use super::hsection::HSection;
+use std::f64::consts::PI;
+
+/// Maximum azimuth increment in radians integrated in each panel
+const MAX_PANEL_DEFLECTION: f64 = 0.25;
+
+/// Shape of the curvature along the transition
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransitionType {
+   /// Linear curvature: g(u) = u
+   Clothoid,
+   /// Bloss curve: g(u) = 3u^2 - 2u^3
+   Bloss,
+   /// Cosine (half-wave) transition: g(u) = (1 - cos(PI u))/2
+   Cosine,
+   /// Sinusoidal (Klein) transition: g(u) = u - sin(2 PI u)/(2 PI)
+   Sinusoidal,
+   /// Helmert (Schramm) biquadratic parabola: g(u) = 2u^2 until u = 1/2,
+   /// g(u) = 1 - 2(1-u)^2 after it
+   Helmert
+}
+
+impl TransitionType {
+   /// Normalized curvature g(u), with g(0) = 0 and g(1) = 1
+   pub fn shape(&self, u: f64) -> f64 {
+      match self {
+         TransitionType::Clothoid => u,
+         TransitionType::Bloss => u*u*(3.0 - 2.0*u),
+         TransitionType::Cosine => (1.0 - (PI*u).cos()) / 2.0,
+         TransitionType::Sinusoidal => u - (2.0*PI*u).sin() / 2.0 / PI,
+         TransitionType::Helmert => {
+            if u <= 0.5 {
+               2.0*u*u
+            } else {
+               1.0 - 2.0*(1.0 - u).powi(2)
+            }
+         }
+      }
+   }
+   /// Integral of g between 0 and u. All the shapes have
+   /// G(1) = 1/2, so they have the azimuth increment of a clothoid
+   pub fn shape_integral(&self, u: f64) -> f64 {
+      match self {
+         TransitionType::Clothoid => u*u / 2.0,
+         TransitionType::Bloss => u.powi(3) - u.powi(4) / 2.0,
+         TransitionType::Cosine => u / 2.0 - (PI*u).sin() / 2.0 / PI,
+         TransitionType::Sinusoidal => u*u / 2.0 + ((2.0*PI*u).cos() - 1.0) / 4.0 / PI / PI,
+         TransitionType::Helmert => {
+            if u <= 0.5 {
+               2.0 * u.powi(3) / 3.0
+            } else {
+               u - 0.5 + 2.0 * (1.0 - u).powi(3) / 3.0
+            }
+         }
+      }
+   }
+}
+
+/// Transition section between a tangent and a circular curve (or
+/// the reverse) with one of the curvature shapes of TransitionType.
+/// As in HClothoid, one of the radius must be zero, but not both
+pub struct HTransition {
+   pub start_point: Point,
+   pub start_azimuth: f64,
+   pub start_radius: f64,
+   pub end_radius: f64,
+   pub length: f64,
+   pub transition_type: TransitionType
+}
+
+impl HTransition {
+   pub fn new(start_point: Point, start_azimuth: f64, start_radius: f64,
+      end_radius: f64, length: f64, transition_type: TransitionType) -> Self {
+      HTransition::new_with_tolerances(start_point, start_azimuth, start_radius,
+         end_radius, length, transition_type, &Tolerances::default())
+   }
+   /// Creates a new HTransition checking the radius against the
+   /// radius tolerance and the length against the linear tolerance
+   pub fn new_with_tolerances(start_point: Point, start_azimuth: f64, start_radius: f64,
+      end_radius: f64, length: f64, transition_type: TransitionType, tol: &Tolerances) -> Self {
+      if tol.is_zero_radius(start_radius) == tol.is_zero_radius(end_radius) {
+         panic!("Transition must have one, and only one, of the radius equals zero");
+      }
+      if length < 0.0 || tol.is_zero_length(length) {
+         panic!("Transition can't have length zero or negative");
+      }
+      HTransition{start_point, start_azimuth: normalize_360(start_azimuth),
+         start_radius, end_radius, length, transition_type}
+   }
+   fn start_curvature(&self) -> f64 {
+      if self.start_radius.abs() < self.end_radius.abs() {
+         0.0
+      } else {
+         1.0 / self.start_radius
+      }
+   }
+   fn end_curvature(&self) -> f64 {
+      if self.end_radius.abs() < self.start_radius.abs() {
+         0.0
+      } else {
+         1.0 / self.end_radius
+      }
+   }
+   /// Azimuth increment in radians between the start point and
+   /// the point at arc length s. Positive in rightward curves
+   pub fn deflection_at_s(&self, s: f64) -> f64 {
+      let k0 = self.start_curvature();
+      let k1 = self.end_curvature();
+      k0*s + (k1 - k0)*self.length*self.transition_type.shape_integral(s / self.length)
+   }
+   fn check_s(&self, s: f64) {
+      if s < 0.0 || s > self.length {
+         panic!("s less than zero or greater than length");
+      }
+   }
+}
+
+impl HSection for HTransition {
+   fn start_point(&self) -> Point {
+      self.start_point
+   }
+   fn end_point(&self) -> Point {
+      self.point_at_s(self.length)
+   }
+   fn start_radius(&self) -> f64 {
+      self.start_radius
+   }
+   fn end_radius(&self) -> f64 {
+      self.end_radius
+   }
+   fn length(&self) -> f64 {
+      self.length
+   }
+   fn start_azimuth(&self) -> f64 {
+      self.start_azimuth
+   }
+   fn end_azimuth(&self) -> f64 {
+      self.azimuth_at_s(self.length)
+   }
+   fn azimuth_at_s(&self, s:f64) -> f64 {
+      self.check_s(s);
+      normalize_360(self.start_azimuth + rad2deg(self.deflection_at_s(s)))
+   }
+   fn point_at_s(&self, s:f64) -> Point {
+      self.check_s(s);
+      let az0 = deg2rad(self.start_azimuth);
+      let panels = 1 + (self.deflection_at_s(s).abs() / MAX_PANEL_DEFLECTION).ceil() as usize;
+      // Helmert curvature has a discontinuous second derivative
+      // in the middle point, so it is integrated in two pieces
+      let mut limits = vec![0.0];
+      if self.transition_type == TransitionType::Helmert && s > self.length / 2.0 {
+         limits.push(self.length / 2.0);
+      }
+      limits.push(s);
+      let mut x = self.start_point.x;
+      let mut y = self.start_point.y;
+      for w in limits.windows(2) {
+         x += gauss_legendre(|t| (az0 + self.deflection_at_s(t)).sin(), w[0], w[1], panels);
+         y += gauss_legendre(|t| (az0 + self.deflection_at_s(t)).cos(), w[0], w[1], panels);
+      }
+      Point::new(x, y)
+   }
+   fn curvature_at_s(&self, s:f64) -> f64 {
+      self.check_s(s);
+      let k0 = self.start_curvature();
+      let k1 = self.end_curvature();
+      k0 + (k1 - k0)*self.transition_type.shape(s / self.length)
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use crate::{eq, eq001, gon2deg};
+   use crate::road::hclothoid::HClothoid;
+
+   const TYPES: [TransitionType; 5] = [TransitionType::Clothoid, TransitionType::Bloss,
+      TransitionType::Cosine, TransitionType::Sinusoidal, TransitionType::Helmert];
+
+   #[test]
+   fn test_shape() {
+      for t in TYPES {
+         assert!(eq(t.shape(0.0), 0.0));
+         assert!(eq(t.shape(1.0), 1.0));
+         // All the shapes are antisymmetric around u = 1/2
+         assert!(eq(t.shape(0.5), 0.5));
+         assert!(eq(t.shape(0.2) + t.shape(0.8), 1.0));
+         assert!(eq(t.shape_integral(0.0), 0.0));
+         assert!(eq(t.shape_integral(1.0), 0.5));
+      }
+   }
+   #[test]
+   fn test_shape_integral() {
+      // Compares the closed form with numerical integration,
+      // split in u = 1/2 because of the Helmert shape
+      for t in TYPES {
+         for u in [0.1_f64, 0.3, 0.5, 0.7, 0.95] {
+            let mut g = gauss_legendre(|x| t.shape(x), 0.0, u.min(0.5), 4);
+            if u > 0.5 {
+               g += gauss_legendre(|x| t.shape(x), 0.5, u, 4);
+            }
+            assert!(eq(g, t.shape_integral(u)));
+         }
+      }
+   }
+   #[test]
+   fn test_clothoid_type_equals_hclothoid() {
+      let start_point = Point{x:433145.265, y: 4503928.05};
+      let start_azimuth = gon2deg(281.031);
+      let hcl = HClothoid::new(start_point, start_azimuth, 0.0, 450.0, 80.222);
+      let htr = HTransition::new(start_point, start_azimuth, 0.0, 450.0, 80.222,
+         TransitionType::Clothoid);
+      for s in [0.0, 20.0, 40.0, 80.222] {
+         assert!(eq001(hcl.point_at_s(s).x, htr.point_at_s(s).x));
+         assert!(eq001(hcl.point_at_s(s).y, htr.point_at_s(s).y));
+         assert!(eq001(hcl.azimuth_at_s(s), htr.azimuth_at_s(s)));
+         assert!(eq(hcl.curvature_at_s(s), htr.curvature_at_s(s)));
+      }
+      // Reverse transition
+      let start_point = Point{x:432730.377, y: 4503969.09};
+      let start_azimuth = gon2deg(336.529);
+      let hcl = HClothoid::new(start_point, start_azimuth, 450.0, 0.0, 80.222);
+      let htr = HTransition::new(start_point, start_azimuth, 450.0, 0.0, 80.222,
+         TransitionType::Clothoid);
+      assert!(eq001(hcl.end_point().x, htr.end_point().x));
+      assert!(eq001(hcl.end_point().y, htr.end_point().y));
+      assert!(eq001(hcl.end_azimuth(), htr.end_azimuth()));
+   }
+   #[test]
+   fn test_end_azimuth() {
+      // Same azimuth increment than a clothoid: L/(2R)
+      for t in TYPES {
+         let htr = HTransition::new(Point::new(0.0, 0.0), 90.0, 0.0, 200.0, 100.0, t);
+         assert!(eq001(htr.end_azimuth(), 90.0 + rad2deg(0.25)));
+         let htr = HTransition::new(Point::new(0.0, 0.0), 90.0, -200.0, 0.0, 100.0, t);
+         assert!(eq001(htr.end_azimuth(), 90.0 - rad2deg(0.25)));
+      }
+   }
+   #[test]
+   fn test_curvature_at_s() {
+      let htr = HTransition::new(Point::new(0.0, 0.0), 0.0, 0.0, 200.0, 100.0,
+         TransitionType::Bloss);
+      assert!(eq(htr.curvature_at_s(0.0), 0.0));
+      assert!(eq(htr.curvature_at_s(25.0), 0.15625/200.0));
+      assert!(eq(htr.curvature_at_s(100.0), 1.0/200.0));
+      let htr = HTransition::new(Point::new(0.0, 0.0), 0.0, -200.0, 0.0, 100.0,
+         TransitionType::Helmert);
+      assert!(eq(htr.curvature_at_s(0.0), -1.0/200.0));
+      assert!(eq(htr.curvature_at_s(25.0), -0.875/200.0));
+      assert!(eq(htr.curvature_at_s(100.0), 0.0));
+      assert!(eq001(htr.radius_at_s(50.0), -400.0));
+   }
+   #[test]
+   fn test_point_at_s() {
+      // The reverse transition run from its end point with the opposite
+      // azimuth and radius ends in the start point of the original one
+      for t in TYPES {
+         let htr = HTransition::new(Point::new(100.0, 200.0), 30.0, 0.0, 150.0, 120.0, t);
+         let end = htr.end_point();
+         let rev = HTransition::new(end, htr.end_azimuth() + 180.0, -150.0, 0.0, 120.0, t);
+         assert!(eq001(rev.end_point().x, 100.0));
+         assert!(eq001(rev.end_point().y, 200.0));
+         assert!(eq001(rev.end_azimuth(), 210.0));
+      }
+      // Bloss curve local coordinates (x along the tangent), computed
+      // with high precision: x = 118.26087, y = 14.23170
+      let htr = HTransition::new(Point::new(0.0, 0.0), 90.0, 0.0, 150.0, 120.0,
+         TransitionType::Bloss);
+      let p = htr.end_point();
+      assert!(eq001(p.x, 118.26087));
+      assert!(eq001(p.y, -14.23170));
+   }
+   #[test]
+   #[should_panic]
+   fn test_new_panic() {
+      let _htr = HTransition::new(Point::new(0.0, 0.0), 0.0, 100.0, 200.0, 100.0,
+         TransitionType::Cosine);
+   }
+}
@@ -1,20 +1,31 @@
 /// Eje de una carretera
 /// Es un vector de alineaciones hsection
 /// 
-/// 
+/// The stations (PK) are measured along the axis from start_station,
+/// the station of the start point of the first section
 use crate::geom::point::Point;
 use crate::road::htangent::HTangent;
 use crate::road::hclothoid::HClothoid;
 use crate::road::hcircle::HCircle;
+use crate::road::htransition::HTransition;
+use crate::road::hcubicparabola::HCubicParabola;
 use crate::road::hsection::HSection;
+use crate::tolerances::Tolerances;
 
 pub struct RoadAxis {
-    pub sections: Vec<Box<dyn HSection>>
+    pub sections: Vec<Box<dyn HSection>>,
+    /// Tolerances used in the checks of the axis and its sections
+    pub tolerances: Tolerances,
+    /// Station of the start point of the axis
+    pub start_station: f64
 }
 
 impl RoadAxis {
     pub fn new() -> Self {
-        RoadAxis{sections: Vec::<Box<dyn HSection>>::new()}
+        RoadAxis::with_tolerances(Tolerances::default())
+    }
+    pub fn with_tolerances(tolerances: Tolerances) -> Self {
+        RoadAxis{sections: Vec::<Box<dyn HSection>>::new(), tolerances, start_station: 0.0}
     }
     pub fn add(&mut self, box_section: Box<dyn HSection>) {
         self.sections.push(box_section);
@@ -28,11 +39,142 @@ impl RoadAxis {
     pub fn add_hclothoid(&mut self, clothoid: HClothoid) {
         self.add(Box::new(clothoid));
     }
+    pub fn add_htransition(&mut self, transition: HTransition) {
+        self.add(Box::new(transition));
+    }
+    pub fn add_hcubicparabola(&mut self, parabola: HCubicParabola) {
+        self.add(Box::new(parabola));
+    }
+    /// Total length of the axis
+    pub fn length(&self) -> f64 {
+        self.sections.iter().map(|s| s.length()).sum()
+    }
+    pub fn end_station(&self) -> f64 {
+        self.start_station + self.length()
+    }
+    /// True if the end point of the axis is its start point, as in a
+    /// roundabout
+    pub fn is_closed(&self) -> bool {
+        if self.sections.is_empty() {
+            return false;
+        }
+        let p0 = self.sections[0].start_point();
+        let p1 = self.sections[self.sections.len() - 1].end_point();
+        p0.dist_to(p1) < self.tolerances.linear
+    }
+    /// Station of the start point of the section with index i
+    pub fn section_start_station(&self, i: usize) -> f64 {
+        if i >= self.sections.len() {
+            panic!("Section index out of range");
+        }
+        self.start_station + self.sections[..i].iter().map(|s| s.length()).sum::<f64>()
+    }
+    /// True if the section with index i is a transition curve between
+    /// a tangent and a curve of the radius
+    pub fn is_transition(&self, i: usize, radius: f64) -> bool {
+        let tol = &self.tolerances;
+        let s = &self.sections[i];
+        (tol.is_zero_radius(s.start_radius()) && tol.eq_radius(s.end_radius(), radius))
+            || (tol.is_zero_radius(s.end_radius()) && tol.eq_radius(s.start_radius(), radius))
+    }
+    /// True if the station is inside the axis, with the linear tolerance
+    pub fn contains_station(&self, station: f64) -> bool {
+        !self.sections.is_empty() 
+            && station > self.start_station - self.tolerances.linear
+            && station < self.end_station() + self.tolerances.linear
+    }
+    /// Index of the section that contains the station and arc length 
+    /// from the start of that section. In the common point of two sections
+    /// the second one is returned, except at the end of the axis
+    pub fn locate(&self, station: f64) -> (usize, f64) {
+        if !self.contains_station(station) {
+            panic!("Station out of the axis");
+        }
+        let mut start = self.start_station;
+        for (i, section) in self.sections.iter().enumerate() {
+            let end = start + section.length();
+            if station < end || i == self.sections.len() - 1 {
+                let s = (station - start).clamp(0.0, section.length());
+                return (i, s);
+            }
+            start = end;
+        }
+        unreachable!();
+    }
+    pub fn point_at_station(&self, station: f64) -> Point {
+        let (i, s) = self.locate(station);
+        self.sections[i].point_at_s(s)
+    }
+    /// Azimuth in sexagesimal degrees at the station
+    pub fn azimuth_at_station(&self, station: f64) -> f64 {
+        let (i, s) = self.locate(station);
+        self.sections[i].azimuth_at_s(s)
+    }
+    /// Signed curvature at the station. Positive in rightward curves
+    pub fn curvature_at_station(&self, station: f64) -> f64 {
+        let (i, s) = self.locate(station);
+        self.sections[i].curvature_at_s(s)
+    }
+    /// Point at the station displaced offset perpendicular to the
+    /// axis. Positive offsets are on the right side of the axis
+    pub fn offset_point_at_station(&self, station: f64, offset: f64) -> Point {
+        let p = self.point_at_station(station);
+        let normal = (self.azimuth_at_station(station) + 90.0).to_radians();
+        Point::new(p.x + offset*normal.sin(), p.y + offset*normal.cos())
+    }
+    /// Stations of the axis multiple of interval, with the start and
+    /// end of the axis and the start of every section (tangent points).
+    /// Stations nearer than the linear tolerance are merged
+    pub fn stations_at_interval(&self, interval: f64) -> Result<Vec<f64>, String> {
+        if interval <= 0.0 || self.tolerances.is_zero_length(interval) {
+            return Err(String::from("Interval must be greater than zero"));
+        }
+        if self.sections.is_empty() {
+            return Err(String::from("Axis without sections"));
+        }
+        let mut stations: Vec<f64> = (0..self.sections.len())
+            .map(|i| self.section_start_station(i)).collect();
+        stations.push(self.end_station());
+        let mut k = (self.start_station / interval).ceil();
+        while k * interval < self.end_station() {
+            stations.push(k * interval);
+            k += 1.0;
+        }
+        stations.sort_by(|a, b| a.total_cmp(b));
+        stations.dedup_by(|a, b| (*a - *b).abs() < self.tolerances.linear);
+        Ok(stations)
+    }
+}
 
+impl Default for RoadAxis {
+    fn default() -> Self {
+        RoadAxis::new()
+    }
 }
 
-mod tests {
+#[cfg(test)]
+pub(crate) mod tests {
     use super::*;
+    use crate::eq001;
+
+    /// Axis starting at (1000, 1000) toward the North with station 100: 
+    /// tangent 100 m, clothoid 60 m, rightward circle R=200 and 100 m,
+    /// clothoid 60 m and tangent 100 m
+    pub(crate) fn sample_axis() -> RoadAxis {
+        let mut axis = RoadAxis::new();
+        axis.start_station = 100.0;
+        let t1 = HTangent::new(Point::new(1000.0, 1000.0), 0.0, 100.0);
+        let cl1 = HClothoid::new(t1.end_point(), t1.end_azimuth(), 0.0, 200.0, 60.0);
+        let c = HCircle::new(cl1.end_point(), cl1.end_azimuth(), 200.0, 100.0);
+        let cl2 = HClothoid::new(c.end_point(), c.end_azimuth(), 200.0, 0.0, 60.0);
+        let t2 = HTangent::new(cl2.end_point(), cl2.end_azimuth(), 100.0);
+        axis.add_htangent(t1);
+        axis.add_hclothoid(cl1);
+        axis.add_hcircle(c);
+        axis.add_hclothoid(cl2);
+        axis.add_htangent(t2);
+        axis
+    }
 
     #[test]
     fn test_new() {
@@ -40,5 +182,87 @@ mod tests {
         let r1 = HTangent::new(p1, 45.0, 100.0);
         let mut axis = RoadAxis::new();
         axis.add(Box::new(r1));
+        assert_eq!(axis.tolerances, Tolerances::default());
+    }
+    #[test]
+    fn test_with_tolerances() {
+        let tol = Tolerances::new(0.01, 1e-5, 0.01);
+        let axis = RoadAxis::with_tolerances(tol);
+        assert_eq!(axis.tolerances.linear, 0.01);
+        assert!(axis.sections.is_empty());
+    }
+    #[test]
+    fn test_length() {
+        let axis = sample_axis();
+        assert!(eq001(axis.length(), 420.0));
+        assert!(eq001(axis.end_station(), 520.0));
+        assert!(eq001(axis.section_start_station(2), 260.0));
+        assert!(axis.contains_station(520.0));
+        assert!(!axis.contains_station(99.0));
+    }
+    #[test]
+    fn test_is_transition() {
+        let axis = sample_axis();
+        assert!(axis.is_transition(1, 200.0));
+        assert!(axis.is_transition(3, 200.0));
+        assert!(!axis.is_transition(3, -200.0));
+        assert!(!axis.is_transition(0, 200.0));
+        assert!(!axis.is_transition(2, 200.0));
+    }
+    #[test]
+    fn test_locate() {
+        let axis = sample_axis();
+        let (i, s) = axis.locate(100.0);
+        assert_eq!(i, 0);
+        assert!(eq001(s, 0.0));
+        let (i, s) = axis.locate(200.0);
+        assert_eq!(i, 1);
+        assert!(eq001(s, 0.0));
+        let (i, s) = axis.locate(300.0);
+        assert_eq!(i, 2);
+        assert!(eq001(s, 40.0));
+        let (i, s) = axis.locate(520.0);
+        assert_eq!(i, 4);
+        assert!(eq001(s, 100.0));
+    }
+    #[test]
+    #[should_panic]
+    fn test_locate_panic() {
+        let axis = sample_axis();
+        let _l = axis.locate(530.0);
+    }
+    #[test]
+    fn test_point_at_station() {
+        let axis = sample_axis();
+        let p = axis.point_at_station(150.0);
+        assert!(eq001(p.x, 1000.0));
+        assert!(eq001(p.y, 1050.0));
+        // Symmetric curve: total deflection 60/200 + 100/200 rad
+        assert!(eq001(axis.azimuth_at_station(520.0), crate::rad2deg(0.8)));
+        assert!(eq001(axis.curvature_at_station(300.0), 1.0/200.0));
+        assert!(eq001(axis.curvature_at_station(150.0), 0.0));
+    }
+    #[test]
+    fn test_offset_point_at_station() {
+        let axis = sample_axis();
+        let p = axis.offset_point_at_station(150.0, 5.0);
+        assert!(eq001(p.x, 1005.0));
+        assert!(eq001(p.y, 1050.0));
+        let p = axis.offset_point_at_station(150.0, -5.0);
+        assert!(eq001(p.x, 995.0));
+        // In the circle the offset point is on the radius
+        let c = axis.offset_point_at_station(300.0, 200.0);
+        let p = axis.offset_point_at_station(320.0, 200.0);
+        assert!(eq001(c.x, p.x));
+        assert!(eq001(c.y, p.y));
+    }
+    #[test]
+    fn test_stations_at_interval() {
+        let axis = sample_axis();
+        let stations = axis.stations_at_interval(50.0).unwrap();
+        assert_eq!(stations, vec![100.0, 150.0, 200.0, 250.0, 260.0, 300.0, 350.0,
+            360.0, 400.0, 420.0, 450.0, 500.0, 520.0]);
+        assert!(axis.stations_at_interval(0.0).is_err());
+        assert!(RoadAxis::new().stations_at_interval(20.0).is_err());
     }
 }
\ No newline at end of file
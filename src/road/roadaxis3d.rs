@@ -0,0 +1,153 @@
+/// Eje 3D de una carretera: planta (RoadAxis) y alzado 
+/// (VerticalAlignment) de la misma carretera.
+///
+/// The station is the horizontal length along the plan axis, so
+/// the true 3D length between two stations is the integral of
+/// sqrt(1 + grade^2)
+use crate::geom::point3d::{Point3D, Vector3D};
+use crate::geom::quadrature::gauss_legendre;
+use crate::road::roadaxis::RoadAxis;
+use crate::road::verticalalignment::VerticalAlignment;
+
+/// Number of Gauss-Legendre panels in every vertical section
+const LENGTH_PANELS: usize = 4;
+
+pub struct RoadAxis3D {
+   pub axis: RoadAxis,
+   pub profile: VerticalAlignment
+}
+
+impl RoadAxis3D {
+   /// Couples plan and profile. Returns Err if any of them is empty or
+   /// if they don't cover the same stations, within the linear
+   /// tolerance of the plan axis
+   pub fn from_axis_and_profile(axis: RoadAxis, profile: VerticalAlignment) -> Result<Self, String> {
+      if axis.sections.is_empty() {
+         return Err(String::from("Plan axis without sections"));
+      }
+      if profile.sections.is_empty() {
+         return Err(String::from("Vertical alignment without sections"));
+      }
+      let tol = &axis.tolerances;
+      if !tol.eq_linear(axis.start_station, profile.start_station())
+         || !tol.eq_linear(axis.end_station(), profile.end_station()) {
+         return Err(format!("Plan axis from {:.3} to {:.3} and vertical alignment from {:.3} to {:.3} don't cover the same stations",
+            axis.start_station, axis.end_station(), profile.start_station(), profile.end_station()));
+      }
+      Ok(RoadAxis3D{axis, profile})
+   }
+   pub fn start_station(&self) -> f64 {
+      self.axis.start_station
+   }
+   pub fn end_station(&self) -> f64 {
+      self.axis.end_station()
+   }
+   pub fn contains_station(&self, station: f64) -> bool {
+      self.axis.contains_station(station)
+   }
+   /// Station clamped to the profile, that can differ from the plan
+   /// axis in the linear tolerance
+   fn profile_station(&self, station: f64) -> f64 {
+      station.clamp(self.profile.start_station(), self.profile.end_station())
+   }
+   pub fn point_at_station(&self, station: f64) -> Point3D {
+      let p = self.axis.point_at_station(station);
+      Point3D::from_point(p, self.profile.elevation_at(self.profile_station(station)))
+   }
+   /// 3D point at the station displaced offset perpendicular to the
+   /// axis, positive to the right, at the elevation of the axis
+   pub fn offset_point_at_station(&self, station: f64, offset: f64) -> Point3D {
+      let p = self.axis.offset_point_at_station(station, offset);
+      Point3D::from_point(p, self.profile.elevation_at(self.profile_station(station)))
+   }
+   /// Unit tangent vector in the direction of increasing stations
+   pub fn tangent_at_station(&self, station: f64) -> Vector3D {
+      let az = self.axis.azimuth_at_station(station).to_radians();
+      let grade = self.profile.grade_at(self.profile_station(station));
+      Vector3D::new(az.sin(), az.cos(), grade).unit_vector()
+   }
+   /// True 3D length between two stations
+   pub fn length_3d_between(&self, start_station: f64, end_station: f64) -> f64 {
+      if !self.contains_station(start_station) || !self.contains_station(end_station) {
+         panic!("Station out of the axis");
+      }
+      let (a, b) = (start_station.min(end_station), start_station.max(end_station));
+      let a = self.profile_station(a);
+      let b = self.profile_station(b);
+      let mut length = 0.0;
+      // The grade is smooth inside every vertical section
+      for section in &self.profile.sections {
+         let lo = section.start_station().max(a);
+         let hi = section.end_station().min(b);
+         if hi > lo {
+            length += gauss_legendre(|s| (1.0 + section.grade_at(s).powi(2)).sqrt(), 
+               lo, hi, LENGTH_PANELS);
+         }
+      }
+      length
+   }
+   /// True 3D length of the whole axis
+   pub fn length_3d(&self) -> f64 {
+      self.length_3d_between(self.start_station(), self.end_station())
+   }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+   use super::*;
+   use crate::eq001;
+   use crate::road::roadaxis::tests::sample_axis;
+   use crate::road::verticalalignment::{Pvi, PviCurve};
+
+   /// Sample plan axis, from station 100 to 520, with a crest curve
+   pub(crate) fn sample_axis_3d() -> RoadAxis3D {
+      let pvis = [Pvi::new(100.0, 500.0, PviCurve::None),
+         Pvi::new(300.0, 508.0, PviCurve::Kv(2500.0)),
+         Pvi::new(520.0, 502.5, PviCurve::None)];
+      let profile = VerticalAlignment::from_pvis(&pvis).unwrap();
+      RoadAxis3D::from_axis_and_profile(sample_axis(), profile).unwrap()
+   }
+
+   #[test]
+   fn test_from_axis_and_profile() {
+      let pvis = [Pvi::new(100.0, 500.0, PviCurve::None),
+         Pvi::new(500.0, 508.0, PviCurve::None)];
+      let profile = VerticalAlignment::from_pvis(&pvis).unwrap();
+      let err = RoadAxis3D::from_axis_and_profile(sample_axis(), profile).err().unwrap();
+      assert!(err.contains("don't cover the same stations"));
+      let r = RoadAxis3D::from_axis_and_profile(sample_axis(), VerticalAlignment::new());
+      assert!(r.is_err());
+   }
+   #[test]
+   fn test_point_at_station() {
+      let axis = sample_axis_3d();
+      let p = axis.point_at_station(150.0);
+      assert!(eq001(p.x, 1000.0));
+      assert!(eq001(p.y, 1050.0));
+      assert!(eq001(p.z, 502.0));
+      let p = axis.offset_point_at_station(150.0, 3.0);
+      assert!(eq001(p.x, 1003.0));
+      assert!(eq001(p.z, 502.0));
+   }
+   #[test]
+   fn test_tangent_at_station() {
+      let axis = sample_axis_3d();
+      let t = axis.tangent_at_station(150.0);
+      assert!(eq001(t.vx, 0.0));
+      assert!(eq001(t.vz / t.vy, 0.04));
+      assert!(eq001(t.length(), 1.0));
+      // Top of the crest: grade 0.04 - u / 2500 = 0 
+      let t = axis.tangent_at_station(300.0 - 81.25 + 100.0);
+      assert!(t.vz.abs() < 1e-6);
+   }
+   #[test]
+   fn test_length_3d() {
+      let axis = sample_axis_3d();
+      // Uniform grades: length is the hypotenuse
+      assert!(eq001(axis.length_3d_between(100.0, 150.0), 50.0 * (1.0 + 0.04_f64.powi(2)).sqrt()));
+      assert!(eq001(axis.length_3d_between(150.0, 100.0), axis.length_3d_between(100.0, 150.0)));
+      let l = axis.length_3d();
+      assert!(l > 420.0);
+      assert!(l < 420.0 * (1.0 + 0.04_f64.powi(2)).sqrt());
+   }
+}
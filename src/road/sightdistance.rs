@@ -0,0 +1,326 @@
+/// Comprobación de visibilidad en acuerdos verticales.
+///
+/// The minimum parameter Kv of crest curves is given by the sight
+/// distance over the curve from the eye of the driver to an object,
+/// and in sag curves by the distance lit by the headlights at night.
+/// The formulas and default values are these of the Spanish 
+/// Norma 3.1-IC:
+///
+/// Stopping distance: D = V t / 3.6 + V^2 / (254 (fl + i))\
+/// Crest, D < L: Kv = D^2 / (2 (sqrt(h1) + sqrt(h2))^2)\
+/// Crest, D > L: L = 2 D - 2 (sqrt(h1) + sqrt(h2))^2 / theta\
+/// Sag, D < L: Kv = D^2 / (2 (h + D tan(beta)))\
+/// Sag, D > L: L = 2 D - 2 (h + D tan(beta)) / theta
+use std::fmt;
+use crate::format_station;
+use crate::road::verticalalignment::VerticalAlignment;
+
+/// Longitudinal friction coefficient fl of the Norma 3.1-IC by 
+/// design speed in km/h
+const LONGITUDINAL_FRICTION: [(f64, f64); 11] = [
+   (40.0, 0.432), (50.0, 0.411), (60.0, 0.390), (70.0, 0.369), (80.0, 0.348),
+   (90.0, 0.334), (100.0, 0.320), (110.0, 0.306), (120.0, 0.291), (130.0, 0.277),
+   (140.0, 0.263)
+];
+
+/// Parameters of the sight distance checks. Heights in meters,
+/// speed in km/h
+#[derive(Debug, Clone, Copy)]
+pub struct SightDistanceParams {
+   pub design_speed: f64,
+   /// Perception and reaction time in seconds
+   pub reaction_time: f64,
+   /// Height of the eye of the driver
+   pub eye_height: f64,
+   /// Height of the obstacle in the stopping distance check
+   pub object_height: f64,
+   /// Height of the oncoming vehicle in the overtaking check
+   pub overtaking_object_height: f64,
+   /// Height of the headlights in sag curves
+   pub headlight_height: f64,
+   /// Upward divergence of the headlight beam in sexagesimal degrees
+   pub headlight_angle: f64,
+   /// Longitudinal friction coefficient. If None it is taken from
+   /// the table of the Norma 3.1-IC for the design speed
+   pub friction: Option<f64>,
+   /// Overtaking sight distance required in crest curves. If None
+   /// overtaking isn't checked
+   pub overtaking_distance: Option<f64>,
+   /// Traffic in both directions. If false only the direction of
+   /// increasing stations is checked
+   pub two_way: bool
+}
+
+impl SightDistanceParams {
+   /// Parameters with the default values of the Norma 3.1-IC
+   pub fn new(design_speed: f64) -> Self {
+      if design_speed <= 0.0 {
+         panic!("Design speed must be greater than zero");
+      }
+      SightDistanceParams{design_speed, reaction_time: 2.0, eye_height: 1.1,
+         object_height: 0.5, overtaking_object_height: 1.1, headlight_height: 0.75,
+         headlight_angle: 1.0, friction: None, overtaking_distance: None, two_way: true}
+   }
+   /// Friction coefficient used in the stopping distance
+   pub fn friction(&self) -> Result<f64, String> {
+      match self.friction {
+         Some(f) => Ok(f),
+         None => longitudinal_friction(self.design_speed).ok_or(format!(
+            "No longitudinal friction for the design speed {} km/h", self.design_speed))
+      }
+   }
+   /// Stopping distance on a grade i, negative downwards
+   pub fn stopping_distance(&self, grade: f64) -> Result<f64, String> {
+      let v = self.design_speed;
+      let f = self.friction()? + grade;
+      if f <= 0.0 {
+         return Err(format!("Grade {} too steep for the stopping distance", grade));
+      }
+      Ok(v * self.reaction_time / 3.6 + v * v / 254.0 / f)
+   }
+}
+
+/// Longitudinal friction coefficient of the Norma 3.1-IC, linearly
+/// interpolated. None if the speed is out of the table (40 to 140 km/h)
+pub fn longitudinal_friction(design_speed: f64) -> Option<f64> {
+   LONGITUDINAL_FRICTION.windows(2).find_map(|w| {
+      let ((v0, f0), (v1, f1)) = (w[0], w[1]);
+      if design_speed >= v0 && design_speed <= v1 {
+         Some(f0 + (f1 - f0) * (design_speed - v0) / (v1 - v0))
+      } else {
+         None
+      }
+   })
+}
+
+/// Minimum Kv of a crest curve for the sight distance d (case D < L)
+pub fn crest_kv_for_distance(d: f64, eye_height: f64, object_height: f64) -> f64 {
+   let h = (eye_height.sqrt() + object_height.sqrt()).powi(2);
+   d * d / 2.0 / h
+}
+/// Minimum Kv of a sag curve for the headlight distance d (case D < L)
+pub fn sag_kv_for_distance(d: f64, headlight_height: f64, headlight_angle: f64) -> f64 {
+   d * d / 2.0 / (headlight_height + d * headlight_angle.to_radians().tan())
+}
+/// Sight distance available over a crest curve of length l and 
+/// change of grade theta
+pub fn crest_sight_distance(l: f64, theta: f64, eye_height: f64, object_height: f64) -> f64 {
+   let h = (eye_height.sqrt() + object_height.sqrt()).powi(2);
+   let theta = theta.abs();
+   let d = (2.0 * h * l / theta).sqrt();
+   if d <= l {
+      d
+   } else {
+      l / 2.0 + h / theta
+   }
+}
+/// Headlight distance available in a sag curve of length l and 
+/// change of grade theta. Infinite if the beam never meets the road
+pub fn sag_sight_distance(l: f64, theta: f64, headlight_height: f64, headlight_angle: f64) -> f64 {
+   let theta = theta.abs();
+   let kv = l / theta;
+   let t = headlight_angle.to_radians().tan();
+   let d = kv * t + ((kv * t).powi(2) + 2.0 * kv * headlight_height).sqrt();
+   if d <= l {
+      d
+   } else if theta > t {
+      (l * theta + 2.0 * headlight_height) / 2.0 / (theta - t)
+   } else {
+      f64::INFINITY
+   }
+}
+
+/// Result of the check of one vertical curve
+#[derive(Debug, Clone, Copy)]
+pub struct CurveSightCheck {
+   /// Number of the vertex, as in the Alzado table
+   pub id_tramo: usize,
+   pub vertex_station: f64,
+   pub kv: f64,
+   pub is_crest: bool,
+   pub required_distance: f64,
+   /// Sight distance in crest curves, headlight distance in sag curves
+   pub available_distance: f64,
+   /// Kv needed for the required distance
+   pub required_kv: f64,
+   /// Required and available overtaking distance, only in crest curves
+   /// and if the overtaking distance is checked
+   pub overtaking: Option<(f64, f64)>
+}
+
+impl CurveSightCheck {
+   pub fn stopping_ok(&self) -> bool {
+      self.available_distance >= self.required_distance
+   }
+   pub fn overtaking_ok(&self) -> bool {
+      match self.overtaking {
+         Some((required, available)) => available >= required,
+         None => true
+      }
+   }
+   pub fn is_ok(&self) -> bool {
+      self.stopping_ok() && self.overtaking_ok()
+   }
+}
+
+#[derive(Debug, Clone)]
+pub struct SightDistanceReport {
+   pub checks: Vec<CurveSightCheck>
+}
+
+impl SightDistanceReport {
+   /// Checks that don't reach the required distances
+   pub fn failures(&self) -> Vec<&CurveSightCheck> {
+      self.checks.iter().filter(|c| !c.is_ok()).collect()
+   }
+}
+
+/// Checks the sight distance of every vertical curve of the alignment.
+/// The stopping distance is computed with the lowest of the grades of
+/// the curve, the most unfavourable one. On two way roads the grades
+/// in the opposite direction, with the sign changed, are also taken
+pub fn check_vertical_curves(va: &VerticalAlignment, params: &SightDistanceParams) 
+   -> Result<SightDistanceReport, String> {
+   let mut checks = Vec::<CurveSightCheck>::new();
+   for row in va.alzado().iter().filter(|r| r.longitud > 0.0) {
+      let mut grade = row.p_entrada.min(row.p_salida);
+      if params.two_way {
+         grade = grade.min(-row.p_entrada.max(row.p_salida));
+      }
+      let required_distance = params.stopping_distance(grade)?;
+      let is_crest = row.theta < 0.0;
+      let (available_distance, required_kv, overtaking) = if is_crest {
+         let available = crest_sight_distance(row.longitud, row.theta, 
+            params.eye_height, params.object_height);
+         let kv = crest_kv_for_distance(required_distance, params.eye_height, 
+            params.object_height);
+         let overtaking = params.overtaking_distance.map(|d| (d, crest_sight_distance(
+            row.longitud, row.theta, params.eye_height, params.overtaking_object_height)));
+         (available, kv, overtaking)
+      } else {
+         let available = sag_sight_distance(row.longitud, row.theta, 
+            params.headlight_height, params.headlight_angle);
+         let kv = sag_kv_for_distance(required_distance, params.headlight_height, 
+            params.headlight_angle);
+         (available, kv, None)
+      };
+      checks.push(CurveSightCheck{id_tramo: row.id_tramo, vertex_station: row.pk, kv: row.kv,
+         is_crest, required_distance, available_distance, required_kv, overtaking});
+   }
+   Ok(SightDistanceReport{checks})
+}
+
+impl fmt::Display for SightDistanceReport {
+   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+      writeln!(f, "{:>4} {:>12} {:>6} {:>10} {:>10} {:>10} {:>10} {:>6}", "Id", "PK", "Type",
+         "Kv", "Kv min", "D req.", "D avail.", "Check")?;
+      for c in &self.checks {
+         writeln!(f, "{:>4} {:>12} {:>6} {:>10.0} {:>10.0} {:>10.1} {:>10.1} {:>6}", c.id_tramo,
+            format_station(c.vertex_station), if c.is_crest { "Crest" } else { "Sag" },
+            c.kv.abs(), c.required_kv, c.required_distance, c.available_distance, 
+            if c.stopping_ok() { "OK" } else { "FAIL" })?;
+         if let Some((required, available)) = c.overtaking {
+            writeln!(f, "{:>4} {:>12} {:>6} {:>10} {:>10} {:>10.1} {:>10.1} {:>6}", "", "", 
+               "Overt.", "", "", required, available, if c.overtaking_ok() { "OK" } else { "FAIL" })?;
+         }
+      }
+      Ok(())
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use crate::{eq, eq001, eq01};
+   use crate::road::verticalalignment::tests::sample_alignment;
+
+   #[test]
+   fn test_longitudinal_friction() {
+      assert!(eq(longitudinal_friction(80.0).unwrap(), 0.348));
+      assert!(eq(longitudinal_friction(85.0).unwrap(), 0.341));
+      assert!(longitudinal_friction(30.0).is_none());
+      assert!(longitudinal_friction(150.0).is_none());
+   }
+   #[test]
+   fn test_stopping_distance() {
+      let params = SightDistanceParams::new(100.0);
+      // 55.556 + 10000 / (254 * 0.32)
+      assert!(eq01(params.stopping_distance(0.0).unwrap(), 178.584));
+      assert!(params.stopping_distance(-0.04).unwrap() > 178.584);
+      assert!(params.stopping_distance(-0.5).is_err());
+      assert!(SightDistanceParams::new(160.0).stopping_distance(0.0).is_err());
+   }
+   #[test]
+   fn test_crest() {
+      let kv = crest_kv_for_distance(178.584, 1.1, 0.5);
+      // Available distance of a curve with that Kv is the distance
+      let theta = -0.04_f64;
+      let l = kv * theta.abs();
+      assert!(eq001(crest_sight_distance(l, theta, 1.1, 0.5), 178.584));
+      // Short curve: D > L
+      let d = crest_sight_distance(50.0, theta, 1.1, 0.5);
+      assert!(d > 50.0);
+      assert!(eq001(50.0, 2.0 * d - 2.0 * (1.1_f64.sqrt() + 0.5_f64.sqrt()).powi(2) / 0.04));
+   }
+   #[test]
+   fn test_sag() {
+      let kv = sag_kv_for_distance(178.584, 0.75, 1.0);
+      let theta = 0.05;
+      let l = kv * theta;
+      assert!(eq001(sag_sight_distance(l, theta, 0.75, 1.0), 178.584));
+      let t = 1.0_f64.to_radians().tan();
+      let d = sag_sight_distance(60.0, theta, 0.75, 1.0);
+      assert!(d > 60.0);
+      assert!(eq001(60.0, 2.0 * d - 2.0 * (0.75 + d * t) / theta));
+      assert!(sag_sight_distance(60.0, 0.01, 0.75, 1.0).is_infinite());
+   }
+   #[test]
+   fn test_check_vertical_curves() {
+      let va = sample_alignment();
+      let mut params = SightDistanceParams::new(80.0);
+      params.overtaking_distance = Some(700.0);
+      let report = check_vertical_curves(&va, &params).unwrap();
+      assert_eq!(report.checks.len(), 2);
+      // Crest Kv 7000 of 53 m
+      let c = report.checks[0];
+      assert!(c.is_crest);
+      assert_eq!(c.id_tramo, 2);
+      assert!(c.stopping_ok());
+      assert!(!c.overtaking_ok());
+      // Sag Kv 5000
+      let c = report.checks[1];
+      assert!(!c.is_crest);
+      assert!(c.overtaking.is_none());
+      assert!(c.stopping_ok());
+      assert_eq!(report.failures().len(), 1);
+      let text = format!("{}", report);
+      assert_eq!(text.lines().count(), 4);
+      assert!(text.contains("FAIL"));
+   }
+   #[test]
+   fn test_check_fails_stopping() {
+      let va = sample_alignment();
+      let report = check_vertical_curves(&va, &SightDistanceParams::new(140.0)).unwrap();
+      // Kv 5000 sag isn't enough for 140 km/h
+      assert!(!report.checks[1].stopping_ok());
+      assert!(report.checks[1].required_kv > 5000.0);
+   }
+   #[test]
+   fn test_check_opposite_direction() {
+      use crate::road::verticalalignment::{Pvi, PviCurve};
+      // Crest from +8% to -1%: the downgrade of 8% is in the opposite
+      // direction
+      let pvis = [Pvi::new(0.0, 100.0, PviCurve::None), Pvi::new(500.0, 140.0, PviCurve::Kv(6500.0)),
+         Pvi::new(1500.0, 130.0, PviCurve::None)];
+      let va = VerticalAlignment::from_pvis(&pvis).unwrap();
+      let mut params = SightDistanceParams::new(100.0);
+      params.two_way = false;
+      let c = check_vertical_curves(&va, &params).unwrap().checks[0];
+      assert!(c.stopping_ok());
+      assert!(eq01(c.required_distance, params.stopping_distance(-0.01).unwrap()));
+      params.two_way = true;
+      let c = check_vertical_curves(&va, &params).unwrap().checks[0];
+      assert!(!c.stopping_ok());
+      assert!(eq01(c.required_distance, params.stopping_distance(-0.08).unwrap()));
+   }
+}
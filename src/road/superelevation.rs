@@ -0,0 +1,239 @@
+/// Peralte: pendientes transversales de la calzada a lo largo del eje.
+///
+/// The cross slope of each side is measured from the axis toward the
+/// edge, positive if the pavement rises and negative if it falls:
+/// a normal crown (bombeo) of 2% is (-0.02, -0.02) and a full 
+/// superelevation of 7% in a rightward curve is (0.07, -0.07).
+///
+/// The model is a list of key stations with the cross slopes of both
+/// sides. Between two keys the slopes change linearly or with a smoothed
+/// (cubic) transition, whose gradient is zero at both keys. Before the 
+/// first key and after the last one the slopes are constant
+use crate::tolerances::Tolerances;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SlopeTransition {
+   Linear,
+   /// Cubic transition 3t^2 - 2t^3, without breaks in the edge profile
+   Smoothed
+}
+
+/// Cross slopes at a station. The transition is used between this key
+/// and the next one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CrossSlopeKey {
+   pub station: f64,
+   pub left: f64,
+   pub right: f64,
+   pub transition: SlopeTransition
+}
+
+pub struct Superelevation {
+   pub keys: Vec<CrossSlopeKey>,
+   pub tolerances: Tolerances
+}
+
+impl Superelevation {
+   pub fn new() -> Self {
+      Superelevation::with_tolerances(Tolerances::default())
+   }
+   pub fn with_tolerances(tolerances: Tolerances) -> Self {
+      Superelevation{keys: Vec::<CrossSlopeKey>::new(), tolerances}
+   }
+   /// Adds a key after the last one. A key at the same station as the
+   /// last one, within the linear tolerance, replaces it. It panics if
+   /// the station is less than the station of the last key
+   pub fn add_key(&mut self, station: f64, left: f64, right: f64, transition: SlopeTransition) {
+      let key = CrossSlopeKey{station, left, right, transition};
+      if let Some(last) = self.keys.last_mut() {
+         if self.tolerances.eq_linear(last.station, station) {
+            *last = key;
+            return;
+         }
+         if station < last.station {
+            panic!("Superelevation keys must be added in increasing stations");
+         }
+      }
+      self.keys.push(key);
+   }
+   /// Index of the key that starts the stretch of the station and 
+   /// parameter t in [0, 1] of the station in the stretch. None before
+   /// the first key and after the last one
+   fn stretch(&self, station: f64) -> Option<(usize, f64)> {
+      let i = self.keys.windows(2).position(|w| station >= w[0].station && station <= w[1].station)?;
+      let (k0, k1) = (&self.keys[i], &self.keys[i + 1]);
+      Some((i, (station - k0.station) / (k1.station - k0.station)))
+   }
+   /// Cross slopes (left, right) at the station
+   pub fn cross_slopes_at(&self, station: f64) -> (f64, f64) {
+      if self.keys.is_empty() {
+         panic!("Superelevation without keys");
+      }
+      match self.stretch(station) {
+         Some((i, t)) => {
+            let (k0, k1) = (&self.keys[i], &self.keys[i + 1]);
+            let f = shape(k0.transition, t);
+            (k0.left + (k1.left - k0.left) * f, k0.right + (k1.right - k0.right) * f)
+         },
+         None => {
+            let key = if station < self.keys[0].station { &self.keys[0] } 
+               else { &self.keys[self.keys.len() - 1] };
+            (key.left, key.right)
+         }
+      }
+   }
+   /// Superelevation at the station: the mean rotation of the pavement,
+   /// positive if it falls toward the right. Zero with a normal crown
+   pub fn superelevation_at(&self, station: f64) -> f64 {
+      let (left, right) = self.cross_slopes_at(station);
+      (left - right) / 2.0
+   }
+   /// Relative gradient (left, right) of the edges at distance width
+   /// from the axis: the grade of each edge minus the grade of the axis
+   pub fn relative_gradient_at(&self, station: f64, width: f64) -> (f64, f64) {
+      if self.keys.is_empty() {
+         panic!("Superelevation without keys");
+      }
+      match self.stretch(station) {
+         Some((i, t)) => {
+            let (k0, k1) = (&self.keys[i], &self.keys[i + 1]);
+            let df = shape_derivative(k0.transition, t) / (k1.station - k0.station);
+            (width * (k1.left - k0.left) * df, width * (k1.right - k0.right) * df)
+         },
+         None => (0.0, 0.0)
+      }
+   }
+   /// Maximum absolute relative gradient of the edges at distance width
+   /// in every stretch between keys, with its start station
+   pub fn max_relative_gradients(&self, width: f64) -> Vec<(f64, f64)> {
+      self.keys.windows(2).map(|w| {
+         let t_max = match w[0].transition { SlopeTransition::Linear => 0.0, SlopeTransition::Smoothed => 0.5 };
+         let station = w[0].station + t_max * (w[1].station - w[0].station);
+         let (left, right) = self.relative_gradient_at(station, width);
+         (w[0].station, left.abs().max(right.abs()))
+      }).collect()
+   }
+   /// Stations where the left or the right cross slope passes through
+   /// zero, in increasing order. The slopes are monotonic between keys,
+   /// so each side crosses zero at most once in every stretch
+   pub fn zero_slope_stations(&self) -> Vec<f64> {
+      let mut stations = Vec::<f64>::new();
+      for w in self.keys.windows(2) {
+         for (q0, q1) in [(w[0].left, w[1].left), (w[0].right, w[1].right)] {
+            if q0 != q1 && q0 * q1 <= 0.0 {
+               let t = inverse_shape(w[0].transition, q0 / (q0 - q1));
+               stations.push(w[0].station + t * (w[1].station - w[0].station));
+            }
+         }
+      }
+      stations.sort_by(|a, b| a.total_cmp(b));
+      stations
+   }
+}
+
+/// Fraction of the change of slope at parameter t of the stretch
+fn shape(transition: SlopeTransition, t: f64) -> f64 {
+   match transition {
+      SlopeTransition::Linear => t,
+      SlopeTransition::Smoothed => t * t * (3.0 - 2.0 * t)
+   }
+}
+/// Parameter t of the stretch where the fraction of the change of
+/// slope is f, in [0, 1]
+fn inverse_shape(transition: SlopeTransition, f: f64) -> f64 {
+   match transition {
+      SlopeTransition::Linear => f,
+      SlopeTransition::Smoothed => 0.5 - ((1.0 - 2.0 * f).asin() / 3.0).sin()
+   }
+}
+fn shape_derivative(transition: SlopeTransition, t: f64) -> f64 {
+   match transition {
+      SlopeTransition::Linear => 1.0,
+      SlopeTransition::Smoothed => 6.0 * t * (1.0 - t)
+   }
+}
+
+impl Default for Superelevation {
+   fn default() -> Self {
+      Self::new()
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use crate::{eq, eq001};
+
+   /// Rightward curve from 200 to 300 with 6%, transitions of 60 m
+   fn sample_superelevation(transition: SlopeTransition) -> Superelevation {
+      let mut se = Superelevation::new();
+      se.add_key(120.0, -0.02, -0.02, transition);
+      se.add_key(140.0, 0.0, -0.02, transition);
+      se.add_key(200.0, 0.06, -0.06, transition);
+      se.add_key(300.0, 0.06, -0.06, transition);
+      se.add_key(360.0, -0.02, -0.02, transition);
+      se
+   }
+
+   #[test]
+   fn test_cross_slopes_at() {
+      let se = sample_superelevation(SlopeTransition::Linear);
+      assert_eq!(se.cross_slopes_at(0.0), (-0.02, -0.02));
+      let (left, right) = se.cross_slopes_at(130.0);
+      assert!(eq(left, -0.01));
+      assert!(eq(right, -0.02));
+      let (left, right) = se.cross_slopes_at(170.0);
+      assert!(eq(left, 0.03));
+      assert!(eq(right, -0.04));
+      assert!(eq(se.superelevation_at(250.0), 0.06));
+      assert!(eq(se.superelevation_at(400.0), 0.0));
+   }
+   #[test]
+   fn test_relative_gradient() {
+      let se = sample_superelevation(SlopeTransition::Linear);
+      // 3.5 m lane: 0.06 * 3.5 / 60
+      let (left, right) = se.relative_gradient_at(170.0, 3.5);
+      assert!(eq(left, 0.0035));
+      assert!(eq(right, -0.04 * 3.5 / 60.0));
+      assert_eq!(se.relative_gradient_at(250.0, 3.5), (0.0, 0.0));
+      let max = se.max_relative_gradients(3.5);
+      assert_eq!(max.len(), 4);
+      assert!(eq(max[1].1, 0.0035));
+   }
+   #[test]
+   fn test_smoothed() {
+      let se = sample_superelevation(SlopeTransition::Smoothed);
+      // Same slopes at the keys and in the middle of the stretches
+      let (left, _right) = se.cross_slopes_at(170.0);
+      assert!(eq(left, 0.03));
+      assert!(eq(se.cross_slopes_at(200.0).0, 0.06));
+      // Zero gradient at the keys, 1.5 times the linear one in the middle
+      assert!(eq(se.relative_gradient_at(140.0 + 1e-9, 3.5).0, 0.0));
+      assert!(eq001(se.relative_gradient_at(170.0, 3.5).0, 1.5 * 0.0035));
+      assert!(eq001(se.max_relative_gradients(3.5)[1].1, 1.5 * 0.0035));
+   }
+   #[test]
+   fn test_zero_slope_stations() {
+      let zeros = sample_superelevation(SlopeTransition::Linear).zero_slope_stations();
+      assert_eq!(zeros.len(), 3);
+      assert!(eq(zeros[0], 140.0) && eq(zeros[2], 345.0));
+      let se = sample_superelevation(SlopeTransition::Smoothed);
+      let zeros = se.zero_slope_stations();
+      assert!(eq001(zeros[2], 300.0 + 60.0 * (0.5 + (std::f64::consts::PI / 18.0).sin())));
+      assert!(eq(se.cross_slopes_at(zeros[2]).0, 0.0));
+   }
+   #[test]
+   fn test_add_key_replaces() {
+      let mut se = Superelevation::new();
+      se.add_key(0.0, -0.02, -0.02, SlopeTransition::Linear);
+      se.add_key(0.0005, 0.0, -0.02, SlopeTransition::Linear);
+      assert_eq!(se.keys.len(), 1);
+      assert!(eq(se.keys[0].left, 0.0));
+   }
+   #[test]
+   #[should_panic]
+   fn test_add_key_panic() {
+      let mut se = sample_superelevation(SlopeTransition::Linear);
+      se.add_key(100.0, -0.02, -0.02, SlopeTransition::Linear);
+   }
+}
@@ -0,0 +1,301 @@
+/// Diseño automático del peralte según la Norma 3.1-IC.
+///
+/// For every circular curve of the axis the superelevation is taken
+/// from the radius-superelevation relations of the norm, and the
+/// rotation is laid out along the adjoining transition curves:
+///
+/// 1. In the tangent, before the transition curve, the outer side goes
+///    from the crown slope (bombeo) to flat.
+/// 2. Along the transition curve the outer side rises linearly from flat
+///    to the full superelevation. The inner side keeps the crown slope
+///    until the outer side reaches it, and then both sides rotate
+///    together to the full superelevation at the start of the circle.
+///
+/// The exit of the curve is symmetric. The rules that can't be fulfilled
+/// are returned in a report, and the superelevation is laid out anyway
+use crate::format_station;
+use crate::road::roadaxis::RoadAxis;
+use crate::road::superelevation::{Superelevation, SlopeTransition};
+
+/// Transverse friction coefficient ft of the Norma 3.1-IC by design
+/// speed in km/h
+const TRANSVERSE_FRICTION: [(f64, f64); 11] = [
+   (40.0, 0.180), (50.0, 0.166), (60.0, 0.151), (70.0, 0.137), (80.0, 0.122),
+   (90.0, 0.113), (100.0, 0.104), (110.0, 0.096), (120.0, 0.087), (130.0, 0.078),
+   (140.0, 0.069)
+];
+
+/// Road groups of the Norma 3.1-IC, with different radius-superelevation
+/// relations
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RoadGroup {
+   /// Autopistas, autovías, vías rápidas y carreteras C-100
+   Group1,
+   /// Carreteras C-80, C-60, C-50 y C-40
+   Group2
+}
+
+impl RoadGroup {
+   /// Superelevation for the radius, as a fraction. None if the radius
+   /// is so large that the crown is kept. Radius under the range of the
+   /// norm get the maximum superelevation
+   pub fn superelevation(&self, radius: f64) -> Option<f64> {
+      let r = radius.abs();
+      let (r1, p_max, k, r2, r3) = match self {
+         RoadGroup::Group1 => (700.0, 8.0, 7.3, 5000.0, 7500.0),
+         RoadGroup::Group2 => (350.0, 7.0, 6.08, 2500.0, 3500.0)
+      };
+      let p = if r <= r1 {
+         p_max
+      } else if r <= r2 {
+         p_max - k * (1.0 - r1 / r).powf(1.3)
+      } else if r <= r3 {
+         2.0
+      } else {
+         return None;
+      };
+      Some(p / 100.0)
+   }
+   /// Minimum radius of the radius-superelevation relations
+   pub fn min_radius(&self) -> f64 {
+      match self {
+         RoadGroup::Group1 => 250.0,
+         RoadGroup::Group2 => 50.0
+      }
+   }
+}
+
+/// Parameters of the superelevation design
+#[derive(Debug, Clone, Copy)]
+pub struct SuperelevationParams {
+   pub design_speed: f64,
+   pub group: RoadGroup,
+   /// Crown slope (bombeo), as a positive fraction
+   pub crown: f64,
+   /// Distance from the axis of rotation to the edge of the pavement
+   pub width: f64,
+   /// Maximum relative gradient of the edge. If None it is taken
+   /// from the norm for the design speed
+   pub max_edge_gradient: Option<f64>
+}
+
+impl SuperelevationParams {
+   /// Parameters with crown of 2% and a 3.5 m lane
+   pub fn new(design_speed: f64, group: RoadGroup) -> Self {
+      if design_speed <= 0.0 {
+         panic!("Design speed must be greater than zero");
+      }
+      SuperelevationParams{design_speed, group, crown: 0.02, width: 3.5, max_edge_gradient: None}
+   }
+   /// Maximum relative gradient of the edge: 1.8 - 0.01 V (%) under
+   /// 80 km/h and 0.9 - 0.005 V (%) from 80 km/h
+   pub fn max_edge_gradient(&self) -> f64 {
+      match self.max_edge_gradient {
+         Some(ip) => ip,
+         None if self.design_speed < 80.0 => (1.8 - 0.01 * self.design_speed) / 100.0,
+         None => (0.9 - 0.005 * self.design_speed) / 100.0
+      }
+   }
+   /// Minimum radius for the design speed with the superelevation
+   /// of the radius: V^2 = 127 R (ft + p). None if the speed is out
+   /// of the friction table
+   pub fn min_radius_for_speed(&self) -> Option<f64> {
+      let v = self.design_speed;
+      let ft = TRANSVERSE_FRICTION.windows(2).find_map(|w| {
+         let ((v0, f0), (v1, f1)) = (w[0], w[1]);
+         if v >= v0 && v <= v1 { Some(f0 + (f1 - f0) * (v - v0) / (v1 - v0)) } else { None }
+      })?;
+      // Minimum radius is in the range of the maximum superelevation
+      let p = self.group.superelevation(self.group.min_radius())?;
+      Some(v * v / 127.0 / (ft + p))
+   }
+}
+
+pub struct SuperelevationDesign {
+   pub superelevation: Superelevation,
+   /// Rules that couldn't be fulfilled, one line for each problem
+   pub issues: Vec<String>
+}
+
+/// Key of the design as (station, outer slope, inner slope)
+type DesignKey = (f64, f64, f64);
+
+/// Designs the superelevation of the axis. The curves are the sections
+/// with equal non zero radius at both ends, and the transition curves
+/// the sections with one of the radius zero
+pub fn design_superelevation(axis: &RoadAxis, params: &SuperelevationParams) -> SuperelevationDesign {
+   let tol = &axis.tolerances;
+   let b = params.crown;
+   let w = params.width;
+   let ip = params.max_edge_gradient();
+   let mut issues = Vec::<String>::new();
+   let mut superelevation = Superelevation::with_tolerances(*tol);
+   if axis.sections.is_empty() {
+      return SuperelevationDesign{superelevation, issues};
+   }
+   superelevation.add_key(axis.start_station, -b, -b, SlopeTransition::Linear);
+   let min_speed_radius = params.min_radius_for_speed();
+   if min_speed_radius.is_none() {
+      issues.push(format!("No transverse friction for the design speed {} km/h", params.design_speed));
+   }
+   for (i, section) in axis.sections.iter().enumerate() {
+      let r = section.start_radius();
+      if tol.is_zero_radius(r) || !tol.eq_radius(r, section.end_radius()) {
+         continue;
+      }
+      let pk = axis.section_start_station(i);
+      if r.abs() < params.group.min_radius() {
+         issues.push(format!("Curve at {}: radius {:.1} under the minimum {:.0} of the norm", 
+            format_station(pk), r.abs(), params.group.min_radius()));
+      }
+      if let Some(min) = min_speed_radius {
+         if r.abs() < min {
+            issues.push(format!("Curve at {}: radius {:.1} under the minimum {:.1} for {} km/h",
+               format_station(pk), r.abs(), min, params.design_speed));
+         }
+      }
+      let p = match params.group.superelevation(r) {
+         Some(p) => p,
+         None => continue
+      };
+      // Length of the crown stage and of the rotations
+      let lb = w * b / ip;
+      let ec = pk;
+      let ce = pk + section.length();
+      let entry = if i > 0 && axis.is_transition(i - 1, r) { axis.sections[i-1].length() } else { 0.0 };
+      let exit = if i + 1 < axis.sections.len() && axis.is_transition(i + 1, r) { 
+         axis.sections[i+1].length() } else { 0.0 };
+      let mut keys = Vec::<DesignKey>::new();
+      for (length, start, sign) in [(entry, ec, -1.0), (exit, ce, 1.0)] {
+         let length = if length == 0.0 {
+            issues.push(format!("Curve at {}: no transition curve at the {}, rotation laid out in the adjacent section",
+               format_station(pk), if sign < 0.0 { "entry" } else { "exit" }));
+            w * p / ip
+         } else {
+            if w * p / length > ip + 1e-12 {
+               issues.push(format!("Curve at {}: edge gradient {:.3}% over the maximum {:.3}% in the transition of {:.1} m",
+                  format_station(pk), 100.0 * w * p / length, 100.0 * ip, length));
+            }
+            length
+         };
+         // Stations from the circle toward the tangent
+         let te = start + sign * length;
+         let mut side = vec![(start, p, -p)];
+         if p > b {
+            side.push((te - sign * length * b / p, b, -b));
+         }
+         side.push((te, 0.0, -b));
+         side.push((te + sign * lb, -b, -b));
+         if sign < 0.0 {
+            side.reverse();
+         }
+         keys.extend(side);
+      }
+      // Right curves rise the left side
+      let outer_left = r > 0.0;
+      let mut overlap = false;
+      for (station, outer, inner) in keys {
+         if superelevation.keys.last().is_some_and(|k| station < k.station - tol.linear) {
+            overlap = true;
+            continue;
+         }
+         let (left, right) = if outer_left { (outer, inner) } else { (inner, outer) };
+         superelevation.add_key(station, left, right, SlopeTransition::Linear);
+      }
+      if overlap {
+         issues.push(format!("Curve at {}: superelevation transition overlaps the previous curve",
+            format_station(pk)));
+      }
+   }
+   if superelevation.keys.last().is_some_and(|k| k.station < axis.end_station()) {
+      superelevation.add_key(axis.end_station(), -b, -b, SlopeTransition::Linear);
+   }
+   SuperelevationDesign{superelevation, issues}
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use crate::{eq, eq001};
+   use crate::geom::point::Point;
+   use crate::road::htangent::HTangent;
+   use crate::road::hcircle::HCircle;
+   use crate::road::hsection::HSection;
+   use crate::road::roadaxis::tests::sample_axis;
+
+   #[test]
+   fn test_group_superelevation() {
+      assert!(eq(RoadGroup::Group1.superelevation(500.0).unwrap(), 0.08));
+      assert!(eq(RoadGroup::Group1.superelevation(6000.0).unwrap(), 0.02));
+      assert!(RoadGroup::Group1.superelevation(8000.0).is_none());
+      assert!(eq(RoadGroup::Group2.superelevation(-300.0).unwrap(), 0.07));
+      // Continuous at the limits of the ranges
+      assert!(eq001(RoadGroup::Group1.superelevation(5000.0).unwrap(), 
+         0.08 - 0.073 * (1.0 - 700.0 / 5000.0_f64).powf(1.3)));
+      let p = RoadGroup::Group2.superelevation(1000.0).unwrap();
+      assert!(p < 0.07 && p > 0.02);
+   }
+   #[test]
+   fn test_params() {
+      let params = SuperelevationParams::new(60.0, RoadGroup::Group2);
+      assert!(eq(params.max_edge_gradient(), 0.012));
+      assert!(eq(SuperelevationParams::new(100.0, RoadGroup::Group1).max_edge_gradient(), 0.004));
+      // 3600 / (127 (0.151 + 0.07))
+      assert!(eq001(params.min_radius_for_speed().unwrap(), 128.2645));
+      assert!(SuperelevationParams::new(30.0, RoadGroup::Group2).min_radius_for_speed().is_none());
+   }
+   #[test]
+   fn test_design() {
+      // Sample axis: rightward curve R=200 from 260 to 360, clothoids of 60 m
+      let axis = sample_axis();
+      let params = SuperelevationParams::new(60.0, RoadGroup::Group2);
+      let design = design_superelevation(&axis, &params);
+      assert!(design.issues.is_empty());
+      let se = &design.superelevation;
+      let lb = 3.5 * 0.02 / 0.012;
+      let (left, right) = se.cross_slopes_at(200.0 - lb);
+      assert!(eq(left, -0.02) && eq(right, -0.02));
+      let (left, right) = se.cross_slopes_at(200.0);
+      assert!(eq(left, 0.0) && eq(right, -0.02));
+      let (left, right) = se.cross_slopes_at(300.0);
+      assert!(eq(left, 0.07) && eq(right, -0.07));
+      // Outer side rises linearly along the clothoid, as the curvature
+      assert!(eq(se.cross_slopes_at(230.0).0, 0.035));
+      let (_left, right) = se.cross_slopes_at(200.0 + 60.0 * 2.0 / 7.0);
+      assert!(eq(right, -0.02));
+      // Exit symmetric
+      assert!(eq(se.cross_slopes_at(420.0).0, 0.0));
+      assert!(eq(se.cross_slopes_at(420.0 + lb).0, -0.02));
+      assert!(eq(se.cross_slopes_at(520.0).1, -0.02));
+      // Edge gradient in the clothoid
+      assert!(eq(se.relative_gradient_at(230.0, 3.5).0, 3.5 * 0.07 / 60.0));
+   }
+   #[test]
+   fn test_design_issues() {
+      // Group 1 at 100 km/h: R=200 too small, clothoids too short
+      let axis = sample_axis();
+      let params = SuperelevationParams::new(100.0, RoadGroup::Group1);
+      let design = design_superelevation(&axis, &params);
+      assert!(design.issues.iter().any(|s| s.contains("under the minimum 250")));
+      assert!(design.issues.iter().any(|s| s.contains("for 100 km/h")));
+      assert!(design.issues.iter().any(|s| s.contains("edge gradient")));
+   }
+   #[test]
+   fn test_design_left_curve_without_clothoids() {
+      let mut axis = RoadAxis::new();
+      let t1 = HTangent::new(Point::new(0.0, 0.0), 0.0, 100.0);
+      let c = HCircle::new(t1.end_point(), t1.end_azimuth(), -300.0, 100.0);
+      let t2 = HTangent::new(c.end_point(), c.end_azimuth(), 100.0);
+      axis.add_htangent(t1);
+      axis.add_hcircle(c);
+      axis.add_htangent(t2);
+      let params = SuperelevationParams::new(60.0, RoadGroup::Group2);
+      let design = design_superelevation(&axis, &params);
+      assert_eq!(design.issues.len(), 2);
+      let (left, right) = design.superelevation.cross_slopes_at(150.0);
+      assert!(eq(left, -0.07) && eq(right, 0.07));
+      // Rotation ends at the start of the circle
+      let l = 3.5 * 0.07 / 0.012;
+      assert!(eq(design.superelevation.cross_slopes_at(100.0 - l).1, 0.0));
+   }
+}
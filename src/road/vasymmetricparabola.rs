@@ -0,0 +1,160 @@
+/// VAsymmetricParabola is a vertical curve with different lengths
+/// before (L1) and after (L2) the vertex. It is made of two parabolas
+/// joined at the station of the vertex with a common grade. The curve
+/// passes at the vertical distance
+///
+/// e = L1 L2 theta / (2 (L1 + L2))
+///
+/// from the vertex, and the vertical curvature is 2e/L1^2 before the
+/// vertex and 2e/L2^2 after it
+use super::vsection::VSection;
+use crate::tolerances::Tolerances;
+
+pub struct VAsymmetricParabola {
+   /// Station of the start point TE
+   pub start_station: f64,
+   pub start_elevation: f64,
+   /// Grade in TE
+   pub start_grade: f64,
+   /// Grade in TS
+   pub end_grade: f64,
+   /// Length from TE to the vertex
+   pub length1: f64,
+   /// Length from the vertex to TS
+   pub length2: f64
+}
+
+impl VAsymmetricParabola {
+   pub fn new(start_station: f64, start_elevation: f64, start_grade: f64, 
+      end_grade: f64, length1: f64, length2: f64) -> Self {
+      VAsymmetricParabola::new_with_tolerances(start_station, start_elevation, start_grade, 
+         end_grade, length1, length2, &Tolerances::default())
+   }
+   /// Creates a new VAsymmetricParabola. Both lengths must be greater
+   /// than the linear tolerance and the grades can't be equal within
+   /// the angular tolerance
+   pub fn new_with_tolerances(start_station: f64, start_elevation: f64, start_grade: f64,
+      end_grade: f64, length1: f64, length2: f64, tol: &Tolerances) -> Self {
+      if length1 < 0.0 || length2 < 0.0 || tol.is_zero_length(length1) || tol.is_zero_length(length2) {
+         panic!("Lengths must be greater than zero");
+      }
+      if tol.eq_angular(start_grade, end_grade) {
+         panic!("Vertical curve can't have equal start and end grades");
+      }
+      VAsymmetricParabola{start_station, start_elevation, start_grade, end_grade, length1, length2}
+   }
+   pub fn theta(&self) -> f64 {
+      self.end_grade - self.start_grade
+   }
+   /// Station of the vertex, where both parabolas join
+   pub fn vertex_station(&self) -> f64 {
+      self.start_station + self.length1
+   }
+   /// Elevation of the vertex in the prolongation of the start grade
+   pub fn vertex_elevation(&self) -> f64 {
+      self.start_elevation + self.start_grade * self.length1
+   }
+   /// Common grade of both parabolas at the vertex station
+   pub fn middle_grade(&self) -> f64 {
+      self.start_grade + self.theta() * self.length2 / (self.length1 + self.length2)
+   }
+   /// Parameters Kv of the first and second parabolas
+   pub fn kv(&self) -> (f64, f64) {
+      let (l1, l2) = (self.length1, self.length2);
+      let t = self.theta() / (l1 + l2);
+      (1.0 / (t * l2 / l1), 1.0 / (t * l1 / l2))
+   }
+}
+
+impl VSection for VAsymmetricParabola {
+   fn start_station(&self) -> f64 {
+      self.start_station
+   }
+   fn length(&self) -> f64 {
+      self.length1 + self.length2
+   }
+   fn start_elevation(&self) -> f64 {
+      self.start_elevation
+   }
+   fn start_grade(&self) -> f64 {
+      self.start_grade
+   }
+   fn end_grade(&self) -> f64 {
+      self.end_grade
+   }
+   fn elevation_at(&self, station: f64) -> f64 {
+      let u = self.check_station(station);
+      let (kv1, kv2) = self.kv();
+      if u <= self.length1 {
+         self.start_elevation + self.start_grade * u + u * u / 2.0 / kv1
+      } else {
+         let v = u - self.length1;
+         let zm = self.elevation_at(self.vertex_station());
+         zm + self.middle_grade() * v + v * v / 2.0 / kv2
+      }
+   }
+   fn grade_at(&self, station: f64) -> f64 {
+      let u = self.check_station(station);
+      let (kv1, kv2) = self.kv();
+      if u <= self.length1 {
+         self.start_grade + u / kv1
+      } else {
+         self.middle_grade() + (u - self.length1) / kv2
+      }
+   }
+   fn vertical_curvature_at(&self, station: f64) -> f64 {
+      let u = self.check_station(station);
+      let (kv1, kv2) = self.kv();
+      if u < self.length1 {
+         1.0 / kv1
+      } else {
+         1.0 / kv2
+      }
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use crate::{eq, eq001};
+   use crate::road::vparabola::VParabola;
+
+   #[test]
+   fn test_new() {
+      let ap = VAsymmetricParabola::new(0.0, 100.0, 0.03, -0.02, 60.0, 120.0);
+      assert!(eq001(ap.length(), 180.0));
+      assert!(eq(ap.grade_at(0.0), 0.03));
+      assert!(eq(ap.grade_at(180.0), -0.02));
+      // Continuous grade at the vertex
+      assert!(eq(ap.grade_at(60.0 - 1e-9), ap.grade_at(60.0 + 1e-9)));
+      // End elevation on the end grade from the vertex
+      assert!(eq001(ap.end_elevation(), ap.vertex_elevation() - 0.02 * 120.0));
+   }
+   #[test]
+   fn test_vertex_and_flecha() {
+      let ap = VAsymmetricParabola::new(0.0, 100.0, 0.03, -0.02, 60.0, 120.0);
+      let (sv, zv) = ap.vertex().unwrap();
+      assert!(eq001(sv, 60.0));
+      assert!(eq001(zv, 101.8));
+      let e = 60.0 * 120.0 * -0.05 / 2.0 / 180.0;
+      assert!(eq001(ap.external(), e));
+      // Chord TE-TS at the vertex station
+      let chord = 100.0 + (ap.end_elevation() - 100.0) * 60.0 / 180.0;
+      assert!(eq001(ap.flecha(), chord - zv));
+   }
+   #[test]
+   fn test_symmetric() {
+      // Equal lengths give the symmetric parabola
+      let ap = VAsymmetricParabola::new(0.0, 100.0, 0.03, -0.02, 90.0, 90.0);
+      let vp = VParabola::new(0.0, 100.0, 0.03, -0.02, 180.0);
+      for s in [0.0, 45.0, 90.0, 135.0, 180.0] {
+         assert!(eq(ap.elevation_at(s), vp.elevation_at(s)));
+      }
+      assert!(eq(ap.flecha(), vp.flecha()));
+   }
+   #[test]
+   #[should_panic]
+   fn test_new_panic() {
+      let _ap = VAsymmetricParabola::new(0.0, 100.0, 0.03, -0.02, 0.0, 120.0);
+   }
+}
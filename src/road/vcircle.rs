@@ -0,0 +1,154 @@
+/// VCircle is a circular vertical curve, used in older designs instead
+/// of the parabola. The circle is tangent to the start and end grades
+/// in the vertical plane (station, elevation), so the elevation is
+///
+/// z = zc - sign(R) * sqrt(R^2 - (s - sc)^2)
+///
+/// with (sc, zc) the center and R the signed radius, positive in sag
+/// curves (center above the curve) and negative in crest curves
+use super::vsection::VSection;
+use crate::tolerances::Tolerances;
+
+pub struct VCircle {
+   /// Station of the start point TE
+   pub start_station: f64,
+   pub start_elevation: f64,
+   /// Grade in TE
+   pub start_grade: f64,
+   /// Grade in TS
+   pub end_grade: f64,
+   /// Signed radius, positive in sag curves
+   pub radius: f64
+}
+
+impl VCircle {
+   /// Creates a new VCircle. The sign of the radius is taken from
+   /// the change of grade, so only its absolute value is used
+   pub fn new(start_station: f64, start_elevation: f64, start_grade: f64, 
+      end_grade: f64, radius: f64) -> Self {
+      VCircle::new_with_tolerances(start_station, start_elevation, start_grade, 
+         end_grade, radius, &Tolerances::default())
+   }
+   /// Creates a new VCircle. The radius can't be zero within the
+   /// radius tolerance and the grades can't be equal within the angular
+   /// tolerance
+   pub fn new_with_tolerances(start_station: f64, start_elevation: f64, start_grade: f64,
+      end_grade: f64, radius: f64, tol: &Tolerances) -> Self {
+      if tol.is_zero_radius(radius) {
+         panic!("Vertical circle can't have radius zero");
+      }
+      if tol.eq_angular(start_grade, end_grade) {
+         panic!("Vertical curve can't have equal start and end grades");
+      }
+      let radius = radius.abs() * (end_grade - start_grade).signum();
+      VCircle{start_station, start_elevation, start_grade, end_grade, radius}
+   }
+   /// Creates the VCircle with the given horizontal length between TE and TS
+   pub fn from_length(start_station: f64, start_elevation: f64, start_grade: f64,
+      end_grade: f64, length: f64) -> Self {
+      let sines = (end_grade.atan().sin() - start_grade.atan().sin()).abs();
+      if length <= 0.0 || sines == 0.0 {
+         panic!("Vertical circle needs length greater than zero and different grades");
+      }
+      VCircle::new(start_station, start_elevation, start_grade, end_grade, length / sines)
+   }
+   /// Center of the circle in the vertical plane (station, elevation)
+   pub fn center(&self) -> (f64, f64) {
+      let a0 = self.start_grade.atan();
+      (self.start_station - self.radius * a0.sin(), self.start_elevation + self.radius * a0.cos())
+   }
+   /// Horizontal distance from the center to the station
+   fn distance_to_center(&self, station: f64) -> f64 {
+      station - self.center().0
+   }
+}
+
+impl VSection for VCircle {
+   fn start_station(&self) -> f64 {
+      self.start_station
+   }
+   fn length(&self) -> f64 {
+      self.radius * (self.end_grade.atan().sin() - self.start_grade.atan().sin())
+   }
+   fn start_elevation(&self) -> f64 {
+      self.start_elevation
+   }
+   fn start_grade(&self) -> f64 {
+      self.start_grade
+   }
+   fn end_grade(&self) -> f64 {
+      self.end_grade
+   }
+   fn elevation_at(&self, station: f64) -> f64 {
+      self.check_station(station);
+      let d = self.distance_to_center(station);
+      let (_sc, zc) = self.center();
+      zc - self.radius.signum() * (self.radius * self.radius - d * d).sqrt()
+   }
+   fn grade_at(&self, station: f64) -> f64 {
+      self.check_station(station);
+      let d = self.distance_to_center(station);
+      self.radius.signum() * d / (self.radius * self.radius - d * d).sqrt()
+   }
+   fn vertical_curvature_at(&self, station: f64) -> f64 {
+      self.check_station(station);
+      let d = self.distance_to_center(station);
+      let r2 = self.radius * self.radius;
+      self.radius.signum() * r2 / (r2 - d * d).powf(1.5)
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use crate::{eq, eq001};
+
+   #[test]
+   fn test_new() {
+      let vc = VCircle::new(100.0, 500.0, 0.03, -0.02, 4000.0);
+      assert!(eq(vc.radius, -4000.0));
+      // Close to the parabola with Kv = R
+      assert!((vc.length() - 200.0).abs() < 0.2);
+      assert!(eq(vc.grade_at(100.0), 0.03));
+      assert!(eq(vc.grade_at(vc.end_station()), -0.02));
+      assert!(eq001(vc.elevation_at(100.0), 500.0));
+   }
+   #[test]
+   fn test_exact_circle() {
+      // Every point is at distance R from the center
+      let vc = VCircle::new(0.0, 100.0, -0.04, 0.05, 3000.0);
+      let (sc, zc) = vc.center();
+      for s in [0.0, 50.0, 133.0, vc.length()] {
+         let z = vc.elevation_at(s);
+         assert!(eq001(((s - sc).powi(2) + (z - zc).powi(2)).sqrt(), 3000.0));
+      }
+      // Vertical curvature at the lowest point is 1/R
+      let low = sc;
+      assert!(eq(vc.grade_at(low), 0.0));
+      assert!(eq(vc.vertical_curvature_at(low), 1.0 / 3000.0));
+   }
+   #[test]
+   fn test_vertex_and_flecha() {
+      let vc = VCircle::new(0.0, 100.0, 0.02, -0.02, 5000.0);
+      // Symmetric grades: vertex in the middle
+      let (sv, zv) = vc.vertex().unwrap();
+      assert!(eq001(sv, vc.length() / 2.0));
+      assert!(eq001(zv, 100.0 + 0.02 * sv));
+      // Exact external of the circle: R (1/cos(a) - 1) measured on 
+      // the vertical through the vertex
+      let a = 0.02_f64.atan();
+      assert!(eq001(vc.external(), -5000.0 * (1.0 - a.cos()) / a.cos()));
+      assert!(eq001(vc.flecha(), -0.02 * sv));
+   }
+   #[test]
+   fn test_from_length() {
+      let vc = VCircle::from_length(0.0, 100.0, 0.02, -0.03, 250.0);
+      assert!(eq(vc.length(), 250.0));
+      assert!(vc.radius < 0.0);
+   }
+   #[test]
+   #[should_panic]
+   fn test_new_panic() {
+      let _vc = VCircle::new(0.0, 100.0, 0.02, 0.02, 5000.0);
+   }
+}
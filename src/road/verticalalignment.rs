@@ -0,0 +1,866 @@
+/// Alzado (vertical alignment) de una carretera.
+/// Es un vector de alineaciones vsection consecutivas
+///
+/// It gives the elevation, grade and vertical curvature at any station
+/// of the axis, and the table of vertices with the fields of the 
+/// Alzado sheets (see AlzadoRow)
+use std::fmt;
+use crate::{format_station, solve_newton_bisection};
+use crate::road::vsection::VSection;
+use crate::road::vgrade::VGrade;
+use crate::road::vparabola::VParabola;
+use crate::road::vcircle::VCircle;
+use crate::road::vasymmetricparabola::VAsymmetricParabola;
+use crate::tolerances::Tolerances;
+
+pub struct VerticalAlignment {
+   pub sections: Vec<Box<dyn VSection>>,
+   /// Tolerances used in the checks of the alignment
+   pub tolerances: Tolerances
+}
+
+/// Kind of the vertical curves when importing an Alzado sheet, that
+/// doesn't record it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VCurveKind {
+   /// Parabolas, asymmetric if the vertex isn't in the middle of TE-TS
+   Parabola,
+   /// Circles, with the radius given by the length LONGITUD
+   Circle
+}
+
+/// Vertical curve of a PVI, given by its parameter Kv or its length.
+/// The sign of Kv is ignored, it is taken from the change of grade
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PviCurve {
+   None,
+   Kv(f64),
+   Length(f64)
+}
+
+/// Point of intersection of two grades (vértice, PIV) of the design
+/// of the profile
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pvi {
+   pub station: f64,
+   pub elevation: f64,
+   pub curve: PviCurve
+}
+
+impl Pvi {
+   pub fn new(station: f64, elevation: f64, curve: PviCurve) -> Self {
+      Pvi{station, elevation, curve}
+   }
+}
+
+/// A vertex of the vertical alignment with the columns of the Alzado
+/// sheet. Vertices without vertical curve have zero length, with TE
+/// and TS in the vertex. Grades are tangents (0.02 is 2%)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AlzadoRow {
+   /// ID_TRAMO: number of the vertex, from 1
+   pub id_tramo: usize,
+   /// PK: station of the vertex
+   pub pk: f64,
+   /// TE: station of the start of the vertical curve
+   pub te: f64,
+   /// COTA_TE: elevation of TE
+   pub cota_te: f64,
+   /// P_ENTRADA: grade before the vertex
+   pub p_entrada: f64,
+   /// LONGITUD: length of the vertical curve
+   pub longitud: f64,
+   /// KV: parameter of the curve, negative in crest curves
+   pub kv: f64,
+   /// COTA: elevation of the vertex
+   pub cota: f64,
+   /// TS: station of the end of the vertical curve
+   pub ts: f64,
+   /// COTA_TS: elevation of TS
+   pub cota_ts: f64,
+   /// P_SALIDA: grade after the vertex
+   pub p_salida: f64,
+   /// FLECHA: vertical distance from the vertex to the middle of the
+   /// chord TE-TS
+   pub flecha: f64,
+   /// THETA: change of grade, P_SALIDA minus P_ENTRADA
+   pub theta: f64
+}
+
+/// Kind of a local extreme of the profile
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExtremeKind {
+   High,
+   Low
+}
+
+/// High or low point of the profile, where the grade changes sign
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProfileExtreme {
+   pub station: f64,
+   pub elevation: f64,
+   pub kind: ExtremeKind
+}
+
+/// Stretch of the profile where the absolute grade is below a
+/// threshold, with poor longitudinal drainage
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FlatZone {
+   pub start_station: f64,
+   pub end_station: f64
+}
+
+impl FlatZone {
+   pub fn length(&self) -> f64 {
+      self.end_station - self.start_station
+   }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProfileRow {
+   pub station: f64,
+   pub elevation: f64,
+   pub grade: f64,
+   /// Some if the row is a high or low point
+   pub extreme: Option<ExtremeKind>,
+   /// True if the row is in a flat zone, ends included
+   pub flat: bool
+}
+
+/// Listing of the profile at regular stations, with the start and
+/// end of every section, the high and low points and the ends of the
+/// flat zones
+#[derive(Debug, Clone)]
+pub struct ProfileListing {
+   pub rows: Vec<ProfileRow>
+}
+
+impl VerticalAlignment {
+   pub fn new() -> Self {
+      VerticalAlignment::with_tolerances(Tolerances::default())
+   }
+   pub fn with_tolerances(tolerances: Tolerances) -> Self {
+      VerticalAlignment{sections: Vec::<Box<dyn VSection>>::new(), tolerances}
+   }
+   /// Builds the alignment from the list of PVIs. The first and the last 
+   /// ones are the start and end points and can't have vertical curve.
+   /// Returns Err with one line for every problem found: vertices too
+   /// close together, overlapping curves or wrong curve data
+   pub fn from_pvis(pvis: &[Pvi]) -> Result<Self, String> {
+      VerticalAlignment::from_pvis_with_tolerances(pvis, Tolerances::default())
+   }
+   pub fn from_pvis_with_tolerances(pvis: &[Pvi], tolerances: Tolerances) -> Result<Self, String> {
+      let lengths = pvi_curve_lengths(pvis, &tolerances)?;
+      let n = pvis.len();
+      let grade = |i: usize| (pvis[i+1].elevation - pvis[i].elevation) 
+         / (pvis[i+1].station - pvis[i].station);
+      let mut va = VerticalAlignment::with_tolerances(tolerances);
+      // Start of the grade not yet added
+      let mut station = pvis[0].station;
+      for i in 1..n {
+         let g = grade(i - 1);
+         let te = pvis[i].station - lengths[i] / 2.0;
+         if !tolerances.is_zero_length(te - station) {
+            let z = pvis[i].elevation + g * (station - pvis[i].station);
+            va.add_vgrade(VGrade::new_with_tolerances(station, z, g, te - station, &tolerances));
+            station = te;
+         }
+         if lengths[i] > 0.0 {
+            let z = pvis[i].elevation + g * (station - pvis[i].station);
+            let end = pvis[i].station + lengths[i] / 2.0;
+            va.add_vparabola(VParabola::new_with_tolerances(station, z, g, grade(i),
+               end - station, &tolerances));
+            station = end;
+         }
+      }
+      Ok(va)
+   }
+   /// Builds the alignment from the rows of an Alzado sheet, ordered by
+   /// station. The first row is the start point and rows with LONGITUD
+   /// zero are vertices without curve. The alignment ends at the TS of
+   /// the last row, or at its PK if it hasn't curve. Returns Err if
+   /// two curves overlap, if a row doesn't follow the grade of the
+   /// previous one, if a curve has equal grades, its vertex out of
+   /// TE-TS or a LONGITUD other than TS - TE, or if the elevations of
+   /// a row don't match the grades and the curve (COTA_TS only with
+   /// parabolas)
+   pub fn from_alzado(rows: &[AlzadoRow], kind: VCurveKind) -> Result<Self, String> {
+      VerticalAlignment::from_alzado_with_tolerances(rows, kind, Tolerances::default())
+   }
+   pub fn from_alzado_with_tolerances(rows: &[AlzadoRow], kind: VCurveKind, 
+      tolerances: Tolerances) -> Result<Self, String> {
+      if rows.len() < 2 {
+         return Err(String::from("At least two rows are needed"));
+      }
+      let tol = tolerances;
+      let check = |column: &str, id: usize, given: f64, computed: f64| {
+         if tol.eq_linear(given, computed) {
+            Ok(())
+         } else {
+            Err(format!("{} of vertex {} is {:.3} but the alignment gives {:.3}", column, id, given, computed))
+         }
+      };
+      let mut va = VerticalAlignment::with_tolerances(tolerances);
+      // End of the last section added: station, elevation and grade
+      let (mut station, mut elevation, mut grade) = (rows[0].pk, rows[0].cota, rows[0].p_salida);
+      for row in &rows[1..] {
+         let has_curve = !tol.is_zero_length(row.longitud);
+         if !tol.eq_angular(row.p_entrada, grade) {
+            return Err(format!("P_ENTRADA of vertex {} isn't the P_SALIDA of the previous one", row.id_tramo));
+         }
+         if has_curve {
+            if tol.eq_angular(row.p_entrada, row.p_salida) {
+               return Err(format!("Vertical curve of vertex {} has equal grades", row.id_tramo));
+            }
+            if row.pk - row.te < tol.linear || row.ts - row.pk < tol.linear {
+               return Err(format!("Vertex {} isn't between TE and TS", row.id_tramo));
+            }
+            if !tol.eq_linear(row.ts - row.te, row.longitud) {
+               return Err(format!("LONGITUD of vertex {} isn't the distance from TE to TS", row.id_tramo));
+            }
+         }
+         let te = if has_curve { row.te } else { row.pk };
+         if te < station - tol.linear {
+            return Err(format!("Vertical curve of vertex {} starts before the end of the previous one", 
+               row.id_tramo));
+         }
+         if !tol.is_zero_length(te - station) {
+            va.add_vgrade(VGrade::new_with_tolerances(station, elevation, grade, te - station, &tol));
+         }
+         if has_curve {
+            check("COTA_TE", row.id_tramo, row.cota_te, elevation + grade * (te - station))?;
+            check("COTA", row.id_tramo, row.cota, row.cota_te + row.p_entrada * (row.pk - te))?;
+            let (l1, l2) = (row.pk - row.te, row.ts - row.pk);
+            match kind {
+               VCurveKind::Circle => va.add_vcircle(VCircle::from_length(te, row.cota_te,
+                  row.p_entrada, row.p_salida, row.longitud)),
+               VCurveKind::Parabola if !tol.eq_linear(l1, l2) => 
+                  va.add_vasymmetricparabola(VAsymmetricParabola::new_with_tolerances(te, 
+                     row.cota_te, row.p_entrada, row.p_salida, l1, l2, &tol)),
+               VCurveKind::Parabola => va.add_vparabola(VParabola::new_with_tolerances(te, 
+                  row.cota_te, row.p_entrada, row.p_salida, row.longitud, &tol))
+            }
+            station = va.end_station();
+            // The sheet gives TS of the parabola, the circle ends some
+            // millimetres away from it
+            if kind == VCurveKind::Parabola {
+               check("COTA_TS", row.id_tramo, row.cota_ts, va.elevation_at(station))?;
+            }
+            elevation = row.cota_ts;
+         } else {
+            check("COTA", row.id_tramo, row.cota, elevation + grade * (row.pk - station))?;
+            station = row.pk;
+            elevation = row.cota;
+         }
+         grade = row.p_salida;
+      }
+      Ok(va)
+   }
+   /// Adds a section at the end of the alignment. Its start station
+   /// must be the end station of the last section
+   pub fn add(&mut self, box_section: Box<dyn VSection>) {
+      if let Some(last) = self.sections.last() {
+         if !self.tolerances.eq_linear(last.end_station(), box_section.start_station()) {
+            panic!("Vertical section doesn't start at the end of the alignment");
+         }
+      }
+      self.sections.push(box_section);
+   }
+   pub fn add_vgrade(&mut self, grade: VGrade) {
+      self.add(Box::new(grade));
+   }
+   pub fn add_vparabola(&mut self, parabola: VParabola) {
+      self.add(Box::new(parabola));
+   }
+   pub fn add_vcircle(&mut self, circle: VCircle) {
+      self.add(Box::new(circle));
+   }
+   pub fn add_vasymmetricparabola(&mut self, parabola: VAsymmetricParabola) {
+      self.add(Box::new(parabola));
+   }
+   pub fn start_station(&self) -> f64 {
+      match self.sections.first() {
+         Some(section) => section.start_station(),
+         None => panic!("Vertical alignment without sections")
+      }
+   }
+   pub fn end_station(&self) -> f64 {
+      match self.sections.last() {
+         Some(section) => section.end_station(),
+         None => panic!("Vertical alignment without sections")
+      }
+   }
+   /// True if the station is inside the alignment, with the linear tolerance
+   pub fn contains_station(&self, station: f64) -> bool {
+      !self.sections.is_empty()
+         && station > self.start_station() - self.tolerances.linear
+         && station < self.end_station() + self.tolerances.linear
+   }
+   /// Index of the section that contains the station and the station
+   /// clamped to the section. In the common point of two sections the
+   /// second one is returned, except at the end of the alignment
+   pub fn locate(&self, station: f64) -> (usize, f64) {
+      if !self.contains_station(station) {
+         panic!("Station out of the vertical alignment");
+      }
+      let last = self.sections.len() - 1;
+      for (i, section) in self.sections.iter().enumerate() {
+         if station < section.end_station() || i == last {
+            return (i, station.clamp(section.start_station(), section.end_station()));
+         }
+      }
+      unreachable!();
+   }
+   pub fn elevation_at(&self, station: f64) -> f64 {
+      let (i, st) = self.locate(station);
+      self.sections[i].elevation_at(st)
+   }
+   pub fn grade_at(&self, station: f64) -> f64 {
+      let (i, st) = self.locate(station);
+      self.sections[i].grade_at(st)
+   }
+   /// Derivative of the grade. Positive in sag curves
+   pub fn vertical_curvature_at(&self, station: f64) -> f64 {
+      let (i, st) = self.locate(station);
+      self.sections[i].vertical_curvature_at(st)
+   }
+   /// Table of vertices with the fields of the Alzado sheet. The first
+   /// and the last rows are the start and end points of the alignment,
+   /// with only the outgoing or incoming grade
+   pub fn alzado(&self) -> Vec<AlzadoRow> {
+      let mut rows = Vec::<AlzadoRow>::new();
+      if self.sections.is_empty() {
+         return rows;
+      }
+      let first = &self.sections[0];
+      rows.push(point_row(1, first.start_station(), first.start_elevation(), 
+         0.0, first.start_grade()));
+      for (i, section) in self.sections.iter().enumerate() {
+         if let Some((pk, cota)) = section.vertex() {
+            // Vertical curve: vertex in the intersection of its tangents
+            let l = section.length();
+            let theta = section.end_grade() - section.start_grade();
+            let mut row = point_row(rows.len() + 1, pk, cota,
+               section.start_grade(), section.end_grade());
+            row.te = section.start_station();
+            row.cota_te = section.start_elevation();
+            row.longitud = l;
+            row.kv = l / theta;
+            row.ts = section.end_station();
+            row.cota_ts = section.end_elevation();
+            row.flecha = section.flecha();
+            rows.push(row);
+         } else if i > 0 && self.sections[i - 1].vertex().is_none() 
+            && !self.tolerances.eq_angular(section.start_grade(), self.sections[i - 1].end_grade()) {
+            // Two grades without vertical curve
+            rows.push(point_row(rows.len() + 1, section.start_station(), 
+               section.start_elevation(), self.sections[i - 1].end_grade(), section.start_grade()));
+         }
+      }
+      let last = &self.sections[self.sections.len() - 1];
+      rows.push(point_row(rows.len() + 1, last.end_station(), last.end_elevation(),
+         last.end_grade(), 0.0));
+      rows
+   }
+}
+
+impl VerticalAlignment {
+   /// High and low points of the profile: stations inside vertical 
+   /// curves where the grade is zero, and vertices without curve where
+   /// the grade changes sign. The ends of the alignment aren't included.
+   /// The grade must be monotonic in every section
+   pub fn high_low_points(&self) -> Vec<ProfileExtreme> {
+      let mut points = Vec::<ProfileExtreme>::new();
+      for (i, section) in self.sections.iter().enumerate() {
+         if i > 0 {
+            let previous = self.sections[i - 1].end_grade();
+            let next = section.start_grade();
+            if previous * next < 0.0 {
+               let kind = if previous > 0.0 { ExtremeKind::High } else { ExtremeKind::Low };
+               let station = section.start_station();
+               points.push(ProfileExtreme{station, elevation: section.start_elevation(), kind});
+            }
+         }
+         if let Some(station) = station_with_grade(section.as_ref(), 0.0) {
+            let kind = if section.start_grade() > 0.0 { ExtremeKind::High } else { ExtremeKind::Low };
+            points.push(ProfileExtreme{station, elevation: section.elevation_at(station), kind});
+         }
+      }
+      points
+   }
+   /// Stretches where the absolute grade is less than threshold and
+   /// whose length is at least min_length
+   pub fn flat_zones(&self, threshold: f64, min_length: f64) -> Vec<FlatZone> {
+      let mut zones = Vec::<FlatZone>::new();
+      for section in &self.sections {
+         let mut stations = vec![section.start_station()];
+         for g in [-threshold, threshold] {
+            if let Some(station) = station_with_grade(section.as_ref(), g) {
+               stations.push(station);
+            }
+         }
+         stations.push(section.end_station());
+         stations.sort_by(|a, b| a.total_cmp(b));
+         for w in stations.windows(2) {
+            let middle = (w[0] + w[1]) / 2.0;
+            if w[1] - w[0] <= 0.0 || section.grade_at(middle).abs() >= threshold {
+               continue;
+            }
+            match zones.last_mut() {
+               Some(last) if self.tolerances.eq_linear(last.end_station, w[0]) => {
+                  last.end_station = w[1];
+               },
+               _ => zones.push(FlatZone{start_station: w[0], end_station: w[1]})
+            }
+         }
+      }
+      zones.retain(|z| z.length() >= min_length);
+      zones
+   }
+   /// Listing of the profile at the stations multiple of interval, the
+   /// ends of the sections, the high and low points and the ends of the
+   /// flat zones of flat_threshold and flat_min_length (see flat_zones)
+   pub fn profile_listing(&self, interval: f64, flat_threshold: f64, 
+      flat_min_length: f64) -> Result<ProfileListing, String> {
+      if interval <= 0.0 || self.tolerances.is_zero_length(interval) {
+         return Err(String::from("Interval must be greater than zero"));
+      }
+      if self.sections.is_empty() {
+         return Err(String::from("Vertical alignment without sections"));
+      }
+      let mut stations: Vec<f64> = self.sections.iter().map(|s| s.start_station()).collect();
+      stations.push(self.end_station());
+      let mut k = (self.start_station() / interval).ceil();
+      while k * interval < self.end_station() {
+         stations.push(k * interval);
+         k += 1.0;
+      }
+      let extremes = self.high_low_points();
+      stations.extend(extremes.iter().map(|e| e.station));
+      let zones = self.flat_zones(flat_threshold, flat_min_length);
+      stations.extend(zones.iter().flat_map(|z| [z.start_station, z.end_station]));
+      stations.sort_by(|a, b| a.total_cmp(b));
+      stations.dedup_by(|a, b| (*a - *b).abs() < self.tolerances.linear);
+      let rows = stations.iter().map(|&station| {
+         let extreme = extremes.iter()
+            .find(|e| (e.station - station).abs() < self.tolerances.linear)
+            .map(|e| e.kind);
+         let flat = zones.iter().any(|z| station > z.start_station - self.tolerances.linear 
+            && station < z.end_station + self.tolerances.linear);
+         ProfileRow{station, elevation: self.elevation_at(station), 
+            grade: self.grade_at(station), extreme, flat}
+      }).collect();
+      Ok(ProfileListing{rows})
+   }
+}
+
+/// Station strictly inside the section where the grade is g, if the
+/// grade of the section crosses that value
+fn station_with_grade(section: &dyn VSection, g: f64) -> Option<f64> {
+   let (g0, g1) = (section.start_grade() - g, section.end_grade() - g);
+   if g0 * g1 >= 0.0 {
+      return None;
+   }
+   let f = |station: f64| (section.grade_at(station) - g, section.vertical_curvature_at(station));
+   solve_newton_bisection(f, section.start_station(), section.end_station(), 1e-9).ok()
+}
+
+impl fmt::Display for ProfileListing {
+   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+      writeln!(f, "{:>12} {:>10} {:>9} {:>5} {:>4}", "PK", "Elevation", "Grade(%)", "Point", "Zone")?;
+      for row in &self.rows {
+         let point = match row.extreme {
+            Some(ExtremeKind::High) => "High",
+            Some(ExtremeKind::Low) => "Low",
+            None => ""
+         };
+         writeln!(f, "{:>12} {:>10.3} {:>9.3} {:>5} {:>4}", format_station(row.station), 
+            row.elevation, row.grade * 100.0, point, if row.flat { "Flat" } else { "" })?;
+      }
+      Ok(())
+   }
+}
+
+/// Lengths of the vertical curves of the PVIs, zero in vertices
+/// without curve. Returns Err with the problems found
+fn pvi_curve_lengths(pvis: &[Pvi], tol: &Tolerances) -> Result<Vec<f64>, String> {
+   if pvis.len() < 2 {
+      return Err(String::from("At least two PVIs are needed"));
+   }
+   let n = pvis.len();
+   let mut errors = Vec::<String>::new();
+   for i in 1..n {
+      let d = pvis[i].station - pvis[i-1].station;
+      if d < tol.linear {
+         errors.push(format!("PVI {} is too close to PVI {}: {:.3} m", i + 1, i, d));
+      }
+   }
+   if pvis[0].curve != PviCurve::None || pvis[n-1].curve != PviCurve::None {
+      errors.push(String::from("First and last PVIs can't have vertical curve"));
+   }
+   if !errors.is_empty() {
+      return Err(errors.join("\n"));
+   }
+   let grade = |i: usize| (pvis[i+1].elevation - pvis[i].elevation) 
+      / (pvis[i+1].station - pvis[i].station);
+   let mut lengths = vec![0.0; n];
+   for i in 1..n-1 {
+      let theta = grade(i) - grade(i - 1);
+      let l = match pvis[i].curve {
+         PviCurve::None => 0.0,
+         PviCurve::Kv(kv) => kv.abs() * theta.abs(),
+         PviCurve::Length(l) => l
+      };
+      if l < 0.0 {
+         errors.push(format!("PVI {} has a negative curve length", i + 1));
+      } else if !tol.is_zero_length(l) && !tol.eq_angular(theta, 0.0) {
+         lengths[i] = l;
+      }
+   }
+   for i in 1..n {
+      let available = pvis[i].station - pvis[i-1].station;
+      let needed = (lengths[i] + lengths[i-1]) / 2.0;
+      if needed > available + tol.linear {
+         let excess = needed - available;
+         errors.push(if lengths[i-1] == 0.0 {
+            format!("Vertical curve of PVI {} starts {:.3} m before PVI {}", i + 1, excess, i)
+         } else if lengths[i] == 0.0 {
+            format!("Vertical curve of PVI {} ends {:.3} m after PVI {}", i, excess, i + 1)
+         } else {
+            format!("Vertical curves of PVI {} and PVI {} overlap by {:.3} m", i, i + 1, excess)
+         });
+      }
+   }
+   if errors.is_empty() {
+      Ok(lengths)
+   } else {
+      Err(errors.join("\n"))
+   }
+}
+
+/// Row of a vertex without vertical curve
+fn point_row(id_tramo: usize, pk: f64, cota: f64, p_entrada: f64, p_salida: f64) -> AlzadoRow {
+   AlzadoRow{id_tramo, pk, te: pk, cota_te: cota, p_entrada, longitud: 0.0, kv: 0.0, 
+      cota, ts: pk, cota_ts: cota, p_salida, flecha: 0.0, theta: p_salida - p_entrada}
+}
+
+impl Default for VerticalAlignment {
+   fn default() -> Self {
+      Self::new()
+   }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+   use super::*;
+   use crate::{eq, eq001, eq01};
+
+   /// First vertices of the Alzado sheet of AlignmentM607.xls,
+   /// from station 0 to 474.14
+   pub(crate) fn sample_alignment() -> VerticalAlignment {
+      let mut va = VerticalAlignment::new();
+      va.add_vgrade(VGrade::new(0.0, 917.6, -0.028084, 0.534));
+      va.add_vparabola(VParabola::new(0.534, 917.585, -0.028084, -0.035645, 52.932));
+      va.add_vgrade(VGrade::new(53.466, 915.898, -0.035645, 57.922));
+      va.add_vparabola(VParabola::new(111.388, 913.834, -0.035645, -0.005, 153.224));
+      va.add_vgrade(VGrade::new(264.612, 910.72, -0.005, 209.528));
+      va
+   }
+
+   #[test]
+   fn test_elevation_at() {
+      let va = sample_alignment();
+      assert!(eq001(va.start_station(), 0.0));
+      assert!(eq001(va.end_station(), 474.14));
+      assert!(eq001(va.elevation_at(53.466), 915.898));
+      assert!(eq001(va.elevation_at(264.612), 910.72));
+      // COTA_TE of the next vertex in the sheet
+      assert!((va.elevation_at(474.14) - 909.672).abs() < 0.002);
+      assert!(eq001(va.grade_at(200.0), -0.035645 + (200.0 - 111.388) / 5000.0));
+      assert!((va.vertical_curvature_at(20.0) + 1.0 / 7000.0).abs() < 1e-6);
+      assert!(eq001(va.vertical_curvature_at(300.0), 0.0));
+   }
+   #[test]
+   #[should_panic]
+   fn test_elevation_at_panic() {
+      let va = sample_alignment();
+      let _z = va.elevation_at(480.0);
+   }
+   #[test]
+   #[should_panic]
+   fn test_add_panic() {
+      let mut va = VerticalAlignment::new();
+      va.add_vgrade(VGrade::new(0.0, 100.0, 0.01, 100.0));
+      va.add_vgrade(VGrade::new(110.0, 101.0, 0.02, 100.0));
+   }
+   #[test]
+   fn test_alzado() {
+      let rows = sample_alignment().alzado();
+      assert_eq!(rows.len(), 4);
+      assert_eq!(rows[0].id_tramo, 1);
+      assert!(eq001(rows[0].p_salida, -0.028084));
+      let r = rows[1];
+      assert!(eq001(r.pk, 27.0));
+      assert!(eq001(r.cota, 916.842));
+      assert!((r.kv + 7000.0).abs() < 1.0);
+      assert!(eq001(r.ts, 53.466));
+      assert!(eq001(r.cota_ts, 915.898));
+      assert!(eq001(r.flecha, -0.100064));
+      assert!(eq001(r.theta, -0.007561));
+      let r = rows[2];
+      assert!(eq001(r.pk, 188.0));
+      assert!(eq001(r.cota, 911.103));
+      assert!(eq001(rows[3].pk, 474.14));
+      assert!(eq001(rows[3].p_entrada, -0.005));
+   }
+   #[test]
+   fn test_from_pvis() {
+      // First vertices of the Alzado sheet of AlignmentM607.xls
+      let pvis = [Pvi::new(0.0, 917.6, PviCurve::None),
+         Pvi::new(27.0, 916.842, PviCurve::Kv(-7000.0)),
+         Pvi::new(188.0, 911.103, PviCurve::Kv(5000.0)),
+         Pvi::new(628.0, 908.903, PviCurve::Length(307.72)),
+         Pvi::new(976.0, 889.315, PviCurve::None)];
+      let va = VerticalAlignment::from_pvis(&pvis).unwrap();
+      assert_eq!(va.sections.len(), 7);
+      assert!(eq001(va.end_station(), 976.0));
+      let rows = va.alzado();
+      assert_eq!(rows.len(), 5);
+      let r = rows[3];
+      assert!(eq01(r.te, 474.14));
+      assert!(eq001(r.cota_te, 909.672));
+      assert!(eq01(r.ts, 781.86));
+      assert!(eq001(r.cota_ts, 900.243));
+      assert!(eq001(r.p_salida, -0.056287));
+      assert!((r.flecha + 3.945483).abs() < 0.002);
+      // The sheet rounds the grades to six decimals, so TE and TS
+      // differ a few millimeters in the longer curves
+      let r = rows[2];
+      assert!(eq01(r.te, 111.388));
+      assert!(eq01(r.ts, 264.612));
+      assert!(eq001(r.cota_ts, 910.72));
+      assert!((r.kv - 5000.0).abs() < 0.1);
+      // Continuous elevation and grade
+      assert!(eq001(va.elevation_at(188.0), 911.103 + r.flecha / 2.0));
+      assert!(eq001(va.grade_at(628.0), (-0.005 - 0.056287) / 2.0));
+   }
+   #[test]
+   fn test_from_pvis_errors() {
+      let pvis = [Pvi::new(0.0, 100.0, PviCurve::None),
+         Pvi::new(100.0, 102.0, PviCurve::Length(150.0)),
+         Pvi::new(200.0, 100.0, PviCurve::Length(80.0)),
+         Pvi::new(200.0, 100.0, PviCurve::None)];
+      let err = VerticalAlignment::from_pvis(&pvis).err().unwrap();
+      assert!(err.contains("PVI 4 is too close to PVI 3"));
+      let pvis = [Pvi::new(0.0, 100.0, PviCurve::None),
+         Pvi::new(100.0, 102.0, PviCurve::Length(250.0)),
+         Pvi::new(200.0, 100.0, PviCurve::Length(80.0)),
+         Pvi::new(300.0, 103.0, PviCurve::None)];
+      let err = VerticalAlignment::from_pvis(&pvis).err().unwrap();
+      assert_eq!(err.lines().count(), 2);
+      assert!(err.contains("Vertical curve of PVI 2 starts 25.000 m before PVI 1"));
+      assert!(err.contains("Vertical curves of PVI 2 and PVI 3 overlap by 65.000 m"));
+      let pvis = [Pvi::new(0.0, 100.0, PviCurve::Kv(1000.0)),
+         Pvi::new(100.0, 102.0, PviCurve::None)];
+      assert!(VerticalAlignment::from_pvis(&pvis).is_err());
+      assert!(VerticalAlignment::from_pvis(&pvis[..1]).is_err());
+   }
+   #[test]
+   fn test_from_pvis_tangent_curves() {
+      // Curves touching in TS = TE, without grade between them
+      let pvis = [Pvi::new(0.0, 100.0, PviCurve::None),
+         Pvi::new(100.0, 102.0, PviCurve::Length(100.0)),
+         Pvi::new(200.0, 100.0, PviCurve::Length(100.0)),
+         Pvi::new(300.0, 103.0, PviCurve::None)];
+      let va = VerticalAlignment::from_pvis(&pvis).unwrap();
+      assert_eq!(va.sections.len(), 4);
+      assert!(eq001(va.grade_at(150.0), -0.02));
+   }
+   #[test]
+   fn test_high_low_points() {
+      // Crest with high point inside, sag with low point inside and
+      // vertex without curve
+      let pvis = [Pvi::new(0.0, 100.0, PviCurve::None),
+         Pvi::new(200.0, 104.0, PviCurve::Length(100.0)),
+         Pvi::new(400.0, 98.0, PviCurve::Length(100.0)),
+         Pvi::new(600.0, 100.0, PviCurve::None),
+         Pvi::new(700.0, 99.0, PviCurve::None)];
+      let va = VerticalAlignment::from_pvis(&pvis).unwrap();
+      let points = va.high_low_points();
+      assert_eq!(points.len(), 3);
+      // Kv = 100 / -0.05, zero grade at u = 0.02 * 2000
+      assert_eq!(points[0].kind, ExtremeKind::High);
+      assert!(eq001(points[0].station, 190.0));
+      assert!(eq001(points[0].elevation, 103.0 + 0.02 * 40.0 - 40.0 * 40.0 / 4000.0));
+      assert_eq!(points[1].kind, ExtremeKind::Low);
+      assert!(eq001(va.grade_at(points[1].station), 0.0));
+      assert_eq!(points[2].kind, ExtremeKind::High);
+      assert!(eq001(points[2].station, 600.0));
+      assert!(eq001(points[2].elevation, 100.0));
+      // No extremes in the sample alignment, always downwards
+      assert!(sample_alignment().high_low_points().is_empty());
+   }
+   #[test]
+   fn test_flat_zones() {
+      let pvis = [Pvi::new(0.0, 100.0, PviCurve::None),
+         Pvi::new(200.0, 104.0, PviCurve::Length(100.0)),
+         Pvi::new(400.0, 98.0, PviCurve::None),
+         Pvi::new(500.0, 98.2, PviCurve::None)];
+      let va = VerticalAlignment::from_pvis(&pvis).unwrap();
+      // Crest: grade from 0.02 to -0.03 in 100 m, |grade| < 0.005 in 20 m
+      let zones = va.flat_zones(0.005, 10.0);
+      assert_eq!(zones.len(), 2);
+      assert!(eq001(zones[0].start_station, 180.0));
+      assert!(eq001(zones[0].length(), 20.0));
+      // Last grade 0.2% is flat in its whole length
+      assert!(eq001(zones[1].start_station, 400.0));
+      assert!(eq001(zones[1].end_station, 500.0));
+      assert_eq!(va.flat_zones(0.005, 50.0).len(), 1);
+   }
+   #[test]
+   fn test_flat_zones_merged() {
+      // Flat stretch through two sections
+      let mut va = VerticalAlignment::new();
+      va.add_vgrade(VGrade::new(0.0, 100.0, 0.001, 50.0));
+      va.add_vgrade(VGrade::new(50.0, 100.05, -0.002, 50.0));
+      let zones = va.flat_zones(0.005, 80.0);
+      assert_eq!(zones.len(), 1);
+      assert!(eq001(zones[0].length(), 100.0));
+   }
+   #[test]
+   fn test_profile_listing() {
+      let pvis = [Pvi::new(0.0, 100.0, PviCurve::None),
+         Pvi::new(200.0, 104.0, PviCurve::Length(100.0)),
+         Pvi::new(400.0, 98.0, PviCurve::None)];
+      let va = VerticalAlignment::from_pvis(&pvis).unwrap();
+      let listing = va.profile_listing(50.0, 0.0, 0.0).unwrap();
+      // 0, 50, 100, 150, 190 (high), 200, 250, 300, 350, 400
+      assert_eq!(listing.rows.len(), 10);
+      let high = listing.rows[4];
+      assert_eq!(high.extreme, Some(ExtremeKind::High));
+      assert!(eq001(high.grade, 0.0));
+      assert!(eq001(listing.rows[1].elevation, 101.0));
+      assert!(listing.rows.iter().all(|r| !r.flat));
+      let text = format!("{}", listing);
+      assert_eq!(text.lines().count(), 11);
+      assert!(text.contains("High"));
+      assert!(!text.contains("Flat"));
+      // Grades 2% and -3% with a curve of 100 m from 150 to 250: the
+      // grade is under 0.5% from 180 to 200
+      let listing = va.profile_listing(50.0, 0.005, 10.0).unwrap();
+      assert_eq!(listing.rows.len(), 11);
+      let flat: Vec<f64> = listing.rows.iter().filter(|r| r.flat).map(|r| r.station).collect();
+      assert_eq!(flat.len(), 3);
+      assert!(eq001(flat[0], 180.0) && eq001(flat[2], 200.0));
+      assert!(format!("{}", listing).contains("Flat"));
+      assert!(va.profile_listing(0.0, 0.0, 0.0).is_err());
+   }
+   /// First rows of the Alzado sheet of AlignmentM607.xls
+   fn sheet_rows() -> Vec<AlzadoRow> {
+      let data = [
+         (1, 0.0, 0.0, 917.6, 0.0, 0.0, 0.0, 917.6, 0.0, 917.6, -0.028084, 0.0, 0.0),
+         (2, 27.0, 0.534, 917.585, -0.028084, 52.932, -7000.0, 916.842, 53.466, 915.898, 
+            -0.035645, -0.100064, -0.007561),
+         (3, 188.0, 111.388, 913.834, -0.035645, 153.224, 5000.0, 911.103, 264.612, 910.72, 
+            -0.005, 1.17388, 0.030645),
+         (4, 628.0, 474.14, 909.672, -0.005, 307.72, -6000.0, 908.903, 781.86, 900.243, 
+            -0.056287, -3.945483, -0.051287)];
+      data.iter().map(|d| AlzadoRow{id_tramo: d.0, pk: d.1, te: d.2, cota_te: d.3, p_entrada: d.4,
+         longitud: d.5, kv: d.6, cota: d.7, ts: d.8, cota_ts: d.9, p_salida: d.10, 
+         flecha: d.11, theta: d.12}).collect()
+   }
+   #[test]
+   fn test_from_alzado() {
+      let rows = sheet_rows();
+      let va = VerticalAlignment::from_alzado(&rows, VCurveKind::Parabola).unwrap();
+      assert_eq!(va.sections.len(), 6);
+      assert!(eq001(va.end_station(), 781.86));
+      assert!(eq001(va.elevation_at(111.388), 913.834));
+      // The exported table gives back the sheet
+      let exported = va.alzado();
+      for (a, b) in exported[1..4].iter().zip(rows[1..4].iter()) {
+         assert!(eq001(a.pk, b.pk));
+         assert!(eq001(a.cota, b.cota));
+         assert!(eq001(a.cota_ts, b.cota_ts));
+         assert!(eq001(a.flecha, b.flecha));
+      }
+   }
+   #[test]
+   fn test_from_alzado_circle_and_asymmetric() {
+      let mut rows = sheet_rows();
+      let va = VerticalAlignment::from_alzado(&rows, VCurveKind::Circle).unwrap();
+      assert!(eq001(va.end_station(), 781.86));
+      // Circles of large radius are very close to the parabolas
+      assert!(eq01(va.elevation_at(188.0), 911.103 + 1.17388 / 2.0));
+      // Vertex displaced 20 m: asymmetric parabola
+      rows[2].pk = 168.0;
+      rows[2].cota = 913.834 - 0.035645 * (168.0 - 111.388);
+      // TS stays on the outgoing grade, which rises with the vertex
+      let rise = rows[2].cota - 0.005 * (264.612 - 168.0) - rows[2].cota_ts;
+      rows[2].cota_ts += rise;
+      rows[3].cota_te += rise;
+      rows[3].cota += rise;
+      rows[3].cota_ts += rise;
+      let va = VerticalAlignment::from_alzado(&rows, VCurveKind::Parabola).unwrap();
+      let exported = va.alzado();
+      assert!(eq001(exported[2].pk, 168.0));
+      assert!(eq001(exported[2].cota, rows[2].cota));
+      assert!(eq(va.grade_at(264.612), -0.005));
+   }
+   #[test]
+   fn test_from_alzado_overlap() {
+      let mut rows = sheet_rows();
+      rows[2].te = 50.0;
+      assert!(VerticalAlignment::from_alzado(&rows, VCurveKind::Parabola).is_err());
+      assert!(VerticalAlignment::from_alzado(&rows[..1], VCurveKind::Parabola).is_err());
+   }
+   #[test]
+   fn test_from_alzado_inconsistent() {
+      let sheet = sheet_rows();
+      let mut rows = sheet.clone();
+      rows[2].cota_te += 0.05;
+      assert!(VerticalAlignment::from_alzado(&rows, VCurveKind::Parabola).err().unwrap().contains("COTA_TE"));
+      let mut rows = sheet.clone();
+      rows[3].cota += 0.05;
+      assert!(VerticalAlignment::from_alzado(&rows, VCurveKind::Parabola).err().unwrap().contains("COTA of vertex 4"));
+      let mut rows = sheet;
+      rows[1].cota_ts -= 0.05;
+      assert!(VerticalAlignment::from_alzado(&rows, VCurveKind::Parabola).err().unwrap().contains("COTA_TS"));
+   }
+   #[test]
+   fn test_from_alzado_bad_rows() {
+      let sheet = sheet_rows();
+      let error = |rows: &[AlzadoRow]| VerticalAlignment::from_alzado(rows, VCurveKind::Parabola).err().unwrap();
+      // Curve with equal grades
+      let mut rows = sheet.clone();
+      rows[3].p_salida = rows[3].p_entrada;
+      assert!(error(&rows).contains("equal grades"));
+      // TE after the vertex
+      let mut rows = sheet.clone();
+      rows[2].te = 190.0;
+      assert!(error(&rows).contains("between TE and TS"));
+      // Vertex after TS
+      let mut rows = sheet.clone();
+      rows[2].ts = 180.0;
+      assert!(error(&rows).contains("between TE and TS"));
+      // LONGITUD isn't TS - TE
+      let mut rows = sheet.clone();
+      rows[2].longitud = 150.0;
+      assert!(error(&rows).contains("LONGITUD"));
+      // P_ENTRADA other than the previous P_SALIDA
+      let mut rows = sheet;
+      rows[3].p_entrada = -0.006;
+      assert!(error(&rows).contains("P_ENTRADA of vertex 4"));
+   }
+   #[test]
+   fn test_alzado_without_curve() {
+      let mut va = VerticalAlignment::new();
+      va.add_vgrade(VGrade::new(0.0, 100.0, 0.01, 100.0));
+      va.add_vgrade(VGrade::new(100.0, 101.0, -0.02, 100.0));
+      let rows = va.alzado();
+      assert_eq!(rows.len(), 3);
+      assert!(eq001(rows[1].pk, 100.0));
+      assert!(eq001(rows[1].longitud, 0.0));
+      assert!(eq001(rows[1].theta, -0.03));
+   }
+}
@@ -0,0 +1,83 @@
+/// VGrade is a vertical section with uniform grade (rasante)
+use super::vsection::VSection;
+use crate::tolerances::Tolerances;
+
+pub struct VGrade {
+   pub start_station: f64,
+   pub start_elevation: f64,
+   /// Grade as a tangent, 0.02 for 2%
+   pub grade: f64,
+   pub length: f64
+}
+
+impl VGrade {
+   pub fn new(start_station: f64, start_elevation: f64, grade: f64, length: f64) -> Self {
+      VGrade::new_with_tolerances(start_station, start_elevation, grade, length, 
+         &Tolerances::default())
+   }
+   /// Creates a new VGrade. The length must be greater than the
+   /// linear tolerance
+   pub fn new_with_tolerances(start_station: f64, start_elevation: f64, grade: f64, 
+      length: f64, tol: &Tolerances) -> Self {
+      if length < 0.0 || tol.is_zero_length(length) {
+         panic!("Length must be greater than zero");
+      }
+      VGrade{start_station, start_elevation, grade, length}
+   }
+}
+
+impl VSection for VGrade {
+   fn start_station(&self) -> f64 {
+      self.start_station
+   }
+   fn length(&self) -> f64 {
+      self.length
+   }
+   fn start_elevation(&self) -> f64 {
+      self.start_elevation
+   }
+   fn start_grade(&self) -> f64 {
+      self.grade
+   }
+   fn end_grade(&self) -> f64 {
+      self.grade
+   }
+   fn elevation_at(&self, station: f64) -> f64 {
+      self.start_elevation + self.grade * self.check_station(station)
+   }
+   fn grade_at(&self, station: f64) -> f64 {
+      self.check_station(station);
+      self.grade
+   }
+   fn vertical_curvature_at(&self, station: f64) -> f64 {
+      self.check_station(station);
+      0.0
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use crate::eq001;
+
+   #[test]
+   fn test_new() {
+      let g = VGrade::new(100.0, 500.0, -0.03, 200.0);
+      assert!(eq001(g.end_station(), 300.0));
+      assert!(eq001(g.end_elevation(), 494.0));
+      assert!(eq001(g.elevation_at(150.0), 498.5));
+      assert!(eq001(g.grade_at(150.0), -0.03));
+      assert!(eq001(g.vertical_curvature_at(150.0), 0.0));
+   }
+   #[test]
+   #[should_panic]
+   fn test_new_panic() {
+      let _g = VGrade::new(100.0, 500.0, -0.03, 0.0);
+   }
+   #[test]
+   #[should_panic]
+   fn test_elevation_at_panic() {
+      let g = VGrade::new(100.0, 500.0, -0.03, 200.0);
+      let _z = g.elevation_at(99.0);
+   }
+}
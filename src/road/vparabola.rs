@@ -0,0 +1,135 @@
+/// VParabola is a parabolic vertical curve (acuerdo vertical).
+/// The grade changes linearly with the station, from the start
+/// grade in TE to the end grade in TS:
+///
+/// z = z_te + p_in*u + u^2 / (2 Kv)
+///
+/// where u is the distance from TE and Kv = L / (p_out - p_in) the
+/// parameter of the curve, negative in crest curves and positive in
+/// sag curves. The vertex (PIV) is in the middle station of the curve,
+/// the external is theta*L/8 and the FLECHA theta*L/4
+use super::vsection::VSection;
+use crate::tolerances::Tolerances;
+
+pub struct VParabola {
+   /// Station of the start point TE
+   pub start_station: f64,
+   pub start_elevation: f64,
+   /// Grade in TE
+   pub start_grade: f64,
+   /// Grade in TS
+   pub end_grade: f64,
+   pub length: f64
+}
+
+impl VParabola {
+   pub fn new(start_station: f64, start_elevation: f64, start_grade: f64, 
+      end_grade: f64, length: f64) -> Self {
+      VParabola::new_with_tolerances(start_station, start_elevation, start_grade, 
+         end_grade, length, &Tolerances::default())
+   }
+   /// Creates a new VParabola. The length must be greater than the linear 
+   /// tolerance and the grades can't be equal within the angular tolerance
+   pub fn new_with_tolerances(start_station: f64, start_elevation: f64, start_grade: f64,
+      end_grade: f64, length: f64, tol: &Tolerances) -> Self {
+      if length < 0.0 || tol.is_zero_length(length) {
+         panic!("Length must be greater than zero");
+      }
+      if tol.eq_angular(start_grade, end_grade) {
+         panic!("Vertical curve can't have equal start and end grades");
+      }
+      VParabola{start_station, start_elevation, start_grade, end_grade, length}
+   }
+   /// Change of grade, end grade minus start grade
+   pub fn theta(&self) -> f64 {
+      self.end_grade - self.start_grade
+   }
+   /// Parameter Kv of the parabola
+   pub fn kv(&self) -> f64 {
+      self.length / self.theta()
+   }
+   /// True in crest (convex) curves
+   pub fn is_crest(&self) -> bool {
+      self.theta() < 0.0
+   }
+   /// Station of the vertex (PIV), intersection of the two grades
+   pub fn vertex_station(&self) -> f64 {
+      self.start_station + self.length / 2.0
+   }
+   /// Elevation of the vertex in the prolongation of the start grade
+   pub fn vertex_elevation(&self) -> f64 {
+      self.start_elevation + self.start_grade * self.length / 2.0
+   }
+}
+
+impl VSection for VParabola {
+   fn start_station(&self) -> f64 {
+      self.start_station
+   }
+   fn length(&self) -> f64 {
+      self.length
+   }
+   fn start_elevation(&self) -> f64 {
+      self.start_elevation
+   }
+   fn start_grade(&self) -> f64 {
+      self.start_grade
+   }
+   fn end_grade(&self) -> f64 {
+      self.end_grade
+   }
+   fn elevation_at(&self, station: f64) -> f64 {
+      let u = self.check_station(station);
+      self.start_elevation + self.start_grade * u + u * u / 2.0 / self.kv()
+   }
+   fn grade_at(&self, station: f64) -> f64 {
+      let u = self.check_station(station);
+      self.start_grade + u / self.kv()
+   }
+   fn vertical_curvature_at(&self, station: f64) -> f64 {
+      self.check_station(station);
+      1.0 / self.kv()
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use crate::{eq, eq001};
+
+   #[test]
+   fn test_sheet_values() {
+      // Vertex 3 of the Alzado sheet of AlignmentM607.xls
+      let vp = VParabola::new(111.388, 913.834, -0.035645, -0.005, 153.224);
+      assert!(eq001(vp.theta(), 0.030645));
+      assert!((vp.kv() - 5000.0).abs() < 0.1);
+      assert!(eq001(vp.vertex_station(), 188.0));
+      assert!(eq001(vp.vertex_elevation(), 911.103));
+      assert!(eq001(vp.end_station(), 264.612));
+      assert!(eq001(vp.end_elevation(), 910.72));
+      assert!(eq001(vp.flecha(), 1.17388));
+      assert!(!vp.is_crest());
+   }
+   #[test]
+   fn test_elevation_and_grade() {
+      let vp = VParabola::new(0.0, 100.0, 0.02, -0.02, 200.0);
+      assert!(vp.is_crest());
+      assert!(eq(vp.kv(), -5000.0));
+      assert!(eq001(vp.elevation_at(100.0), vp.vertex_elevation() + vp.external()));
+      assert!(eq(vp.grade_at(100.0), 0.0));
+      assert!(eq(vp.grade_at(200.0), -0.02));
+      assert!(eq001(vp.end_elevation(), 100.0));
+      assert!(eq(vp.vertical_curvature_at(50.0), -1.0 / 5000.0));
+   }
+   #[test]
+   #[should_panic]
+   fn test_new_panic_grades() {
+      let _vp = VParabola::new(0.0, 100.0, 0.02, 0.02, 200.0);
+   }
+   #[test]
+   #[should_panic]
+   fn test_grade_at_panic() {
+      let vp = VParabola::new(0.0, 100.0, 0.02, -0.02, 200.0);
+      let _g = vp.grade_at(201.0);
+   }
+}
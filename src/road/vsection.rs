@@ -0,0 +1,71 @@
+/// Trait for vertical alignment sections: uniform grades and
+/// vertical curves
+///
+/// Vertical sections are given by the stations (PK) of the axis, 
+/// not by the arc length from their start point as HSection,
+/// because the profile is always defined and staked out by station.
+///
+/// The criteria used is:
+///
+/// **Grade:** tangent of the slope angle (0.02 is 2%). Positive 
+/// upwards in the direction of increasing stations\
+/// **Vertical curvature:** derivative of the grade with the station.
+/// Positive in sag curves (cóncavos), negative in crest curves (convexos)
+pub trait VSection {
+   fn start_station(&self) -> f64;
+   fn length(&self) -> f64;
+   fn end_station(&self) -> f64 {
+      self.start_station() + self.length()
+   }
+   fn start_elevation(&self) -> f64;
+   fn end_elevation(&self) -> f64 {
+      self.elevation_at(self.end_station())
+   }
+   fn start_grade(&self) -> f64;
+   fn end_grade(&self) -> f64;
+   fn elevation_at(&self, station: f64) -> f64;
+   fn grade_at(&self, station: f64) -> f64;
+   fn vertical_curvature_at(&self, station: f64) -> f64;
+   /// Station and elevation of the vertex, intersection of the start
+   /// and end tangents. None if both grades are equal
+   fn vertex(&self) -> Option<(f64, f64)> {
+      let (p0, p1) = (self.start_grade(), self.end_grade());
+      if p0 == p1 {
+         return None;
+      }
+      let (s0, z0) = (self.start_station(), self.start_elevation());
+      let (s1, z1) = (self.end_station(), self.end_elevation());
+      let station = (z1 - z0 + p0 * s0 - p1 * s1) / (p0 - p1);
+      Some((station, z0 + p0 * (station - s0)))
+   }
+   /// Vertical distance from the vertex to the curve. Positive in sag
+   /// curves, zero in grades
+   fn external(&self) -> f64 {
+      match self.vertex() {
+         Some((station, z)) => self.elevation_at(station) - z,
+         None => 0.0
+      }
+   }
+   /// FLECHA of the Alzado sheets: vertical distance from the vertex to
+   /// the chord TE-TS at the station of the vertex. Positive in sag
+   /// curves, zero in grades
+   fn flecha(&self) -> f64 {
+      match self.vertex() {
+         Some((station, z)) => {
+            let (s0, z0) = (self.start_station(), self.start_elevation());
+            let chord = (self.end_elevation() - z0) / self.length();
+            z0 + chord * (station - s0) - z
+         },
+         None => 0.0
+      }
+   }
+   /// Distance from the start of the section to the station.
+   /// Panics if the station isn't in the section
+   fn check_station(&self, station: f64) -> f64 {
+      let u = station - self.start_station();
+      if u < 0.0 || u > self.length() {
+         panic!("Station out of the vertical section");
+      }
+      u
+   }
+}
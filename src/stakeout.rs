@@ -0,0 +1,43 @@
+//! Replanteo (stakeout) del eje en campo
+
+pub mod deflection;
+pub mod polar;
+
+/// Unit of the angles in the stakeout tables
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AngleUnit {
+   /// Centesimal degrees, 400 in a full circle
+   Gon,
+   /// Sexagesimal degrees, 360 in a full circle
+   Degrees
+}
+
+impl AngleUnit {
+   /// Converts an angle in sexagesimal degrees to the unit
+   pub fn convert_degrees(&self, value: f64) -> f64 {
+      match self {
+         AngleUnit::Gon => crate::deg2gon(value),
+         AngleUnit::Degrees => value
+      }
+   }
+   /// Short name of the unit used in the headers of the tables
+   pub fn symbol(&self) -> &'static str {
+      match self {
+         AngleUnit::Gon => "gon",
+         AngleUnit::Degrees => "deg"
+      }
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use crate::eq;
+
+   #[test]
+   fn test_convert_degrees() {
+      assert!(eq(AngleUnit::Gon.convert_degrees(90.0), 100.0));
+      assert!(eq(AngleUnit::Degrees.convert_degrees(90.0), 90.0));
+      assert_eq!(AngleUnit::Gon.symbol(), "gon");
+   }
+}
@@ -0,0 +1,159 @@
+//! Tablas de replanteo por ángulos de deflexión (método de 
+//! las cuerdas) para curvas circulares y clotoides.
+//!
+//! The instrument is placed at the start point of the curve and 
+//! oriented along its start tangent. Each row gives the deflection
+//! angle from the tangent to the point, positive in rightward curves,
+//! the chord from the start point and the sub-chord from the previous
+//! point, to be measured with the tape
+use std::fmt;
+use crate::format_station;
+use crate::road::roadaxis::RoadAxis;
+use super::AngleUnit;
+
+#[derive(Debug, Clone, Copy)]
+pub struct DeflectionRow {
+   pub station: f64,
+   /// Arc length from the start of the curve
+   pub arc: f64,
+   /// Deflection angle from the start tangent, in the unit of the table
+   pub deflection: f64,
+   /// Chord from the start point of the curve
+   pub chord: f64,
+   /// Chord from the previous point. Zero in the first row
+   pub sub_chord: f64
+}
+
+#[derive(Debug, Clone)]
+pub struct DeflectionTable {
+   /// Index of the curve in the sections of the axis
+   pub section_index: usize,
+   pub unit: AngleUnit,
+   pub rows: Vec<DeflectionRow>
+}
+
+/// Deflection table of the section with index section_index of 
+/// the axis. Rows are generated at the start and end of the curve
+/// and at the stations multiple of interval inside it.
+/// Returns Err if the section doesn't exist, is a tangent or 
+/// the interval isn't positive
+pub fn deflection_table(axis: &RoadAxis, section_index: usize, interval: f64,
+   unit: AngleUnit) -> Result<DeflectionTable, String> {
+   if section_index >= axis.sections.len() {
+      return Err(format!("Section {} doesn't exist in the axis", section_index));
+   }
+   if interval <= 0.0 || axis.tolerances.is_zero_length(interval) {
+      return Err(String::from("Interval must be greater than zero"));
+   }
+   let tol = &axis.tolerances;
+   let section = &axis.sections[section_index];
+   if tol.is_zero_radius(section.start_radius()) && tol.is_zero_radius(section.end_radius()) {
+      return Err(format!("Section {} is a tangent, not a curve", section_index));
+   }
+   let start_station = axis.section_start_station(section_index);
+   let length = section.length();
+   let mut arcs = vec![0.0];
+   let mut k = (start_station / interval).floor() + 1.0;
+   while k * interval - start_station < length - tol.linear {
+      let arc = k * interval - start_station;
+      if arc > tol.linear {
+         arcs.push(arc);
+      }
+      k += 1.0;
+   }
+   arcs.push(length);
+
+   let start_point = section.start_point();
+   let start_azimuth = section.start_azimuth();
+   let mut rows = Vec::<DeflectionRow>::new();
+   let mut previous = start_point;
+   for arc in arcs {
+      let p = section.point_at_s(arc);
+      let chord = start_point.dist_to(p);
+      let deflection = if tol.is_zero_length(chord) {
+         0.0
+      } else {
+         let mut d = start_point.azimuth_to(p) - start_azimuth;
+         if d > 180.0 {
+            d -= 360.0;
+         } else if d <= -180.0 {
+            d += 360.0;
+         }
+         d
+      };
+      rows.push(DeflectionRow{station: start_station + arc, arc, 
+         deflection: unit.convert_degrees(deflection), chord, sub_chord: previous.dist_to(p)});
+      previous = p;
+   }
+   Ok(DeflectionTable{section_index, unit, rows})
+}
+
+impl fmt::Display for DeflectionTable {
+   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+      writeln!(f, "{:>12} {:>10} {:>12} {:>10} {:>10}", "PK", "Arc", 
+         format!("Defl.({})", self.unit.symbol()), "Chord", "Sub-chord")?;
+      for row in &self.rows {
+         writeln!(f, "{:>12} {:>10.3} {:>12.4} {:>10.3} {:>10.3}", format_station(row.station),
+            row.arc, row.deflection, row.chord, row.sub_chord)?;
+      }
+      Ok(())
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use crate::{eq001, rad2deg, deg2gon};
+   use crate::road::roadaxis::tests::sample_axis;
+
+   #[test]
+   fn test_deflection_table_circle() {
+      // Circle of the sample axis: R=200, from station 260 to 360
+      let axis = sample_axis();
+      let table = deflection_table(&axis, 2, 20.0, AngleUnit::Gon).unwrap();
+      assert_eq!(table.rows.len(), 6);
+      assert!(eq001(table.rows[1].station, 280.0));
+      assert!(eq001(table.rows[5].arc, 100.0));
+      for row in &table.rows {
+         // Deflection of a circle is half the central angle
+         assert!(eq001(row.deflection, deg2gon(rad2deg(row.arc / 2.0 / 200.0))));
+         assert!(eq001(row.chord, 2.0 * 200.0 * (row.arc / 2.0 / 200.0).sin()));
+      }
+      assert!(eq001(table.rows[2].sub_chord, 2.0 * 200.0 * (20.0_f64 / 400.0).sin()));
+      assert!(eq001(table.rows[0].sub_chord, 0.0));
+   }
+   #[test]
+   fn test_deflection_table_clothoid() {
+      // Clothoid from station 200 to 260: deflection close to 
+      // a third of the tangent angle
+      let axis = sample_axis();
+      let table = deflection_table(&axis, 1, 25.0, AngleUnit::Degrees).unwrap();
+      let stations: Vec<f64> = table.rows.iter().map(|r| r.station).collect();
+      assert_eq!(stations, vec![200.0, 225.0, 250.0, 260.0]);
+      let last = table.rows.last().unwrap();
+      let tau = 60.0 / 2.0 / 200.0;
+      assert!((last.deflection - rad2deg(tau / 3.0)).abs() < 0.01);
+      assert!(last.deflection > 0.0);
+      // Exit clothoid begins with the full deflection of the circle
+      let table = deflection_table(&axis, 3, 25.0, AngleUnit::Degrees).unwrap();
+      assert!((table.rows.last().unwrap().deflection - rad2deg(2.0 * tau / 3.0)).abs() < 0.01);
+   }
+   #[test]
+   fn test_deflection_table_errors() {
+      let axis = sample_axis();
+      assert!(deflection_table(&axis, 0, 20.0, AngleUnit::Gon).is_err());
+      assert!(deflection_table(&axis, 7, 20.0, AngleUnit::Gon).is_err());
+      assert!(deflection_table(&axis, 2, 0.0, AngleUnit::Gon).is_err());
+   }
+   #[test]
+   fn test_display() {
+      let axis = sample_axis();
+      let table = deflection_table(&axis, 2, 50.0, AngleUnit::Gon).unwrap();
+      let text = format!("{}", table);
+      let lines: Vec<&str> = text.lines().collect();
+      assert_eq!(lines.len(), 5);
+      assert!(lines[0].contains("Defl.(gon)"));
+      assert!(lines[1].contains("0+260.000"));
+      assert!(lines[4].contains("0+360.000"));
+   }
+}
@@ -0,0 +1,209 @@
+//! Replanteo por coordenadas polares desde una estación 
+//! arbitraria.
+//!
+//! The instrument is placed at a station point and oriented to a
+//! backsight point, whose bearing is zero. Bearings are measured 
+//! clockwise from the backsight in gon, as in the usual total 
+//! stations. If a vertical profile is given, the height difference
+//! from the instrument to every point is computed too. Points out of
+//! the axis take the cross slope of their side from the superelevation,
+//! and without it they haven't height difference
+use std::fmt;
+use crate::{deg2gon, format_station};
+use crate::geom::point::Point;
+use crate::road::roadaxis::RoadAxis;
+use crate::road::verticalalignment::VerticalAlignment;
+use crate::road::superelevation::Superelevation;
+use crate::tolerances::Tolerances;
+
+#[derive(Debug, Clone, Copy)]
+pub struct PolarStakeout {
+   /// Point where the instrument is placed
+   pub station: Point,
+   /// Point used to orientate the instrument
+   pub backsight: Point,
+   /// Elevation of the horizontal axis of the instrument, needed
+   /// for the height differences
+   pub instrument_elevation: Option<f64>
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PolarRow {
+   /// Station (PK) of the point in the axis
+   pub pk: f64,
+   /// Offset from the axis, positive to the right
+   pub offset: f64,
+   pub point: Point,
+   /// Bearing in gon from the backsight, between 0 and 400
+   pub bearing: f64,
+   /// Horizontal distance from the instrument
+   pub distance: f64,
+   /// Elevation of the point minus elevation of the instrument
+   pub height_difference: Option<f64>
+}
+
+#[derive(Debug, Clone)]
+pub struct PolarTable {
+   pub rows: Vec<PolarRow>
+}
+
+impl PolarStakeout {
+   pub fn new(station: Point, backsight: Point, instrument_elevation: Option<f64>) -> Self {
+      PolarStakeout::new_with_tolerances(station, backsight, instrument_elevation, &Tolerances::default())
+   }
+   /// Creates a new PolarStakeout. Station and backsight can't be
+   /// closer than the linear tolerance
+   pub fn new_with_tolerances(station: Point, backsight: Point, instrument_elevation: Option<f64>,
+      tol: &Tolerances) -> Self {
+      if tol.is_zero_length(station.dist_to(backsight)) {
+         panic!("Station and backsight can't be the same point");
+      }
+      PolarStakeout{station, backsight, instrument_elevation}
+   }
+   /// Bearing in gon, between 0 and 400, and horizontal distance 
+   /// from the instrument to the point
+   pub fn bearing_and_distance(&self, point: Point) -> (f64, f64) {
+      let az = self.station.azimuth_to(point) - self.station.azimuth_to(self.backsight);
+      let mut bearing = deg2gon(crate::normalize_360(az));
+      if bearing >= 400.0 {
+         bearing -= 400.0;
+      }
+      (bearing, self.station.dist_to(point))
+   }
+   /// Stakeout data of the point of the axis at pk with the offset.
+   /// The profile gives the elevation of the axis, and out of it there
+   /// isn't height difference. The elevation of a point out of the axis
+   /// follows the cross slope of its side, so it needs the superelevation
+   pub fn stake_point(&self, axis: &RoadAxis, pk: f64, offset: f64, 
+      profile: Option<&VerticalAlignment>, superelevation: Option<&Superelevation>) -> PolarRow {
+      let point = axis.offset_point_at_station(pk, offset);
+      let (bearing, distance) = self.bearing_and_distance(point);
+      let height_difference = match (profile, self.instrument_elevation) {
+         (Some(p), Some(zi)) if p.contains_station(pk) => 
+            offset_height(pk, offset, superelevation).map(|dz| p.elevation_at(pk) + dz - zi),
+         _ => None
+      };
+      PolarRow{pk, offset, point, bearing, distance, height_difference}
+   }
+   /// Stakeout table of the axis at the stations multiple of 
+   /// interval and at the tangent points, with one row for each
+   /// offset in every station (use 0.0 for the axis itself)
+   pub fn table(&self, axis: &RoadAxis, interval: f64, offsets: &[f64], 
+      profile: Option<&VerticalAlignment>, superelevation: Option<&Superelevation>) 
+      -> Result<PolarTable, String> {
+      if offsets.is_empty() {
+         return Err(String::from("At least one offset is needed"));
+      }
+      let mut rows = Vec::<PolarRow>::new();
+      for pk in axis.stations_at_interval(interval)? {
+         for offset in offsets {
+            rows.push(self.stake_point(axis, pk, *offset, profile, superelevation));
+         }
+      }
+      Ok(PolarTable{rows})
+   }
+}
+
+/// Height of the point at the offset over the axis, with the cross
+/// slope of its side. None out of the axis without superelevation
+fn offset_height(pk: f64, offset: f64, superelevation: Option<&Superelevation>) -> Option<f64> {
+   if offset == 0.0 {
+      return Some(0.0);
+   }
+   let (left, right) = superelevation?.cross_slopes_at(pk);
+   Some(offset.abs() * if offset < 0.0 { left } else { right })
+}
+
+impl fmt::Display for PolarTable {
+   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+      writeln!(f, "{:>12} {:>8} {:>12} {:>12} {:>10} {:>10} {:>8}", "PK", "Offset",
+         "X", "Y", "Bearing", "Distance", "dZ")?;
+      for row in &self.rows {
+         let dz = match row.height_difference {
+            Some(dz) => format!("{:.3}", dz),
+            None => String::from("-")
+         };
+         writeln!(f, "{:>12} {:>8.3} {:>12.3} {:>12.3} {:>10.4} {:>10.3} {:>8}",
+            format_station(row.pk), row.offset, row.point.x, row.point.y, 
+            row.bearing, row.distance, dz)?;
+      }
+      Ok(())
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use crate::eq001;
+   use crate::road::roadaxis::tests::sample_axis;
+   use crate::road::verticalalignment::{Pvi, PviCurve};
+   use crate::road::superelevation::SlopeTransition;
+
+   #[test]
+   fn test_bearing_and_distance() {
+      // Backsight to the North
+      let ps = PolarStakeout::new(Point::new(0.0, 0.0), Point::new(0.0, 100.0), None);
+      let (b, d) = ps.bearing_and_distance(Point::new(10.0, 0.0));
+      assert!(eq001(b, 100.0));
+      assert!(eq001(d, 10.0));
+      let (b, _d) = ps.bearing_and_distance(Point::new(-10.0, 0.0));
+      assert!(eq001(b, 300.0));
+      // Backsight to the East
+      let ps = PolarStakeout::new(Point::new(0.0, 0.0), Point::new(50.0, 0.0), None);
+      let (b, _d) = ps.bearing_and_distance(Point::new(0.0, 10.0));
+      assert!(eq001(b, 300.0));
+      let (b, _d) = ps.bearing_and_distance(Point::new(10.0, -10.0));
+      assert!(eq001(b, 50.0));
+   }
+   #[test]
+   #[should_panic]
+   fn test_new_panic() {
+      let _ps = PolarStakeout::new(Point::new(1.0, 1.0), Point::new(1.0, 1.0), None);
+   }
+   #[test]
+   #[should_panic]
+   fn test_new_panic_tolerance() {
+      let _ps = PolarStakeout::new(Point::new(1.0, 1.0), Point::new(1.0, 1.0005), None);
+   }
+   #[test]
+   fn test_stake_point() {
+      let axis = sample_axis();
+      let ps = PolarStakeout::new(Point::new(990.0, 1050.0), Point::new(990.0, 2000.0), Some(100.0));
+      // Grade of 2% from 92 at 100
+      let pvis = [Pvi::new(100.0, 92.0, PviCurve::None), Pvi::new(400.0, 98.0, PviCurve::None)];
+      let profile = VerticalAlignment::from_pvis(&pvis).unwrap();
+      let row = ps.stake_point(&axis, 150.0, 0.0, Some(&profile), None);
+      assert!(eq001(row.distance, 10.0));
+      assert!(eq001(row.height_difference.unwrap(), -7.0));
+      // Normal crown of 2%: 5 m out of the axis the point is 10 cm lower
+      let mut se = Superelevation::new();
+      se.add_key(0.0, -0.02, -0.02, SlopeTransition::Linear);
+      let row = ps.stake_point(&axis, 150.0, 5.0, Some(&profile), Some(&se));
+      assert!(eq001(row.bearing, 100.0));
+      assert!(eq001(row.distance, 15.0));
+      assert!(eq001(row.height_difference.unwrap(), -7.1));
+      // Superelevation of 4% to the right
+      se.add_key(140.0, 0.04, -0.04, SlopeTransition::Linear);
+      let row = ps.stake_point(&axis, 150.0, -5.0, Some(&profile), Some(&se));
+      assert!(eq001(row.height_difference.unwrap(), -6.8));
+      // Out of the axis without superelevation and out of the profile
+      assert!(ps.stake_point(&axis, 150.0, 5.0, Some(&profile), None).height_difference.is_none());
+      assert!(ps.stake_point(&axis, 450.0, 0.0, Some(&profile), None).height_difference.is_none());
+      let row = ps.stake_point(&axis, 150.0, 0.0, None, None);
+      assert!(row.height_difference.is_none());
+   }
+   #[test]
+   fn test_table() {
+      let axis = sample_axis();
+      let ps = PolarStakeout::new(Point::new(990.0, 1050.0), Point::new(990.0, 2000.0), None);
+      let table = ps.table(&axis, 50.0, &[-3.5, 0.0, 3.5], None, None).unwrap();
+      assert_eq!(table.rows.len(), 13 * 3);
+      assert!(eq001(table.rows[4].pk, 150.0));
+      assert!(eq001(table.rows[4].point.x, 1000.0));
+      assert!(ps.table(&axis, 50.0, &[], None, None).is_err());
+      assert!(ps.table(&axis, -1.0, &[0.0], None, None).is_err());
+      let text = format!("{}", table);
+      assert_eq!(text.lines().count(), 40);
+      assert!(text.lines().nth(1).unwrap().contains("0+100.000"));
+   }
+}
@@ -0,0 +1,20 @@
+//! Modelos del terreno y perfiles del terreno a lo largo del eje
+
+pub mod groundprofile;
+pub mod groundsection;
+pub mod tin;
+
+use crate::geom::point::Point;
+
+/// Terrain model that gives the elevation of the existing ground
+pub trait TerrainModel {
+   /// Elevation of the ground at the point. None if the point is out
+   /// of the area covered by the model
+   fn elevation_at(&self, p: Point) -> Option<f64>;
+   /// Parameters t in (0, 1) of the points where the segment p0-p1
+   /// crosses the edges of the model, where the slope of the ground
+   /// changes. Models with smooth surface return no crossings
+   fn edge_crossings(&self, _p0: Point, _p1: Point) -> Vec<f64> {
+      Vec::new()
+   }
+}
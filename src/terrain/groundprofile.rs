@@ -0,0 +1,153 @@
+//! Perfil longitudinal del terreno a lo largo del eje o de una
+//! línea paralela al eje.
+//!
+//! The ground is sampled at the stations multiple of the interval, at
+//! the tangent points of the axis and where the axis crosses the edges
+//! of the terrain model, so the profile is exact for triangulated models
+use crate::road::roadaxis::RoadAxis;
+use super::TerrainModel;
+
+/// Maximum length of the chords used to find the edge crossings
+/// along curved sections
+const MAX_CHORD: f64 = 2.0;
+
+/// Point of the ground profile. The elevation is None where the
+/// terrain has no coverage
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GroundPoint {
+   pub station: f64,
+   pub elevation: Option<f64>
+}
+
+#[derive(Debug, Clone)]
+pub struct GroundProfile {
+   /// Offset of the sampled line from the axis, positive to the right
+   pub offset: f64,
+   pub points: Vec<GroundPoint>
+}
+
+impl GroundProfile {
+   /// Stretches of the profile with ground, as station/elevation pairs,
+   /// separated by the gaps without coverage
+   pub fn segments(&self) -> Vec<Vec<(f64, f64)>> {
+      let mut segments = Vec::<Vec<(f64, f64)>>::new();
+      let mut current = Vec::<(f64, f64)>::new();
+      for p in &self.points {
+         match p.elevation {
+            Some(z) => current.push((p.station, z)),
+            None => if !current.is_empty() {
+               segments.push(std::mem::take(&mut current));
+            }
+         }
+      }
+      if !current.is_empty() {
+         segments.push(current);
+      }
+      segments
+   }
+   /// Ground elevation at the station, interpolated linearly between
+   /// the points of the profile. None in the gaps and out of the profile
+   pub fn elevation_at(&self, station: f64) -> Option<f64> {
+      let i = self.points.iter().position(|p| p.station >= station)?;
+      let p1 = self.points[i];
+      if p1.station == station {
+         return p1.elevation;
+      }
+      if i == 0 {
+         return None;
+      }
+      let p0 = self.points[i - 1];
+      let (z0, z1) = (p0.elevation?, p1.elevation?);
+      Some(z0 + (z1 - z0) * (station - p0.station) / (p1.station - p0.station))
+   }
+}
+
+/// Ground profile along the line parallel to the axis at the offset,
+/// positive to the right (0.0 for the axis itself)
+pub fn ground_profile(axis: &RoadAxis, terrain: &dyn TerrainModel, interval: f64, 
+   offset: f64) -> Result<GroundProfile, String> {
+   let mut stations = axis.stations_at_interval(interval)?;
+   let mut crossings = Vec::<f64>::new();
+   for w in stations.windows(2) {
+      let n = ((w[1] - w[0]) / MAX_CHORD).ceil().max(1.0) as usize;
+      let step = (w[1] - w[0]) / n as f64;
+      for k in 0..n {
+         let s0 = w[0] + k as f64 * step;
+         let p0 = axis.offset_point_at_station(s0, offset);
+         let p1 = axis.offset_point_at_station(s0 + step, offset);
+         crossings.extend(terrain.edge_crossings(p0, p1).iter().map(|t| s0 + t * step));
+      }
+   }
+   stations.extend(crossings);
+   stations.sort_by(|a, b| a.total_cmp(b));
+   stations.dedup_by(|a, b| (*a - *b).abs() < axis.tolerances.linear);
+   let points = stations.iter().map(|&station| GroundPoint{station, 
+      elevation: terrain.elevation_at(axis.offset_point_at_station(station, offset))}).collect();
+   Ok(GroundProfile{offset, points})
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use crate::eq001;
+   use crate::geom::point::Point;
+   use crate::road::roadaxis::tests::sample_axis;
+
+   /// Terrain with a ridge along the horizontal line y, covering only
+   /// the points with y < 1300
+   struct Ridge {
+      y: f64
+   }
+   impl TerrainModel for Ridge {
+      fn elevation_at(&self, p: Point) -> Option<f64> {
+         if p.y < 1300.0 {
+            Some(500.0 - 0.1 * (p.y - self.y).abs())
+         } else {
+            None
+         }
+      }
+      fn edge_crossings(&self, p0: Point, p1: Point) -> Vec<f64> {
+         let t = (self.y - p0.y) / (p1.y - p0.y);
+         if t > 0.0 && t < 1.0 { vec![t] } else { Vec::new() }
+      }
+   }
+
+   #[test]
+   fn test_ground_profile() {
+      // Sample axis goes toward the North from (1000, 1000) at station 100
+      let axis = sample_axis();
+      let profile = ground_profile(&axis, &Ridge{y: 1100.0}, 30.0, 0.0).unwrap();
+      // The ridge is at station 200, also the end of the first tangent
+      let top = profile.points.iter().find(|p| eq001(p.station, 200.0)).unwrap();
+      assert!(eq001(top.elevation.unwrap(), 500.0));
+      assert!(eq001(profile.elevation_at(150.0).unwrap(), 495.0));
+      assert!(eq001(profile.elevation_at(165.0).unwrap(), 496.5));
+      // Gap at the end of the axis
+      assert!(profile.points.last().unwrap().elevation.is_none());
+      let segments = profile.segments();
+      assert_eq!(segments.len(), 1);
+      assert!(eq001(segments[0][0].0, 100.0));
+   }
+   #[test]
+   fn test_edge_crossings() {
+      // Ridge inside the clothoid, not at a sampled station
+      let axis = sample_axis();
+      let profile = ground_profile(&axis, &Ridge{y: 1123.4}, 50.0, -10.0).unwrap();
+      let top = profile.points.iter().map(|p| p.elevation.unwrap_or(0.0))
+         .fold(f64::MIN, f64::max);
+      assert!(eq001(top, 500.0));
+      assert!(ground_profile(&axis, &Ridge{y: 1100.0}, 0.0, 0.0).is_err());
+   }
+   #[test]
+   fn test_segments() {
+      let points = vec![GroundPoint{station: 0.0, elevation: Some(1.0)},
+         GroundPoint{station: 10.0, elevation: None},
+         GroundPoint{station: 20.0, elevation: Some(2.0)},
+         GroundPoint{station: 30.0, elevation: Some(4.0)}];
+      let profile = GroundProfile{offset: 0.0, points};
+      assert_eq!(profile.segments().len(), 2);
+      assert!(profile.elevation_at(5.0).is_none());
+      assert!(eq001(profile.elevation_at(25.0).unwrap(), 3.0));
+      assert!(profile.elevation_at(35.0).is_none());
+   }
+}
@@ -0,0 +1,153 @@
+//! Perfil transversal del terreno en un PK.
+//!
+//! The ground is cut along the normal to the axis at the station, from
+//! the left width to the right width. It is sampled at the ends, at
+//! the axis, at the offsets multiple of the interval and where the 
+//! section line crosses the edges of the terrain model, so the ground
+//! line is exact for triangulated models
+use crate::road::roadaxis::RoadAxis;
+use super::TerrainModel;
+
+/// Point of the ground line. The elevation is None where the terrain
+/// has no coverage
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GroundSectionPoint {
+   /// Offset from the axis, positive to the right
+   pub offset: f64,
+   pub elevation: Option<f64>
+}
+
+#[derive(Debug, Clone)]
+pub struct GroundSection {
+   pub station: f64,
+   /// Points from left to right
+   pub points: Vec<GroundSectionPoint>
+}
+
+impl GroundSection {
+   /// Ground elevation at the offset, interpolated linearly between
+   /// the points. None in the gaps and out of the section
+   pub fn elevation_at(&self, offset: f64) -> Option<f64> {
+      let i = self.points.iter().position(|p| p.offset >= offset)?;
+      let p1 = self.points[i];
+      if p1.offset == offset {
+         return p1.elevation;
+      }
+      if i == 0 {
+         return None;
+      }
+      let p0 = self.points[i - 1];
+      let (z0, z1) = (p0.elevation?, p1.elevation?);
+      Some(z0 + (z1 - z0) * (offset - p0.offset) / (p1.offset - p0.offset))
+   }
+   /// Points with ground as (offset, elevation) pairs
+   pub fn pairs(&self) -> Vec<(f64, f64)> {
+      self.points.iter().filter_map(|p| p.elevation.map(|z| (p.offset, z))).collect()
+   }
+}
+
+/// Ground line at the station, between left_width to the left of the
+/// axis and right_width to the right, sampled at the offsets multiple
+/// of interval and at the edge crossings of the terrain
+pub fn ground_section(axis: &RoadAxis, terrain: &dyn TerrainModel, station: f64, 
+   left_width: f64, right_width: f64, interval: f64) -> Result<GroundSection, String> {
+   if interval <= 0.0 || axis.tolerances.is_zero_length(interval) {
+      return Err(String::from("Interval must be greater than zero"));
+   }
+   if left_width < 0.0 || right_width < 0.0 || axis.tolerances.is_zero_length(left_width + right_width) {
+      return Err(String::from("Widths can't be negative and the section must have length"));
+   }
+   if !axis.contains_station(station) {
+      return Err(format!("Station {:.3} out of the axis", station));
+   }
+   let mut offsets = vec![-left_width, 0.0, right_width];
+   let mut k = (-left_width / interval).ceil();
+   while k * interval < right_width {
+      offsets.push(k * interval);
+      k += 1.0;
+   }
+   let p0 = axis.offset_point_at_station(station, -left_width);
+   let p1 = axis.offset_point_at_station(station, right_width);
+   let width = left_width + right_width;
+   offsets.extend(terrain.edge_crossings(p0, p1).iter().map(|t| -left_width + t * width));
+   offsets.retain(|o| *o >= -left_width && *o <= right_width);
+   offsets.sort_by(|a, b| a.total_cmp(b));
+   offsets.dedup_by(|a, b| axis.tolerances.eq_linear(*a, *b));
+   let points = offsets.iter().map(|&offset| GroundSectionPoint{offset,
+      elevation: terrain.elevation_at(axis.offset_point_at_station(station, offset))}).collect();
+   Ok(GroundSection{station, points})
+}
+
+/// Ground lines at the stations multiple of station_interval and at the
+/// start of the sections of the axis
+pub fn ground_sections(axis: &RoadAxis, terrain: &dyn TerrainModel, station_interval: f64,
+   left_width: f64, right_width: f64, interval: f64) -> Result<Vec<GroundSection>, String> {
+   axis.stations_at_interval(station_interval)?.iter()
+      .map(|&s| ground_section(axis, terrain, s, left_width, right_width, interval))
+      .collect()
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use crate::eq001;
+   use crate::geom::point::Point;
+   use crate::geom::point3d::Point3D;
+   use crate::road::roadaxis::tests::sample_axis;
+   use crate::terrain::tin::Tin;
+
+   /// TIN of the plane z = 0.1 x on a grid of 10 m around the first
+   /// tangent of the sample axis, from y = 900 to y = 1150
+   fn sample_tin() -> Tin {
+      let mut points = Vec::<Point3D>::new();
+      for i in 0..=20 {
+         for j in 0..=25 {
+            let (x, y) = (900.0 + 10.0 * i as f64, 900.0 + 10.0 * j as f64);
+            points.push(Point3D::new(x, y, 0.1 * x));
+         }
+      }
+      Tin::new(&points).unwrap()
+   }
+
+   #[test]
+   fn test_ground_section() {
+      // Sample axis goes toward the North along x = 1000 until station 200
+      let axis = sample_axis();
+      let tin = sample_tin();
+      let section = ground_section(&axis, &tin, 153.0, 25.0, 25.0, 100.0).unwrap();
+      let offsets: Vec<f64> = section.points.iter().map(|p| p.offset).collect();
+      for o in [-25.0, -20.0, -10.0, 0.0, 10.0, 20.0, 25.0] {
+         assert!(offsets.iter().any(|x| eq001(*x, o)));
+      }
+      // Diagonals of the squares cross the section too
+      assert!(offsets.len() > 7);
+      assert!(offsets.windows(2).all(|w| w[0] < w[1]));
+      for p in &section.points {
+         assert!(eq001(p.elevation.unwrap(), 100.0 + 0.1 * p.offset));
+      }
+      assert!(eq001(section.elevation_at(-12.5).unwrap(), 98.75));
+      assert_eq!(section.pairs().len(), section.points.len());
+   }
+   #[test]
+   fn test_ground_section_gap() {
+      // The TIN ends at x = 1100: no ground at the right end
+      let axis = sample_axis();
+      let tin = sample_tin();
+      let section = ground_section(&axis, &tin, 120.0, 10.0, 120.0, 5.0).unwrap();
+      assert!(section.points.last().unwrap().elevation.is_none());
+      assert!(eq001(section.elevation_at(100.0).unwrap(), 110.0));
+      assert!(section.elevation_at(110.0).is_none());
+      assert!(tin.elevation_at(Point::new(1110.0, 1020.0)).is_none());
+      assert!(ground_section(&axis, &tin, 50.0, 10.0, 10.0, 5.0).is_err());
+      assert!(ground_section(&axis, &tin, 150.0, 10.0, 10.0, 0.0).is_err());
+   }
+   #[test]
+   fn test_ground_sections() {
+      let axis = sample_axis();
+      let sections = ground_sections(&axis, &sample_tin(), 20.0, 10.0, 10.0, 5.0).unwrap();
+      assert_eq!(sections.len(), axis.stations_at_interval(20.0).unwrap().len());
+      assert!(eq001(sections[0].station, 100.0));
+      // Out of the TIN beyond y = 1150
+      assert!(sections.last().unwrap().points.iter().all(|p| p.elevation.is_none()));
+   }
+}
@@ -0,0 +1,795 @@
+//! Modelo digital del terreno por red de triángulos irregulares (TIN).
+//!
+//! The triangulation is built by incremental insertion of the points
+//! inside a large super triangle, with a walk from the last inserted
+//! point to locate each new one and Lawson flips to keep the Delaunay
+//! condition. The points are inserted in a snake order over a grid so
+//! the walks are short. Breaklines are forced into the triangulation
+//! by flipping the edges that cross them, and the remaining edges are
+//! then flipped back to Delaunay without crossing the breaklines.
+//! Breaklines may share vertices but they can't cross each other.
+//!
+//! Queries use a grid index over the triangles. The elevation is
+//! interpolated in the plane of each triangle, so slope and aspect are
+//! constant inside a triangle
+use std::collections::{HashSet, VecDeque};
+use crate::geom::point::Point;
+use crate::geom::point3d::Point3D;
+use crate::tolerances::Tolerances;
+use super::TerrainModel;
+
+/// No neighbour
+const NONE: usize = usize::MAX;
+/// Relative error below which orientation and in circle tests are
+/// taken as zero
+const EPSILON: f64 = 1e-12;
+/// Half size of the super triangle, in times the size of the data
+const SUPER_SIZE: f64 = 1000.0;
+
+pub struct Tin {
+   pub points: Vec<Point3D>,
+   /// Vertex indices of each triangle, counterclockwise
+   triangles: Vec<[usize; 3]>,
+   /// Neighbour across the edge opposite each vertex
+   neighbours: Vec<[usize; 3]>,
+   /// Edges of the breaklines, as (lower, higher) vertex indices
+   breaklines: HashSet<(usize, usize)>,
+   index: GridIndex,
+   pub tolerances: Tolerances
+}
+
+impl Tin {
+   /// Delaunay triangulation of the points. Points closer than the
+   /// linear tolerance to a previous one are discarded
+   pub fn new(points: &[Point3D]) -> Result<Tin, String> {
+      Tin::with_tolerances(points, &[], Tolerances::default())
+   }
+   /// Triangulation of the points with breaklines, polylines whose
+   /// segments are edges of the triangulation. The vertices of the
+   /// breaklines are added to the points. Error if a breakline crosses
+   /// a previous one out of their vertices
+   pub fn with_breaklines(points: &[Point3D], breaklines: &[Vec<Point3D>]) -> Result<Tin, String> {
+      Tin::with_tolerances(points, breaklines, Tolerances::default())
+   }
+   pub fn with_tolerances(points: &[Point3D], breaklines: &[Vec<Point3D>],
+      tolerances: Tolerances) -> Result<Tin, String> {
+      let mut all: Vec<Point3D> = points.to_vec();
+      let mut segments = Vec::<(usize, usize, usize)>::new();
+      for (k, line) in breaklines.iter().enumerate() {
+         let first = all.len();
+         all.extend(line.iter().cloned());
+         segments.extend((first..first + line.len()).zip(first + 1..first + line.len()).map(|(a, b)| (k, a, b)));
+      }
+      if all.len() < 3 {
+         return Err(String::from("A TIN needs at least three points"));
+      }
+      let mut triangulation = Triangulation::new(&all, tolerances.linear)?;
+      let mut map = vec![0; all.len()];
+      for i in triangulation.insertion_order() {
+         map[i] = triangulation.insert(i);
+      }
+      for (k, a, b) in segments {
+         if !triangulation.insert_breakline(map[a], map[b]) {
+            return Err(format!("Breakline {} crosses a previous breakline", k));
+         }
+      }
+      triangulation.restore_delaunay();
+      triangulation.into_tin(&all, tolerances)
+   }
+   /// Number of triangles
+   pub fn len(&self) -> usize {
+      self.triangles.len()
+   }
+   pub fn is_empty(&self) -> bool {
+      self.triangles.is_empty()
+   }
+   /// Vertices of the triangle, counterclockwise
+   pub fn triangle(&self, i: usize) -> [Point3D; 3] {
+      let t = self.triangles[i];
+      [self.points[t[0]], self.points[t[1]], self.points[t[2]]]
+   }
+   /// Vertex indices of the triangle, counterclockwise
+   pub fn triangle_vertices(&self, i: usize) -> [usize; 3] {
+      self.triangles[i]
+   }
+   /// Neighbours of the triangle across the edge opposite each vertex.
+   /// None on the boundary
+   pub fn neighbours(&self, i: usize) -> [Option<usize>; 3] {
+      self.neighbours[i].map(|n| if n == NONE { None } else { Some(n) })
+   }
+   /// True if the edge between the two vertices is part of a breakline
+   pub fn is_breakline_edge(&self, a: usize, b: usize) -> bool {
+      self.breaklines.contains(&(a.min(b), a.max(b)))
+   }
+   /// Triangle that contains the point. None out of the model
+   pub fn triangle_at(&self, p: Point) -> Option<usize> {
+      self.index.candidates(p.x, p.y, p.x, p.y).into_iter()
+         .find(|&t| self.contains(t, p))
+   }
+   fn contains(&self, t: usize, p: Point) -> bool {
+      let [a, b, c] = self.triangle(t);
+      let area = orient(a.x, a.y, b.x, b.y, c.x, c.y);
+      let tol = -EPSILON.sqrt() * area;
+      orient(a.x, a.y, b.x, b.y, p.x, p.y) >= tol && orient(b.x, b.y, c.x, c.y, p.x, p.y) >= tol
+         && orient(c.x, c.y, a.x, a.y, p.x, p.y) >= tol
+   }
+   /// Gradient (dz/dx, dz/dy) of the plane of the triangle
+   fn gradient(&self, t: usize) -> (f64, f64) {
+      let [a, b, c] = self.triangle(t);
+      let (ux, uy, uz) = (b.x - a.x, b.y - a.y, b.z - a.z);
+      let (vx, vy, vz) = (c.x - a.x, c.y - a.y, c.z - a.z);
+      let nz = ux * vy - uy * vx;
+      ((vy * uz - uy * vz) / nz, (ux * vz - vx * uz) / nz)
+   }
+   /// Slope of the ground at the point, as a fraction
+   pub fn slope_at(&self, p: Point) -> Option<f64> {
+      let (gx, gy) = self.gradient(self.triangle_at(p)?);
+      Some(gx.hypot(gy))
+   }
+   /// Aspect of the ground at the point: azimuth in sexagesimal degrees
+   /// of the direction of steepest descent. None out of the model and
+   /// on flat triangles
+   pub fn aspect_at(&self, p: Point) -> Option<f64> {
+      let (gx, gy) = self.gradient(self.triangle_at(p)?);
+      if gx == 0.0 && gy == 0.0 {
+         return None;
+      }
+      Some(crate::normalize_360((-gx).atan2(-gy).to_degrees()))
+   }
+}
+
+impl TerrainModel for Tin {
+   fn elevation_at(&self, p: Point) -> Option<f64> {
+      let t = self.triangle_at(p)?;
+      let a = self.triangle(t)[0];
+      let (gx, gy) = self.gradient(t);
+      Some(a.z + gx * (p.x - a.x) + gy * (p.y - a.y))
+   }
+   fn edge_crossings(&self, p0: Point, p1: Point) -> Vec<f64> {
+      let (dx, dy) = (p1.x - p0.x, p1.y - p0.y);
+      let mut crossings = Vec::<f64>::new();
+      let candidates = self.index.candidates(p0.x.min(p1.x), p0.y.min(p1.y),
+         p0.x.max(p1.x), p0.y.max(p1.y));
+      for t in candidates {
+         let v = self.triangle(t);
+         for i in 0..3 {
+            let (a, b) = (v[i], v[(i + 1) % 3]);
+            let (ex, ey) = (b.x - a.x, b.y - a.y);
+            let det = dx * ey - dy * ex;
+            if det.abs() < EPSILON * (dx.hypot(dy) * ex.hypot(ey)) {
+               continue;
+            }
+            let s = ((a.x - p0.x) * ey - (a.y - p0.y) * ex) / det;
+            let u = ((a.x - p0.x) * dy - (a.y - p0.y) * dx) / det;
+            if s > 0.0 && s < 1.0 && (0.0..=1.0).contains(&u) {
+               crossings.push(s);
+            }
+         }
+      }
+      crossings.sort_by(|a, b| a.total_cmp(b));
+      let length = dx.hypot(dy);
+      crossings.dedup_by(|a, b| (*a - *b) * length < self.tolerances.linear);
+      crossings
+   }
+}
+
+/// Orientation of c relative to the line a-b: positive if a, b, c are
+/// counterclockwise
+fn orient(ax: f64, ay: f64, bx: f64, by: f64, cx: f64, cy: f64) -> f64 {
+   (bx - ax) * (cy - ay) - (by - ay) * (cx - ax)
+}
+
+/// What stops a breakline segment from being forced into the
+/// triangulation
+enum Obstacle {
+   /// Vertex on the segment, which is split there
+   Vertex(usize),
+   /// Previous breakline crossed by the segment
+   Breakline
+}
+
+/// Triangulation under construction, in coordinates relative to the
+/// centre of the points and with the super triangle
+struct Triangulation {
+   xy: Vec<(f64, f64)>,
+   /// Number of input points. The super triangle vertices follow them
+   n: usize,
+   triangles: Vec<[usize; 3]>,
+   neighbours: Vec<[usize; 3]>,
+   /// A triangle of each inserted vertex
+   vertex_triangle: Vec<usize>,
+   breaklines: HashSet<(usize, usize)>,
+   last: usize,
+   tolerance: f64
+}
+
+impl Triangulation {
+   fn new(points: &[Point3D], tolerance: f64) -> Result<Self, String> {
+      let n = points.len();
+      let (mut xmin, mut ymin, mut xmax, mut ymax) = (f64::MAX, f64::MAX, f64::MIN, f64::MIN);
+      for p in points {
+         xmin = xmin.min(p.x);
+         ymin = ymin.min(p.y);
+         xmax = xmax.max(p.x);
+         ymax = ymax.max(p.y);
+      }
+      let size = (xmax - xmin).max(ymax - ymin);
+      if size < tolerance {
+         return Err(String::from("All points are coincident"));
+      }
+      let (cx, cy) = ((xmin + xmax) / 2.0, (ymin + ymax) / 2.0);
+      let mut xy: Vec<(f64, f64)> = points.iter().map(|p| (p.x - cx, p.y - cy)).collect();
+      let m = SUPER_SIZE * size;
+      xy.extend([(-3.0 * m, -3.0 * m), (3.0 * m, -3.0 * m), (0.0, 3.0 * m)]);
+      let mut vertex_triangle = vec![NONE; n + 3];
+      vertex_triangle[n..].fill(0);
+      Ok(Triangulation{xy, n, triangles: vec![[n, n + 1, n + 2]], neighbours: vec![[NONE; 3]],
+         vertex_triangle, breaklines: HashSet::new(), last: 0, tolerance})
+   }
+   /// Indices of the input points in snake order over a grid
+   fn insertion_order(&self) -> Vec<usize> {
+      let k = ((self.n as f64 / 8.0).sqrt().ceil() as usize).max(1);
+      let pts = &self.xy[..self.n];
+      let (xmin, xmax) = pts.iter().fold((f64::MAX, f64::MIN), |(a, b), p| (a.min(p.0), b.max(p.0)));
+      let (ymin, ymax) = pts.iter().fold((f64::MAX, f64::MIN), |(a, b), p| (a.min(p.1), b.max(p.1)));
+      let cell = |v: f64, min: f64, max: f64| {
+         if max > min { (((v - min) / (max - min) * k as f64) as usize).min(k - 1) } else { 0 }
+      };
+      let mut order: Vec<usize> = (0..self.n).collect();
+      order.sort_by_key(|&i| {
+         let (cx, cy) = (cell(pts[i].0, xmin, xmax), cell(pts[i].1, ymin, ymax));
+         cy * k + if cy % 2 == 0 { cx } else { k - 1 - cx }
+      });
+      order
+   }
+   fn orient(&self, a: usize, b: usize, c: usize) -> f64 {
+      let (pa, pb, pc) = (self.xy[a], self.xy[b], self.xy[c]);
+      let det = orient(pa.0, pa.1, pb.0, pb.1, pc.0, pc.1);
+      let bound = ((pb.0 - pa.0) * (pc.1 - pa.1)).abs() + ((pb.1 - pa.1) * (pc.0 - pa.0)).abs();
+      if det.abs() <= EPSILON * bound { 0.0 } else { det }
+   }
+   /// True if d is strictly inside the circumcircle of the
+   /// counterclockwise triangle a, b, c
+   fn in_circle(&self, a: usize, b: usize, c: usize, d: usize) -> bool {
+      let pd = self.xy[d];
+      let [(adx, ady), (bdx, bdy), (cdx, cdy)] = [a, b, c].map(|v| (self.xy[v].0 - pd.0, self.xy[v].1 - pd.1));
+      let (alift, blift, clift) = (adx * adx + ady * ady, bdx * bdx + bdy * bdy, cdx * cdx + cdy * cdy);
+      let det = alift * (bdx * cdy - cdx * bdy) + blift * (cdx * ady - adx * cdy) + clift * (adx * bdy - bdx * ady);
+      let bound = alift * ((bdx * cdy).abs() + (cdx * bdy).abs()) + blift * ((cdx * ady).abs() + (adx * cdy).abs())
+         + clift * ((adx * bdy).abs() + (bdx * ady).abs());
+      det > EPSILON * bound
+   }
+   fn set(&mut self, t: usize, vertices: [usize; 3], neighbours: [usize; 3]) {
+      if t == self.triangles.len() {
+         self.triangles.push(vertices);
+         self.neighbours.push(neighbours);
+      } else {
+         self.triangles[t] = vertices;
+         self.neighbours[t] = neighbours;
+      }
+      for v in vertices {
+         self.vertex_triangle[v] = t;
+      }
+   }
+   /// Changes the neighbour old of triangle t by new
+   fn replace_neighbour(&mut self, t: usize, old: usize, new: usize) {
+      if t == NONE {
+         return;
+      }
+      if let Some(k) = self.neighbours[t].iter().position(|&n| n == old) {
+         self.neighbours[t][k] = new;
+      }
+   }
+   /// Index in triangle t of the vertex v
+   fn position(&self, t: usize, v: usize) -> usize {
+      self.triangles[t].iter().position(|&w| w == v).unwrap()
+   }
+   fn locate(&self, p: usize) -> usize {
+      let mut t = self.last;
+      let mut steps = 0;
+      'walk: loop {
+         for k in 0..3 {
+            let i = (k + steps) % 3;
+            let tri = self.triangles[t];
+            if self.orient(tri[(i + 1) % 3], tri[(i + 2) % 3], p) < 0.0 {
+               t = self.neighbours[t][i];
+               steps += 1;
+               continue 'walk;
+            }
+         }
+         return t;
+      }
+   }
+   /// Inserts the point and returns its vertex index, or the index of
+   /// the vertex that it duplicates
+   fn insert(&mut self, p: usize) -> usize {
+      let t = self.locate(p);
+      let near = std::iter::once(t).chain(self.neighbours[t].iter().cloned().filter(|&n| n != NONE));
+      for s in near.collect::<Vec<usize>>() {
+         for v in self.triangles[s] {
+            let (a, b) = (self.xy[v], self.xy[p]);
+            if v < self.n && (a.0 - b.0).hypot(a.1 - b.1) < self.tolerance {
+               return v;
+            }
+         }
+      }
+      let tri = self.triangles[t];
+      let on_edge = (0..3).find(|&i| self.orient(tri[(i + 1) % 3], tri[(i + 2) % 3], p) == 0.0);
+      let mut stack = match on_edge {
+         Some(i) => self.split_edge(t, i, p),
+         None => self.split_triangle(t, p)
+      };
+      while let Some(t) = stack.pop() {
+         let u = self.neighbours[t][0];
+         if u == NONE {
+            continue;
+         }
+         let [p, v1, v2] = self.triangles[t];
+         let j = self.neighbours[u].iter().position(|&n| n == t).unwrap();
+         if self.in_circle(p, v1, v2, self.triangles[u][j]) {
+            self.flip(t, 0);
+            stack.push(t);
+            stack.push(u);
+         }
+      }
+      self.last = t;
+      p
+   }
+   /// Splits triangle t in three with the point p, and returns the new
+   /// triangles, all of them with p as first vertex
+   fn split_triangle(&mut self, t: usize, p: usize) -> Vec<usize> {
+      let [a, b, c] = self.triangles[t];
+      let [na, nb, nc] = self.neighbours[t];
+      let (t1, t2) = (self.triangles.len(), self.triangles.len() + 1);
+      self.set(t, [p, b, c], [na, t1, t2]);
+      self.set(t1, [p, c, a], [nb, t2, t]);
+      self.set(t2, [p, a, b], [nc, t, t1]);
+      self.replace_neighbour(nb, t, t1);
+      self.replace_neighbour(nc, t, t2);
+      vec![t, t1, t2]
+   }
+   /// Splits the edge opposite vertex i of triangle t with the point p
+   fn split_edge(&mut self, t: usize, i: usize, p: usize) -> Vec<usize> {
+      let tri = self.triangles[t];
+      let (a, b, c) = (tri[i], tri[(i + 1) % 3], tri[(i + 2) % 3]);
+      let (nb, nc) = (self.neighbours[t][(i + 1) % 3], self.neighbours[t][(i + 2) % 3]);
+      let u = self.neighbours[t][i];
+      let j = self.neighbours[u].iter().position(|&n| n == t).unwrap();
+      let q = self.triangles[u][j];
+      let (ub, uc) = (self.neighbours[u][(j + 1) % 3], self.neighbours[u][(j + 2) % 3]);
+      let (t1, u1) = (self.triangles.len(), self.triangles.len() + 1);
+      self.set(t, [p, c, a], [nb, t1, u1]);
+      self.set(t1, [p, a, b], [nc, u, t]);
+      self.set(u, [p, b, q], [ub, u1, t1]);
+      self.set(u1, [p, q, c], [uc, t, u]);
+      self.replace_neighbour(nc, t, t1);
+      self.replace_neighbour(uc, u, u1);
+      vec![t, t1, u, u1]
+   }
+   /// Flips the edge opposite vertex i of triangle t. The vertex is the
+   /// first one of both new triangles
+   fn flip(&mut self, t: usize, i: usize) {
+      let tri = self.triangles[t];
+      let (v0, v1, v2) = (tri[i], tri[(i + 1) % 3], tri[(i + 2) % 3]);
+      let (a1, a2) = (self.neighbours[t][(i + 1) % 3], self.neighbours[t][(i + 2) % 3]);
+      let u = self.neighbours[t][i];
+      let j = self.neighbours[u].iter().position(|&n| n == t).unwrap();
+      let q = self.triangles[u][j];
+      let (b1, b2) = (self.neighbours[u][(j + 1) % 3], self.neighbours[u][(j + 2) % 3]);
+      self.set(t, [v0, v1, q], [b1, u, a2]);
+      self.set(u, [v0, q, v2], [b2, a1, t]);
+      self.replace_neighbour(b1, u, t);
+      self.replace_neighbour(a1, t, u);
+   }
+   /// Triangle with the edge a-b and index of the vertex opposite it
+   fn find_edge(&self, a: usize, b: usize) -> Option<(usize, usize)> {
+      let start = self.vertex_triangle[a];
+      let mut t = start;
+      loop {
+         let k = self.position(t, a);
+         let tri = self.triangles[t];
+         if tri[(k + 1) % 3] == b {
+            return Some((t, (k + 2) % 3));
+         }
+         if tri[(k + 2) % 3] == b {
+            return Some((t, (k + 1) % 3));
+         }
+         t = self.neighbours[t][(k + 1) % 3];
+         if t == start || t == NONE {
+            return None;
+         }
+      }
+   }
+   fn is_breakline(&self, a: usize, b: usize) -> bool {
+      self.breaklines.contains(&(a.min(b), a.max(b)))
+   }
+   /// Edges crossed by the segment a-b, or the obstacle found on it
+   fn crossed_edges(&self, a: usize, b: usize) -> Result<Vec<(usize, usize)>, Obstacle> {
+      let along = |v: usize| {
+         let (pa, pb, pv) = (self.xy[a], self.xy[b], self.xy[v]);
+         (pv.0 - pa.0) * (pb.0 - pa.0) + (pv.1 - pa.1) * (pb.1 - pa.1) > 0.0
+      };
+      // Triangle around a crossed by the segment
+      let start = self.vertex_triangle[a];
+      let mut t = start;
+      let (mut right, mut left) = loop {
+         let k = self.position(t, a);
+         let tri = self.triangles[t];
+         let (v1, v2) = (tri[(k + 1) % 3], tri[(k + 2) % 3]);
+         let (o1, o2) = (self.orient(a, b, v1), self.orient(a, b, v2));
+         if o1 == 0.0 && along(v1) {
+            return Err(Obstacle::Vertex(v1));
+         }
+         if o2 == 0.0 && along(v2) {
+            return Err(Obstacle::Vertex(v2));
+         }
+         if o1 < 0.0 && o2 > 0.0 {
+            break (v1, v2);
+         }
+         t = self.neighbours[t][(k + 1) % 3];
+         assert!(t != start, "Breakline segment not found around its vertex");
+      };
+      let mut edges = Vec::<(usize, usize)>::new();
+      loop {
+         if self.is_breakline(right, left) {
+            return Err(Obstacle::Breakline);
+         }
+         edges.push((right, left));
+         let i = (0..3).find(|&i| self.triangles[t][i] != right && self.triangles[t][i] != left).unwrap();
+         let u = self.neighbours[t][i];
+         let j = self.neighbours[u].iter().position(|&n| n == t).unwrap();
+         let q = self.triangles[u][j];
+         if q == b {
+            return Ok(edges);
+         }
+         let o = self.orient(a, b, q);
+         if o == 0.0 {
+            return Err(Obstacle::Vertex(q));
+         }
+         if o < 0.0 { right = q } else { left = q }
+         t = u;
+      }
+   }
+   /// Forces the segment a-b as an edge of the triangulation. False if
+   /// it crosses a previous breakline, which is left as it is
+   fn insert_breakline(&mut self, a: usize, b: usize) -> bool {
+      if a == b {
+         return true;
+      }
+      if self.find_edge(a, b).is_none() {
+         let mut queue: VecDeque<(usize, usize)> = match self.crossed_edges(a, b) {
+            Ok(edges) => edges.into_iter().collect(),
+            Err(Obstacle::Vertex(c)) => return self.insert_breakline(a, c) && self.insert_breakline(c, b),
+            Err(Obstacle::Breakline) => return false
+         };
+         while let Some((x, y)) = queue.pop_front() {
+            let (t, i) = self.find_edge(x, y).unwrap();
+            let u = self.neighbours[t][i];
+            let p = self.triangles[t][i];
+            let j = self.neighbours[u].iter().position(|&n| n == t).unwrap();
+            let q = self.triangles[u][j];
+            debug_assert!(!self.is_breakline(x, y), "Flip of a breakline edge");
+            if self.orient(p, q, x) * self.orient(p, q, y) < 0.0 {
+               self.flip(t, i);
+               let crosses = self.orient(a, b, p) * self.orient(a, b, q) < 0.0
+                  && self.orient(p, q, a) * self.orient(p, q, b) < 0.0;
+               if crosses {
+                  queue.push_back((p, q));
+               }
+            } else {
+               queue.push_back((x, y));
+            }
+         }
+      }
+      self.breaklines.insert((a.min(b), a.max(b)));
+      true
+   }
+   /// Flips the edges that aren't Delaunay and aren't breaklines
+   fn restore_delaunay(&mut self) {
+      if self.breaklines.is_empty() {
+         return;
+      }
+      loop {
+         let mut flips = 0;
+         for t in 0..self.triangles.len() {
+            for i in 0..3 {
+               let u = self.neighbours[t][i];
+               if u == NONE {
+                  continue;
+               }
+               let tri = self.triangles[t];
+               let (p, v1, v2) = (tri[i], tri[(i + 1) % 3], tri[(i + 2) % 3]);
+               if self.is_breakline(v1, v2) {
+                  continue;
+               }
+               let j = self.neighbours[u].iter().position(|&n| n == t).unwrap();
+               let q = self.triangles[u][j];
+               if self.in_circle(p, v1, v2, q) && self.orient(p, q, v1) * self.orient(p, q, v2) < 0.0 {
+                  self.flip(t, i);
+                  flips += 1;
+               }
+            }
+         }
+         if flips == 0 {
+            break;
+         }
+      }
+   }
+   /// Tin without the super triangle and the discarded points
+   fn into_tin(self, points: &[Point3D], tolerances: Tolerances) -> Result<Tin, String> {
+      let keep: Vec<bool> = self.triangles.iter().map(|t| t.iter().all(|&v| v < self.n)).collect();
+      let mut new_triangle = vec![NONE; self.triangles.len()];
+      let mut count = 0;
+      for (t, k) in keep.iter().enumerate() {
+         if *k {
+            new_triangle[t] = count;
+            count += 1;
+         }
+      }
+      if count == 0 {
+         return Err(String::from("Points are collinear"));
+      }
+      let mut new_vertex = vec![NONE; self.n];
+      let mut tin_points = Vec::<Point3D>::new();
+      let mut triangles = Vec::<[usize; 3]>::with_capacity(count);
+      let mut neighbours = Vec::<[usize; 3]>::with_capacity(count);
+      for (t, tri) in self.triangles.iter().enumerate() {
+         if !keep[t] {
+            continue;
+         }
+         triangles.push(tri.map(|v| {
+            if new_vertex[v] == NONE {
+               new_vertex[v] = tin_points.len();
+               tin_points.push(points[v]);
+            }
+            new_vertex[v]
+         }));
+         neighbours.push(self.neighbours[t].map(|n| if n == NONE { NONE } else { new_triangle[n] }));
+      }
+      let breaklines = self.breaklines.iter()
+         .map(|&(a, b)| (new_vertex[a].min(new_vertex[b]), new_vertex[a].max(new_vertex[b]))).collect();
+      let index = GridIndex::new(&tin_points, &triangles);
+      Ok(Tin{points: tin_points, triangles, neighbours, breaklines, index, tolerances})
+   }
+}
+
+/// Regular grid over the area of the model with the triangles whose
+/// bounding box overlaps each cell
+struct GridIndex {
+   x0: f64,
+   y0: f64,
+   cell: f64,
+   nx: usize,
+   ny: usize,
+   cells: Vec<Vec<usize>>
+}
+
+impl GridIndex {
+   fn new(points: &[Point3D], triangles: &[[usize; 3]]) -> Self {
+      let (mut xmin, mut ymin, mut xmax, mut ymax) = (f64::MAX, f64::MAX, f64::MIN, f64::MIN);
+      for p in points {
+         xmin = xmin.min(p.x);
+         ymin = ymin.min(p.y);
+         xmax = xmax.max(p.x);
+         ymax = ymax.max(p.y);
+      }
+      let (w, h) = ((xmax - xmin).max(f64::MIN_POSITIVE), (ymax - ymin).max(f64::MIN_POSITIVE));
+      let cell = (w * h / triangles.len() as f64).sqrt().max(w.max(h) / 4096.0);
+      let nx = ((w / cell).ceil() as usize).max(1);
+      let ny = ((h / cell).ceil() as usize).max(1);
+      let mut index = GridIndex{x0: xmin, y0: ymin, cell, nx, ny, cells: vec![Vec::new(); nx * ny]};
+      for (t, tri) in triangles.iter().enumerate() {
+         let xs = tri.map(|v| points[v].x);
+         let ys = tri.map(|v| points[v].y);
+         let (i0, j0, i1, j1) = index.range(xs.iter().cloned().fold(f64::MAX, f64::min),
+            ys.iter().cloned().fold(f64::MAX, f64::min), xs.iter().cloned().fold(f64::MIN, f64::max),
+            ys.iter().cloned().fold(f64::MIN, f64::max)).unwrap();
+         for j in j0..=j1 {
+            for i in i0..=i1 {
+               index.cells[j * nx + i].push(t);
+            }
+         }
+      }
+      index
+   }
+   /// Range of cells (i0, j0, i1, j1) that overlap the box. None if it
+   /// is out of the grid
+   fn range(&self, xmin: f64, ymin: f64, xmax: f64, ymax: f64) -> Option<(usize, usize, usize, usize)> {
+      let (w, h) = (self.nx as f64 * self.cell, self.ny as f64 * self.cell);
+      if xmax < self.x0 || ymax < self.y0 || xmin > self.x0 + w || ymin > self.y0 + h {
+         return None;
+      }
+      let i = |x: f64| (((x - self.x0) / self.cell).max(0.0) as usize).min(self.nx - 1);
+      let j = |y: f64| (((y - self.y0) / self.cell).max(0.0) as usize).min(self.ny - 1);
+      Some((i(xmin), j(ymin), i(xmax), j(ymax)))
+   }
+   /// Triangles that may overlap the box, without repetitions
+   fn candidates(&self, xmin: f64, ymin: f64, xmax: f64, ymax: f64) -> Vec<usize> {
+      let mut triangles = Vec::<usize>::new();
+      if let Some((i0, j0, i1, j1)) = self.range(xmin, ymin, xmax, ymax) {
+         for j in j0..=j1 {
+            for i in i0..=i1 {
+               triangles.extend(&self.cells[j * self.nx + i]);
+            }
+         }
+         if i0 != i1 || j0 != j1 {
+            triangles.sort_unstable();
+            triangles.dedup();
+         }
+      }
+      triangles
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use crate::{eq, eq001};
+
+   /// Pseudorandom points in [0, size] x [0, size] with elevation f(x, y)
+   fn random_points(n: usize, size: f64, f: impl Fn(f64, f64) -> f64) -> Vec<Point3D> {
+      let mut seed: u64 = 12345;
+      let mut next = || {
+         seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+         (seed >> 11) as f64 / (1u64 << 53) as f64 * size
+      };
+      (0..n).map(|_| {
+         let (x, y) = (next(), next());
+         Point3D::new(x, y, f(x, y))
+      }).collect()
+   }
+   fn grid_points(n: usize, step: f64, f: impl Fn(f64, f64) -> f64) -> Vec<Point3D> {
+      let mut points = Vec::<Point3D>::new();
+      for i in 0..n {
+         for j in 0..n {
+            let (x, y) = (500000.0 + i as f64 * step, 4400000.0 + j as f64 * step);
+            points.push(Point3D::new(x, y, f(x, y)));
+         }
+      }
+      points
+   }
+   /// Checks the neighbours and that no vertex is inside the
+   /// circumcircle of a triangle, except across breaklines
+   fn check_delaunay(tin: &Tin) {
+      for t in 0..tin.len() {
+         let [a, b, c] = tin.triangle(t);
+         assert!(orient(a.x, a.y, b.x, b.y, c.x, c.y) > 0.0);
+         for (i, n) in tin.neighbours(t).iter().enumerate() {
+            let Some(u) = *n else { continue };
+            assert!(tin.neighbours(u).contains(&Some(t)));
+            let tri = tin.triangle_vertices(t);
+            if tin.is_breakline_edge(tri[(i + 1) % 3], tri[(i + 2) % 3]) {
+               continue;
+            }
+            let q = tin.triangle_vertices(u).into_iter().find(|v| !tri.contains(v)).unwrap();
+            let d = tin.points[q];
+            let m = |p: Point3D| (p.x - d.x, p.y - d.y, (p.x - d.x).powi(2) + (p.y - d.y).powi(2));
+            let ((ax, ay, al), (bx, by, bl), (cx, cy, cl)) = (m(a), m(b), m(c));
+            let det = al * (bx * cy - cx * by) + bl * (cx * ay - ax * cy) + cl * (ax * by - bx * ay);
+            assert!(det <= 1e-6 * (al * bl * cl).cbrt().powi(2), "Edge of triangle {} isn't Delaunay", t);
+         }
+      }
+   }
+
+   #[test]
+   fn test_plane() {
+      let plane = |x: f64, y: f64| 100.0 + 0.1 * (x - 500000.0) + 0.05 * (y - 4400000.0);
+      let tin = Tin::new(&grid_points(11, 10.0, plane)).unwrap();
+      // Two triangles by square
+      assert_eq!(tin.len(), 200);
+      check_delaunay(&tin);
+      for (x, y) in [(500003.3, 4400007.1), (500050.0, 4400050.0), (500099.9, 4400000.1)] {
+         assert!(eq001(tin.elevation_at(Point::new(x, y)).unwrap(), plane(x, y)));
+      }
+      let p = Point::new(500012.0, 4400031.0);
+      assert!(eq(tin.slope_at(p).unwrap(), 0.1_f64.hypot(0.05)));
+      assert!(eq001(tin.aspect_at(p).unwrap(), 180.0 + 0.1_f64.atan2(0.05).to_degrees()));
+      assert!(tin.elevation_at(Point::new(499999.0, 4400050.0)).is_none());
+      assert!(tin.triangle_at(Point::new(500150.0, 4400050.0)).is_none());
+   }
+   #[test]
+   fn test_random_points() {
+      let n = 2000;
+      let tin = Tin::new(&random_points(n, 1000.0, |x, y| x * y / 1000.0)).unwrap();
+      check_delaunay(&tin);
+      // T = 2 n - 2 - h, with h the vertices of the convex hull
+      let hull = (0..tin.len()).map(|t| tin.neighbours(t).iter().filter(|n| n.is_none()).count()).sum::<usize>();
+      assert_eq!(tin.len(), 2 * n - 2 - hull);
+      for p in tin.points.iter().step_by(97) {
+         assert!(eq001(tin.elevation_at(Point::new(p.x, p.y)).unwrap(), p.z));
+      }
+   }
+   #[test]
+   fn test_duplicates_and_errors() {
+      let mut points = grid_points(3, 10.0, |_, _| 0.0);
+      points.push(Point3D::new(500010.0002, 4400010.0, 5.0));
+      let tin = Tin::new(&points).unwrap();
+      assert_eq!(tin.points.len(), 9);
+      assert_eq!(tin.len(), 8);
+      assert!(Tin::new(&points[..2]).is_err());
+      let collinear: Vec<Point3D> = (0..5).map(|i| Point3D::new(i as f64, 2.0 * i as f64, 0.0)).collect();
+      assert!(Tin::new(&collinear).is_err());
+   }
+   #[test]
+   fn test_breakline() {
+      // Delaunay takes the short diagonal B-D; the breakline forces A-C
+      let points = [Point3D::new(10.0, -1.0, 0.0), Point3D::new(10.0, 1.0, 0.0)];
+      let ridge = vec![Point3D::new(0.0, 0.0, 0.0), Point3D::new(20.0, 0.0, 10.0)];
+      let mut all = points.to_vec();
+      all.extend(ridge.iter().cloned());
+      let tin = Tin::new(&all).unwrap();
+      assert!(eq001(tin.elevation_at(Point::new(10.0, 0.0)).unwrap(), 0.0));
+      let tin = Tin::with_breaklines(&points, &[ridge]).unwrap();
+      assert!(eq001(tin.elevation_at(Point::new(10.0, 0.0)).unwrap(), 5.0));
+      assert!(eq001(tin.elevation_at(Point::new(15.0, 0.0)).unwrap(), 7.5));
+   }
+   #[test]
+   fn test_breaklines_in_cloud() {
+      let points = random_points(500, 100.0, |_, _| 0.0);
+      // Second breakline passes through a vertex of the first one
+      let lines = vec![
+         vec![Point3D::new(1.0, 1.0, 5.0), Point3D::new(50.0, 40.0, 5.0), Point3D::new(99.0, 99.0, 5.0)],
+         vec![Point3D::new(50.0, 0.5, 2.0), Point3D::new(50.0, 99.5, 2.0)]];
+      let tin = Tin::with_breaklines(&points, &lines).unwrap();
+      check_delaunay(&tin);
+      let vertex = |p: &Point3D| tin.points.iter().position(|q| q.x == p.x && q.y == p.y).unwrap();
+      let (a, b) = (vertex(&lines[0][0]), vertex(&lines[0][1]));
+      assert!(tin.is_breakline_edge(a, b));
+      assert!((0..tin.len()).any(|t| {
+         let v = tin.triangle_vertices(t);
+         v.contains(&a) && v.contains(&b)
+      }));
+      // Breakline at x = 50 is split at (50, 40)
+      let c = vertex(&lines[1][0]);
+      assert!(tin.is_breakline_edge(c, b));
+      // Area of the TIN doesn't change with the breaklines
+      let area = |tin: &Tin| (0..tin.len()).map(|t| {
+         let [a, b, c] = tin.triangle(t);
+         orient(a.x, a.y, b.x, b.y, c.x, c.y) / 2.0
+      }).sum::<f64>();
+      let mut all = points.clone();
+      all.extend(lines.iter().flatten().cloned());
+      assert!(eq001(area(&tin), area(&Tin::new(&all).unwrap())));
+      // Elevation along the breakline
+      assert!(eq001(tin.elevation_at(Point::new(25.5, 20.5)).unwrap(), 5.0));
+   }
+   #[test]
+   fn test_crossing_breaklines() {
+      let points = grid_points(11, 10.0, |_, _| 0.0);
+      let first = vec![Point3D::new(500012.0, 4400013.0, 1.0), Point3D::new(500087.0, 4400081.0, 1.0)];
+      let second = vec![Point3D::new(500011.0, 4400083.0, 2.0), Point3D::new(500089.0, 4400017.0, 2.0)];
+      assert!(Tin::with_breaklines(&points, &[first.clone(), second.clone()]).is_err());
+      // Sharing a vertex they are valid
+      let second = vec![Point3D::new(500011.0, 4400083.0, 2.0), first[1], Point3D::new(500089.0, 4400017.0, 2.0)];
+      let tin = Tin::with_breaklines(&points, &[first.clone(), second]).unwrap();
+      check_delaunay(&tin);
+      let vertex = |p: &Point3D| tin.points.iter().position(|q| q.x == p.x && q.y == p.y).unwrap();
+      let (a, b) = (vertex(&first[0]), vertex(&first[1]));
+      assert!(tin.is_breakline_edge(a, b));
+      assert!((0..tin.len()).any(|t| {
+         let v = tin.triangle_vertices(t);
+         v.contains(&a) && v.contains(&b)
+      }));
+   }
+   #[test]
+   fn test_edge_crossings() {
+      let tin = Tin::new(&grid_points(11, 1.0, |_, _| 0.0)).unwrap();
+      // Vertical lines at 1, 2 and 3 and the diagonals of the squares
+      // at 1.5 and 2.5
+      let crossings = tin.edge_crossings(Point::new(500000.5, 4400000.5), Point::new(500003.5, 4400000.5));
+      assert_eq!(crossings.len(), 5);
+      for (t, expected) in crossings.iter().zip([1.0, 2.0, 3.0, 4.0, 5.0]) {
+         assert!(eq(*t, expected / 6.0));
+      }
+   }
+   #[test]
+   fn test_many_points() {
+      let n = 50000;
+      let points = random_points(n, 5000.0, |x, y| (x / 100.0).sin() * 10.0 + y / 50.0);
+      let tin = Tin::new(&points).unwrap();
+      assert!(tin.len() > 2 * n - 200);
+      for p in points.iter().step_by(1009) {
+         assert!(eq001(tin.elevation_at(Point::new(p.x, p.y)).unwrap(), p.z));
+      }
+   }
+}
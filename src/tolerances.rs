@@ -0,0 +1,111 @@
+/// Tolerances used in validation, intersection and
+/// degenerate-case checks.
+///
+/// Every value is expressed in engineering units:
+///
+/// **linear:** metres. Lengths, distances and coordinates whose
+/// difference is below it are considered equal. Default 0.001 m (1 mm)\
+/// **angular:** radians. Directions whose difference is below it are
+/// considered parallel. Default 1e-6 rad (about 0.06 mgon, 0.2")\
+/// **radius:** metres. A radius whose absolute value is below it is
+/// considered zero, that is, infinite radius. Default 0.001 m
+///
+/// The tolerances can be set for a whole axis (`RoadAxis::tolerances`)
+/// or passed to a single operation through the `*_with_tolerances`
+/// variants of constructors and geometric operations.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tolerances {
+   pub linear: f64,
+   pub angular: f64,
+   pub radius: f64
+}
+
+/// Default linear tolerance: 1 mm
+pub const DEFAULT_LINEAR_TOLERANCE: f64 = 0.001;
+/// Default angular tolerance: 1e-6 radians
+pub const DEFAULT_ANGULAR_TOLERANCE: f64 = 1e-6;
+/// Default radius tolerance: 1 mm
+pub const DEFAULT_RADIUS_TOLERANCE: f64 = 0.001;
+
+impl Tolerances {
+   /// Creates a new set of tolerances. All of them must be
+   /// greater than zero
+   pub fn new(linear: f64, angular: f64, radius: f64) -> Self {
+      if linear <= 0.0 || angular <= 0.0 || radius <= 0.0 {
+         panic!("Tolerances must be greater than zero");
+      }
+      Tolerances{linear, angular, radius}
+   }
+   /// True if the two lengths differ less than the linear tolerance
+   pub fn eq_linear(&self, x: f64, y: f64) -> bool {
+      (x-y).abs() < self.linear
+   }
+   /// True if the two angles in radians differ less than
+   /// the angular tolerance
+   pub fn eq_angular(&self, x: f64, y: f64) -> bool {
+      (x-y).abs() < self.angular
+   }
+   /// True if the two radius differ less than the radius tolerance
+   pub fn eq_radius(&self, x: f64, y: f64) -> bool {
+      (x-y).abs() < self.radius
+   }
+   /// True if the length is zero within the linear tolerance
+   pub fn is_zero_length(&self, length: f64) -> bool {
+      self.eq_linear(length, 0.0)
+   }
+   /// True if the radius must be taken as zero (infinite radius)
+   pub fn is_zero_radius(&self, radius: f64) -> bool {
+      self.eq_radius(radius, 0.0)
+   }
+}
+
+impl Default for Tolerances {
+   fn default() -> Self {
+      Tolerances {
+         linear: DEFAULT_LINEAR_TOLERANCE,
+         angular: DEFAULT_ANGULAR_TOLERANCE,
+         radius: DEFAULT_RADIUS_TOLERANCE
+      }
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+
+   #[test]
+   fn test_default() {
+      let tol = Tolerances::default();
+      assert_eq!(tol.linear, 0.001);
+      assert_eq!(tol.angular, 1e-6);
+      assert_eq!(tol.radius, 0.001);
+   }
+   #[test]
+   #[should_panic]
+   fn test_new_panic() {
+      let _tol = Tolerances::new(0.001, 0.0, 0.001);
+   }
+   #[test]
+   fn test_eq_linear() {
+      let tol = Tolerances::new(0.01, 1e-6, 0.001);
+      assert!(tol.eq_linear(1.0, 1.005));
+      assert!(!tol.eq_linear(1.0, 1.02));
+      assert!(tol.is_zero_length(-0.005));
+   }
+   #[test]
+   fn test_eq_angular() {
+      let tol = Tolerances::default();
+      assert!(tol.eq_angular(1.0, 1.0 + 5e-7));
+      assert!(!tol.eq_angular(1.0, 1.0 + 5e-6));
+   }
+   #[test]
+   fn test_is_zero_radius() {
+      let tol = Tolerances::default();
+      assert!(tol.is_zero_radius(0.0));
+      assert!(tol.is_zero_radius(-0.0005));
+      assert!(!tol.is_zero_radius(0.01));
+      let tol = Tolerances::new(0.001, 1e-6, 0.1);
+      assert!(tol.is_zero_radius(0.01));
+      assert!(tol.eq_radius(450.0, 450.05));
+   }
+}